@@ -0,0 +1,26 @@
+//! Generate typed entity structs from a `.fgd` file.
+//!
+//! Usage: `fgd_codegen <path/to/game.fgd> [classname]... > src/source/game.rs`
+//!
+//! All point classes are generated if no classnames are given. Run `rustfmt` on the output.
+
+use source_map_gen::source::{codegen, Fgd};
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let Some(path) = args.next() else {
+        eprintln!("Usage: fgd_codegen <path/to/game.fgd> [classname]...");
+        std::process::exit(1);
+    };
+    let classnames: Vec<String> = args.collect();
+    let classnames: Vec<&str> = classnames.iter().map(String::as_str).collect();
+
+    let fgd = match Fgd::load(&path) {
+        Ok(fgd) => fgd,
+        Err(err) => {
+            eprintln!("Failed to load {path}: {err}");
+            std::process::exit(1);
+        }
+    };
+    print!("{}", codegen(&fgd, &classnames));
+}
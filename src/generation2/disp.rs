@@ -1,5 +1,8 @@
-use crate::map::DispInfo;
+use crate::generation2::noise::{Fractal, Noise};
+use crate::generation2::shape::cube;
+use crate::map::{DispInfo, Material, Solid};
 use crate::utils::Vec2d;
+use std::collections::HashMap;
 
 use super::*;
 
@@ -8,6 +11,7 @@ use super::*;
 ///
 /// Basicly the walkablity of the traingle. shown by Hammer with the
 /// "Display walkable area" button (DW).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum TriangleTag {
     // about 45 point smth
     /// > A value of "0" means it has a large slope in the z-axis and the player
@@ -20,6 +24,31 @@ pub enum TriangleTag {
     Flat = 9,
 }
 
+impl TriangleTag {
+    /// Least Z of a triangle's normal that the player can walk on, about 45.6 degrees.
+    /// Same as Source's `PLAYER_MAX_SLOPE` / Hammer's walkable threshold.
+    pub const WALKABLE_NORMAL_Z: f32 = 0.7;
+    /// Least Z of a triangle's normal for it to be flat, about 36.9 degrees.
+    /// Same as Hammer's buildable threshold.
+    pub const FLAT_NORMAL_Z: f32 = 0.8;
+
+    /// The tag of a triangle from its normalized normal.
+    pub fn from_normal(normal: &Vector3<f32>) -> Self {
+        if normal.z >= Self::FLAT_NORMAL_Z {
+            Self::Flat
+        } else if normal.z >= Self::WALKABLE_NORMAL_Z {
+            Self::Steep
+        } else {
+            Self::Unwalkable
+        }
+    }
+
+    /// Can a player walk on it. `Steep` or `Flat`.
+    pub const fn is_walkable(self) -> bool {
+        !matches!(self, Self::Unwalkable)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, PartialOrd)]
 pub struct Displacement {
     /// Number of points in 1 dimension
@@ -31,9 +60,18 @@ pub struct Displacement {
     pub normals: Vec2d<Vector3<f32>>,
     pub distances: Vec2d<f32>,
     pub alphas: Vec2d<f32>,
+    /// One bit per point, same order as [`ideal_points()`]. Cleared for points on an
+    /// edge shared with a lower power displacement that it doesn't share a point with.
+    /// See [`sew()`].
+    ///
+    /// [`ideal_points()`]: Self::ideal_points()
+    pub allowed_verts: [i32; 10],
 }
 
 impl Displacement {
+    /// All points allowed.
+    pub const ALL_VERTS_ALLOWED: [i32; 10] = [-1; 10];
+
     pub const fn new(corners: [Vector3<f32>; 4], width: usize) -> Self {
         // inlining corners leads to move errors O_o
         let [bl, tl, tr, br] = corners;
@@ -44,6 +82,7 @@ impl Displacement {
             normals: Vec2d::new(Vec2d::strides(width)),
             distances: Vec2d::new(Vec2d::strides(width)),
             alphas: Vec2d::new(Vec2d::strides(width)),
+            allowed_verts: Self::ALL_VERTS_ALLOWED,
         }
     }
 
@@ -56,6 +95,7 @@ impl Displacement {
             normals: Vec2d::new(Vec2d::strides(width)),
             distances: Vec2d::new(Vec2d::strides(width)),
             alphas: Vec2d::new(Vec2d::strides(width)),
+            allowed_verts: Self::ALL_VERTS_ALLOWED,
         }
     }
 
@@ -130,6 +170,42 @@ impl Displacement {
         points
     }
 
+    /// The actual position of each point, the [`ideal_points()`] moved by `normals`
+    /// times `distances`. Same order as [`ideal_points()`].
+    ///
+    /// [`ideal_points()`]: Self::ideal_points()
+    pub fn points(&self) -> Vec2d<Vector3<f32>> {
+        let mut points = self.ideal_points();
+        let offsets = self.normals.inner.iter().zip(self.distances.inner.iter());
+        for (point, (normal, distance)) in points.inner.iter_mut().zip(offsets) {
+            *point += &(normal.clone() * *distance);
+        }
+        points
+    }
+
+    /// Set `normals` and `distances` so each point is moved along the plane's
+    /// normal by `height`, a function of each of the [`ideal_points()`].
+    /// Ex: noise sampled at the world position, which makes neighboring
+    /// displacements match at their edges.
+    ///
+    /// [`ideal_points()`]: Self::ideal_points()
+    pub fn set_heights(&mut self, mut height: impl FnMut(&Vector3<f32>) -> f32) {
+        let normal = self.plane.normal();
+        let ideal = self.ideal_points();
+        let len = ideal.inner.len();
+        let (mut normals, mut distances) = (Vec::with_capacity(len), Vec::with_capacity(len));
+        for point in ideal.inner.iter() {
+            let height = height(point);
+            normals.push(normal.clone() * height.signum());
+            distances.push(height.abs());
+        }
+        self.normals = Vec2d::from_parts(normals, Vec2d::strides(self.width));
+        self.distances = Vec2d::from_parts(distances, Vec2d::strides(self.width));
+        if self.alphas.inner.len() != len {
+            self.alphas = Vec2d::from_parts(vec![0.0; len], Vec2d::strides(self.width));
+        }
+    }
+
     /// 0,0,0
     #[inline]
     fn offsets(&self) -> Vec2d<Vector3<f32>> {
@@ -142,27 +218,284 @@ impl Displacement {
         Vec2d::from_parts(vec![self.plane.normal(); self.width * self.width], [self.width, 1])
     }
 
-    // TODO: actually calculate walkablity
-    // <https://developer.valvesoftware.com/wiki/.vmf#triangle_tags>
-    /// 0, walkable
-    fn triangle_tags(&self) -> Vec2d<i32> {
+    /// The triangles of each square of points, two per square.
+    /// Split along alternating diagonals in a checkerboard like Source does.
+    /// Ordered the same as the triangle tags, left to right then bottom to top.
+    ///
+    /// Returns the indexes (row, column) of the points of each triangle, counter
+    /// clockwise when looking at the front of the displacement.
+    fn triangles(&self) -> impl Iterator<Item = [[usize; 2]; 3]> {
+        let squares = self.width.saturating_sub(1);
+        (0..squares).flat_map(move |y| {
+            (0..squares).flat_map(move |x| {
+                let [p00, p10, p01, p11] = [[y, x], [y, x + 1], [y + 1, x], [y + 1, x + 1]];
+                if (x + y) % 2 == 0 {
+                    [[p00, p10, p11], [p00, p11, p01]]
+                } else {
+                    [[p00, p10, p01], [p10, p11, p01]]
+                }
+            })
+        })
+    }
+
+    /// The [`TriangleTag`] of each triangle, from the displaced points.
+    /// Width is 2 per square of points.
+    ///
+    /// See <https://developer.valvesoftware.com/wiki/.vmf#triangle_tags>
+    pub fn triangle_tags(&self) -> Vec2d<TriangleTag> {
         // 5 -> 4x8
         // 9 -> 8x16
         // 17 -> 16x32
-        let height = self.width - 1;
-        let width = height * 2; // x2 cuz 2 trianlges in 1 square
+        let width = self.width.saturating_sub(1) * 2; // x2 cuz 2 trianlges in 1 square
+        let points = self.points();
+        let tags = self.triangles().map(|[a, b, c]| {
+            let (a, b, c) = (&points[a], &points[b], &points[c]);
+            let normal = (b.clone() - a).cross(&(c.clone() - a)).normalize();
+            TriangleTag::from_normal(&normal)
+        });
+        Vec2d::from_parts(tags.collect(), Vec2d::strides(width))
+    }
 
-        let total = width * height;
-        let value = TriangleTag::Unwalkable as i32;
+    /// The normal of each of the [`points()`], the average of the triangles around it.
+    ///
+    /// [`points()`]: Self::points()
+    pub fn point_normals(&self) -> Vec2d<Vector3<f32>> {
+        let points = self.points();
+        let mut normals = Vec2d::from_parts(
+            vec![Vector3::origin(); self.width * self.width],
+            Vec2d::strides(self.width),
+        );
+        for triangle in self.triangles() {
+            let [a, b, c] = triangle.map(|index| &points[index]);
+            let normal = (b.clone() - a).cross(&(c.clone() - a)).normalize();
+            for index in triangle {
+                normals[index] += &normal;
+            }
+        }
+        for normal in normals.inner.iter_mut() {
+            normal.normalize_mut();
+        }
+        normals
+    }
 
-        Vec2d::from_parts(vec![value; total], [width, 1])
+    /// Paint the alpha of each point by a closure of its displaced position and
+    /// normal. Alphas are 0 to 255, 0 being the first texture of a blend material
+    /// and 255 the second. See [`terrain::paint()`].
+    ///
+    /// [`terrain::paint()`]: crate::generation2::terrain::paint
+    pub fn paint_with(
+        &mut self, mut alpha: impl FnMut(&Vector3<f32>, &Vector3<f32>) -> f32,
+    ) -> &mut Self {
+        let points = self.points();
+        let normals = self.point_normals();
+        let alphas = points.inner.iter().zip(normals.inner.iter());
+        let alphas = alphas.map(|(point, normal)| alpha(point, normal).clamp(0.0, 255.0));
+        self.alphas = Vec2d::from_parts(alphas.collect(), Vec2d::strides(self.width));
+        self
     }
 
-    /// All always allowed. As far as I can tell, this is only a small optimization
-    /// for two displacments of differing power to save like 5 triangles.
-    #[inline]
-    const fn allowed_verts() -> [i32; 10] {
-        [-1; 10]
+    /// Paint by height, 0 at `heights.start` to 255 at `heights.end`.
+    /// Ex: grass in valleys and rock on peaks. Reverse the range for the opposite.
+    pub fn paint_by_height(&mut self, heights: Range<f32>) -> &mut Self {
+        self.paint_with(|point, _| 255.0 * unlerp(&heights, point.z))
+    }
+
+    /// Paint by slope in degrees, 0 at `degrees.start` to 255 at `degrees.end`.
+    /// Ex: `30.0..45.0` for grass on flat ground and dirt on steep slopes.
+    pub fn paint_by_slope(&mut self, degrees: Range<f32>) -> &mut Self {
+        self.paint_with(|_, normal| {
+            let slope = normal.z.clamp(-1.0, 1.0).acos().to_degrees();
+            255.0 * unlerp(&degrees, slope)
+        })
+    }
+
+    /// Paint by noise at the world position, 0 where the noise is -1 and 255 where it is 1.
+    pub fn paint_by_noise(&mut self, noise: &Noise, fractal: &Fractal) -> &mut Self {
+        self.paint_with(|point, _| 255.0 * (noise.fbm(point.x, point.y, fractal) + 1.0) / 2.0)
+    }
+
+    /// Paint by the distance to a path on the XY plane (ignoring height), 255 within
+    /// `dists.start` units of the path fading to 0 at `dists.end`. Ex: a dirt road.
+    ///
+    /// # Panics
+    /// Debug asserts `path` is not empty.
+    pub fn paint_by_path(&mut self, path: &[Vector3<f32>], dists: Range<f32>) -> &mut Self {
+        debug_assert!(!path.is_empty(), "Empty path");
+        let path: Vec<_> = path.iter().map(|point| point.clone().into_vector2()).collect();
+        self.paint_with(|point, _| {
+            let point = point.clone().into_vector2();
+            let dist = match path.as_slice() {
+                [only] => point.dist(only),
+                path => path
+                    .windows(2)
+                    .map(|segment| dist_to_segment(&point, &segment[0], &segment[1]))
+                    .fold(f32::MAX, f32::min),
+            };
+            255.0 * (1.0 - unlerp(&dists, dist))
+        })
+    }
+
+    /// [`Self::triangle_tags()`] as their VMF values.
+    fn triangle_tags_i32(&self) -> Vec2d<i32> {
+        let tags = self.triangle_tags();
+        Vec2d::from_parts(tags.inner.into_iter().map(|tag| tag as i32).collect(), tags.strides)
+    }
+
+    /// Which points can be walked on, all the triangles that touch it are walkable.
+    /// Ex: for placing spawns or props only on walkable terrain.
+    /// Same order as [`ideal_points()`].
+    ///
+    /// [`ideal_points()`]: Self::ideal_points()
+    pub fn walkable_mask(&self) -> Vec2d<bool> {
+        let mut mask =
+            Vec2d::from_parts(vec![true; self.width * self.width], Vec2d::strides(self.width));
+        let tags = self.triangle_tags();
+        for (triangle, tag) in self.triangles().zip(tags.inner) {
+            if !tag.is_walkable() {
+                for point in triangle {
+                    mask[point] = false;
+                }
+            }
+        }
+        mask
+    }
+
+    /// Is the point at `index` (row, column) allowed. See [`Self::allowed_verts`].
+    pub const fn is_vert_allowed(&self, index: [usize; 2]) -> bool {
+        let bit = index[0] * self.width + index[1];
+        self.allowed_verts[bit / 32] & (1 << (bit % 32)) != 0
+    }
+
+    /// Allow or disallow the point at `index` (row, column). See [`Self::allowed_verts`].
+    pub const fn set_vert_allowed(&mut self, index: [usize; 2], allowed: bool) -> &mut Self {
+        let bit = index[0] * self.width + index[1];
+        let mask = 1 << (bit % 32);
+        if allowed {
+            self.allowed_verts[bit / 32] |= mask;
+        } else {
+            self.allowed_verts[bit / 32] &= !mask;
+        }
+        self
+    }
+
+    /// Set `normals` and `distances` so the points are at `points`.
+    /// Points that are not moved get the plane's normal and a distance of 0.
+    fn set_points(&mut self, points: &Vec2d<Vector3<f32>>) {
+        let normal = self.plane.normal();
+        let ideal = self.ideal_points();
+        let (normals, distances) = ideal
+            .inner
+            .iter()
+            .zip(points.inner.iter())
+            .map(|(ideal, point)| match ideal.dir_and_dist(point) {
+                (_, dist) if dist < 1e-4 => (normal.clone(), 0.0),
+                dir_and_dist => dir_and_dist,
+            })
+            .unzip();
+        self.normals = Vec2d::from_parts(normals, Vec2d::strides(self.width));
+        self.distances = Vec2d::from_parts(distances, Vec2d::strides(self.width));
+    }
+
+    /// Change the number of points to [`power_to_len(power)`], keeping the shape.
+    /// `power` is clamped to 2..=4, like vbsp.
+    /// The offsets of the points and `alphas` are bilinearly resampled, bicubic resampling isn't
+    /// supported as it can overshoot the original points.
+    /// Resets [`Self::allowed_verts`].
+    ///
+    /// [`power_to_len(power)`]: Self::power_to_len()
+    pub fn resample(&mut self, mut power: u32) -> &mut Self {
+        if power < 2 {
+            eprintln!("[resample()] power clamped to 2");
+            power = 2;
+        } else if power > 4 {
+            eprintln!("[resample()] power clamped to 4");
+            power = 4;
+        }
+        let offsets = self.point_offsets();
+        let alphas = self.alphas_or_zeros();
+        let width = Self::power_to_len(power);
+        let max = (width - 1) as f32;
+        let uvs: Vec<_> = (0..width * width)
+            .map(|i| ((i % width) as f32 / max, (i / width) as f32 / max))
+            .collect();
+
+        self.width = width;
+        self.set_samples(&offsets, &alphas, &uvs);
+        self.allowed_verts = Self::ALL_VERTS_ALLOWED;
+        self
+    }
+
+    /// Split into four displacements with the same power, each a quarter of `self`.
+    /// Their shared edges match. In the order: `bottom_left`, `top_left`, `top_right`,
+    /// `bottom_right`, same as [`Self::corners()`].
+    ///
+    /// Each needs its own brush, ex: splitting the brush in half along both axes.
+    pub fn subdivide(&self) -> [Self; 4] {
+        let offsets = self.point_offsets();
+        let alphas = self.alphas_or_zeros();
+        let [bl, tl, tr, br] = self.corners();
+        // position on the plane, u from left to right, v from bottom to top
+        let corner = |u: f32, v: f32| bl.lerp(br, u).lerp(&tl.lerp(tr, u), v);
+        let max = (self.width - 1) as f32;
+
+        [(0.0, 0.0), (0.0, 0.5), (0.5, 0.5), (0.5, 0.0)].map(|(u, v)| {
+            let corners =
+                [corner(u, v), corner(u, v + 0.5), corner(u + 0.5, v + 0.5), corner(u + 0.5, v)];
+            let mut disp = Self::new(corners, self.width);
+            let uvs: Vec<_> = (0..self.width * self.width)
+                .map(|i| {
+                    let (x, y) = ((i % self.width) as f32, (i / self.width) as f32);
+                    (u + x / max / 2.0, v + y / max / 2.0)
+                })
+                .collect();
+            disp.set_samples(&offsets, &alphas, &uvs);
+            disp
+        })
+    }
+
+    /// How far each point is moved from the [`ideal_points()`].
+    ///
+    /// [`ideal_points()`]: Self::ideal_points()
+    fn point_offsets(&self) -> Vec2d<Vector3<f32>> {
+        let mut offsets = self.points();
+        for (offset, ideal) in offsets.inner.iter_mut().zip(self.ideal_points().inner) {
+            *offset -= &ideal;
+        }
+        offsets
+    }
+
+    /// `alphas`, or all 0 if they haven't been set.
+    fn alphas_or_zeros(&self) -> Vec2d<f32> {
+        match self.alphas.inner.len() == self.width * self.width {
+            true => self.alphas.clone(),
+            false => Vec2d::from_parts(vec![0.0; self.width * self.width], self.alphas.strides),
+        }
+    }
+
+    /// Set the points and alphas by sampling `offsets` and `alphas` of another
+    /// displacement at `uvs`, one per point. `(0, 0)` is the bottom left and `(1, 1)`
+    /// the top right.
+    fn set_samples(
+        &mut self, offsets: &Vec2d<Vector3<f32>>, alphas: &Vec2d<f32>, uvs: &[(f32, f32)],
+    ) {
+        let mut points = self.ideal_points();
+        for (point, &(u, v)) in points.inner.iter_mut().zip(uvs) {
+            *point += &bilinear(offsets, u, v, |a, b, t| a.lerp(b, t));
+        }
+        self.set_points(&points);
+        let alphas = uvs.iter().map(|&(u, v)| bilinear(alphas, u, v, |&a, &b, t| lerp(a, b, t)));
+        self.alphas = Vec2d::from_parts(alphas.collect(), Vec2d::strides(self.width));
+    }
+
+    /// The indexes (row, column) of the points along each of the four edges, in order.
+    fn edges(&self) -> [Vec<[usize; 2]>; 4] {
+        let last = self.width.saturating_sub(1);
+        [
+            (0..self.width).map(|x| [0, x]).collect(),
+            (0..self.width).map(|x| [last, x]).collect(),
+            (0..self.width).map(|y| [y, 0]).collect(),
+            (0..self.width).map(|y| [y, last]).collect(),
+        ]
     }
 
     pub fn into_disp_info(self) -> DispInfo {
@@ -170,8 +503,8 @@ impl Displacement {
             // annoying move errors solved by order O_o
             offsets: self.offsets(),
             offset_normals: self.offset_normals(),
-            triangle_tags: self.triangle_tags(),
-            allowed_verts: Self::allowed_verts(),
+            triangle_tags: self.triangle_tags_i32(),
+            allowed_verts: self.allowed_verts,
 
             power: Displacement::len_to_power(self.width),
             start_position: self.plane.bottom_left,
@@ -190,8 +523,9 @@ impl Displacement {
         let normals = disp_info.normals;
         let distances = disp_info.distances;
         let alphas = disp_info.alphas;
+        let allowed_verts = disp_info.allowed_verts;
 
-        Self { width: len, plane, bottom_right, normals, distances, alphas }
+        Self { width: len, plane, bottom_right, normals, distances, alphas, allowed_verts }
     }
 
     // fn project_unit_cube_to_sphere(&mut self) {
@@ -203,6 +537,169 @@ impl Displacement {
     // }
 }
 
+/// Sew together the edges of all the [`Displacement`]s in `solids` so there are no
+/// cracks between them, like Hammer's Sew tool. Ex: after generating them separately.
+///
+/// Points on shared edges and corners are moved to the average of their positions.
+/// Points on an edge shared with a lower power displacement with no matching point
+/// are moved onto that displacement's edge and disallowed in
+/// [`Displacement::allowed_verts`].
+pub fn sew(solids: &mut [Solid]) {
+    // positions are compared on a 1/16 unit grid
+    let key = |point: &Vector3<f32>| [point.x, point.y, point.z].map(|n| (n * 16.0).round() as i64);
+
+    let mut disps: Vec<&mut Displacement> = solids
+        .iter_mut()
+        .flat_map(|solid| solid.sides.iter_mut())
+        .filter_map(|side| side.disp.as_mut())
+        .collect();
+    let ideals: Vec<_> = disps.iter().map(|disp| disp.ideal_points()).collect();
+    let mut points: Vec<_> = disps.iter().map(|disp| disp.points()).collect();
+    let edges: Vec<_> = disps.iter().map(|disp| disp.edges()).collect();
+
+    // every edge point of every displacement, grouped by where it is
+    let mut shared: HashMap<[i64; 3], Vec<(usize, [usize; 2])>> = HashMap::new();
+    for (i, disp_edges) in edges.iter().enumerate() {
+        for &index in disp_edges.iter().flatten() {
+            let group = shared.entry(key(&ideals[i][index])).or_default();
+            // corners are on two edges
+            if !group.contains(&(i, index)) {
+                group.push((i, index));
+            }
+        }
+    }
+
+    // average shared points
+    for group in shared.values().filter(|group| group.len() > 1) {
+        let mut average = Vector3::origin();
+        for &(i, index) in group {
+            average += &points[i][index];
+        }
+        let average = average / group.len() as f32;
+        for &(i, index) in group {
+            points[i][index] = average.clone();
+        }
+    }
+
+    // move unshared points onto the edge of a lower power neighbor
+    for (i, disp_edges) in edges.iter().enumerate() {
+        for &index in disp_edges.iter().flatten() {
+            if shared[&key(&ideals[i][index])].len() > 1 {
+                continue;
+            }
+            let ideal = &ideals[i][index];
+            let on_edge =
+                edges.iter().enumerate().filter(|&(j, _)| j != i).find_map(|(j, other)| {
+                    other.iter().flat_map(|edge| edge.windows(2)).find_map(|segment| {
+                        let (a, b) = (&ideals[j][segment[0]], &ideals[j][segment[1]]);
+                        let t = segment_t(ideal, a, b)?;
+                        Some(points[j][segment[0]].lerp(&points[j][segment[1]], t))
+                    })
+                });
+            if let Some(point) = on_edge {
+                points[i][index] = point;
+                disps[i].set_vert_allowed(index, false);
+            }
+        }
+    }
+
+    for (disp, points) in disps.iter_mut().zip(points.iter()) {
+        disp.set_points(points);
+    }
+}
+
+/// A sphere of six displacements with `radius` around `center`. Ex: smooth rocks
+/// and boulders with one brush instead of many like [`sphere_globe()`].
+/// `power` is clamped to 2..=4.
+///
+/// [`sphere_globe()`]: crate::generation2::shape::sphere_globe
+pub fn sphere<'a>(
+    center: &Vector3<f32>, radius: f32, power: u32, mats: [&'a Material<'a>; 1],
+    options: &'a SolidOptions,
+) -> Solid<'a> {
+    let offset = Vector3::new(radius, radius, radius);
+    let bounds = Bounds::new(center.clone() - &offset, center.clone() + &offset);
+    projected_cube(&bounds, power, mats, options, project_cube_to_sphere)
+}
+
+/// A box with rounded edges and corners of six displacements, a superellipsoid
+/// filling `bounds`. `exponent` is how boxy it is, 2 is an ellipsoid and higher
+/// gets closer to a box. Ex: 4 for a worn stone block. `power` is clamped to 2..=4.
+///
+/// See <https://en.wikipedia.org/wiki/Superellipsoid>
+pub fn rounded_box<'a>(
+    bounds: &Bounds, exponent: f32, power: u32, mats: [&'a Material<'a>; 1],
+    options: &'a SolidOptions,
+) -> Solid<'a> {
+    projected_cube(bounds, power, mats, options, |unit| {
+        // evenly spaced directions, then scaled to where |x|^n + |y|^n + |z|^n = 1
+        let dir = project_cube_to_sphere(unit);
+        let sum: f32 = [dir.x, dir.y, dir.z].iter().map(|n| n.abs().powf(exponent)).sum();
+        dir * sum.powf(-1.0 / exponent)
+    })
+}
+
+/// A cube filling `bounds` with a displacement on each side, each point moved to
+/// `project` of its position on a unit cube (-1 to 1), scaled back to `bounds`.
+pub(crate) fn projected_cube<'a>(
+    bounds: &Bounds, mut power: u32, mats: [&'a Material<'a>; 1], options: &'a SolidOptions,
+    project: impl Fn(&Vector3<f32>) -> Vector3<f32>,
+) -> Solid<'a> {
+    if power < 2 {
+        // NOTE: hammer seems to support power 1 displacements O_O
+        // bsp seems to definitely not support it
+        eprintln!("[projected_cube()] power clamped to 2");
+        power = 2;
+    } else if power > 4 {
+        eprintln!("[projected_cube()] power clamped to 4");
+        power = 4;
+    };
+    let len = Displacement::power_to_len(power);
+
+    let mut cube = cube(bounds, &[mats[0]; 6], options);
+    for side in cube.sides.iter_mut() {
+        let mut disp = Displacement::new_plane(side.plane.clone(), len);
+        let mut points = disp.ideal_points();
+        for point in points.inner.iter_mut() {
+            *point = bounds.unit_to_point(&project(&bounds.point_to_unit(point)));
+        }
+        disp.set_points(&points);
+        disp.alphas = Vec2d::from_parts(vec![0.0; len * len], Vec2d::strides(len));
+        side.disp = Some(disp);
+    }
+    cube
+}
+
+/// Bilinearly sample a square `grid` at `u` from left to right and `v` from bottom
+/// to top, both 0 to 1.
+fn bilinear<T>(grid: &Vec2d<T>, u: f32, v: f32, lerp: impl Fn(&T, &T, f32) -> T) -> T {
+    let width = grid.strides[0];
+    let max = width.saturating_sub(1);
+    let (x, y) = (u.clamp(0.0, 1.0) * max as f32, v.clamp(0.0, 1.0) * max as f32);
+    let (x0, y0) =
+        ((x as usize).min(max.saturating_sub(1)), (y as usize).min(max.saturating_sub(1)));
+    let (x1, y1) = ((x0 + 1).min(max), (y0 + 1).min(max));
+    let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+    let bottom = lerp(&grid[[y0, x0]], &grid[[y0, x1]], tx);
+    let top = lerp(&grid[[y1, x0]], &grid[[y1, x1]], tx);
+    lerp(&bottom, &top, ty)
+}
+
+/// How far `point` is along the segment from `a` to `b`, if it is on it
+/// and not at either end.
+fn segment_t(point: &Vector3<f32>, a: &Vector3<f32>, b: &Vector3<f32>) -> Option<f32> {
+    const EPSILON: f32 = 1.0 / 16.0;
+    let ab = b.clone() - a;
+    let len = ab.magnitude();
+    if len < EPSILON {
+        return None;
+    }
+    let t = (point.clone() - a).dot(&ab) / (len * len);
+    let closest = a.clone() + &(ab * t);
+    let on_segment = t * len > EPSILON && (1.0 - t) * len > EPSILON;
+    (on_segment && closest.dist(point) < EPSILON).then_some(t)
+}
+
 /// `a` when `t` is 0. `b` when `t` is 1. extrapolates not 0..=1 (I THINK).
 /// See <https://en.wikipedia.org/wiki/Linear_interpolation>
 pub(crate) fn lerp(a: f32, b: f32, t: f32) -> f32 {
@@ -222,10 +719,32 @@ pub(crate) fn lerp(a: f32, b: f32, t: f32) -> f32 {
     (1.0 - t) * a + t * b
 }
 
+/// 0 at `range.start` to 1 at `range.end`, clamped. Ranges can be reversed.
+fn unlerp(range: &Range<f32>, value: f32) -> f32 {
+    let len = range.end - range.start;
+    if len == 0.0 {
+        return (value >= range.end) as u8 as f32;
+    }
+    ((value - range.start) / len).clamp(0.0, 1.0)
+}
+
+/// Distance from `point` to the closest point on the line segment from `a` to `b`.
+fn dist_to_segment(point: &Vector2<f32>, a: &Vector2<f32>, b: &Vector2<f32>) -> f32 {
+    let ab = b.clone() - a;
+    let len_2 = ab.x * ab.x + ab.y * ab.y;
+    if len_2 == 0.0 {
+        return point.dist(a);
+    }
+    let ap = point.clone() - a;
+    let t = ((ap.x * ab.x + ap.y * ab.y) / len_2).clamp(0.0, 1.0);
+    point.dist(&(a.clone() + &(ab * t)))
+}
+
 /// Project a point on a unit cube (-1 to 1) a point on a unit sphere.
+/// Used by [`sphere()`].
 /// 
 /// See also: <http://mathproofs.blogspot.com/2005/07/mapping-cube-to-sphere.html>
-pub(crate) fn project_cube_to_sphere(point: &Vector3<f32>) -> Vector3<f32> {
+pub fn project_cube_to_sphere(point: &Vector3<f32>) -> Vector3<f32> {
     let Vector3 { x, y, z } = point;
     let x_2 = x * x;
     let y_2 = y * y;
@@ -319,6 +838,7 @@ mod tests {
             normals: Vec2d::new([0, 1]),
             distances: Vec2d::new([0, 1]),
             alphas: Vec2d::new([0, 1]),
+            allowed_verts: Displacement::ALL_VERTS_ALLOWED,
         };
 
         let output = input.ideal_points();
@@ -326,6 +846,242 @@ mod tests {
         assert_eq!(truth, output);
     }
 
+    #[test]
+    fn triangle_tags() {
+        // top of a 64x64 brush, rising to the east
+        let plane = Plane::new(
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 64.0, 0.0),
+            Vector3::new(64.0, 64.0, 0.0),
+        );
+        let tags_with_slope = |slope: f32| {
+            let mut disp = Displacement::new_plane(plane.clone(), 5);
+            disp.set_heights(|point| point.x * slope);
+            disp.triangle_tags()
+        };
+
+        let flat = tags_with_slope(0.0);
+        assert_eq!(8, flat.strides[0]);
+        assert_eq!(4 * 8, flat.inner.len());
+        assert!(flat.inner.iter().all(|&tag| tag == TriangleTag::Flat));
+        // 40 degrees
+        assert!(tags_with_slope(0.84).inner.iter().all(|&tag| tag == TriangleTag::Steep));
+        // 60 degrees
+        assert!(tags_with_slope(1.73).inner.iter().all(|&tag| tag == TriangleTag::Unwalkable));
+
+        // a cliff in the middle
+        let mut disp = Displacement::new_plane(plane, 5);
+        disp.set_heights(|point| if point.x > 32.0 { 64.0 } else { 0.0 });
+        let mask = disp.walkable_mask();
+        let info = disp.into_disp_info();
+        assert!(info.triangle_tags.inner.contains(&(TriangleTag::Flat as i32)));
+        assert!(info.triangle_tags.inner.contains(&(TriangleTag::Unwalkable as i32)));
+        for row in mask.rows() {
+            assert_eq!([true, true, false, false, true], row);
+        }
+    }
+
+    #[test]
+    fn paint() {
+        let plane = Plane::new(
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 64.0, 0.0),
+            Vector3::new(64.0, 64.0, 0.0),
+        );
+        let mut disp = Displacement::new_plane(plane, 5);
+        // flat west half, 45 degrees up to the east
+        disp.set_heights(|point| (point.x - 32.0).max(0.0));
+
+        disp.paint_by_height(0.0..32.0);
+        assert_eq!([0.0, 0.0, 0.0, 127.5, 255.0], disp.alphas.rows().next().unwrap());
+
+        disp.paint_by_slope(30.0..40.0);
+        let row = disp.alphas.rows().next().unwrap().to_vec();
+        assert_eq!([0.0, 0.0], row[..2]);
+        assert_eq!([255.0, 255.0], row[3..]);
+
+        // road along the west edge
+        let path = [Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 64.0, 0.0)];
+        disp.paint_by_path(&path, 16.0..32.0);
+        for row in disp.alphas.rows() {
+            assert_eq!([255.0, 255.0, 0.0, 0.0, 0.0], row);
+        }
+
+        let noise = Noise::new(3);
+        disp.paint_by_noise(&noise, &Fractal::new().frequency(1.0 / 16.0));
+        assert!(disp.alphas.inner.iter().all(|alpha| (0.0..=255.0).contains(alpha)));
+        assert!(disp.alphas.inner.iter().any(|&alpha| alpha != 127.5));
+    }
+
+    #[test]
+    fn sew_grid() {
+        use crate::generation2::terrain::from_heightmap;
+        use crate::map::{Map, Material};
+
+        let mat = Material::new("DEV/DEV_MEASUREWALL01C".into());
+        let nodraw = Material::NODRAW;
+        let options = &SolidOptions::default().allow_frac();
+        let heights = Vec2d::from_parts(vec![0.0; 9 * 9], Vec2d::strides(9));
+        // 2x2 power 2 tiles. south west, south east, north west, north east
+        let mut map = Map {
+            solids: from_heightmap(&heights, 256.0, 2, [&mat, &nodraw], options),
+            ..Default::default()
+        };
+        let fractal = Fractal::new().frequency(1.0 / 128.0);
+        // cracks everywhere, different noise on each tile
+        for (seed, solid) in map.solids.iter_mut().enumerate() {
+            let noise = Noise::new(seed as u64);
+            let disp = solid.sides[0].disp.as_mut().unwrap();
+            disp.set_heights(|point| noise.fbm(point.x, point.y, &fractal) * 64.0);
+        }
+        let disp = |map: &Map, i: usize| map.solids[i].sides[0].disp.clone().unwrap();
+        assert_ne!(disp(&map, 0).points()[[0, 4]], disp(&map, 1).points()[[0, 0]]);
+        let corner = disp(&map, 0).points()[[0, 0]].clone();
+
+        map.sew_displacements();
+        let points: Vec<_> = (0..4).map(|i| disp(&map, i).points()).collect();
+        let close = |a: &Vector3<f32>, b: &Vector3<f32>| a.dist(b) < 0.01;
+        for i in 0..5 {
+            assert!(close(&points[0][[i, 4]], &points[1][[i, 0]]));
+            assert!(close(&points[2][[i, 4]], &points[3][[i, 0]]));
+            assert!(close(&points[0][[4, i]], &points[2][[0, i]]));
+            assert!(close(&points[1][[4, i]], &points[3][[0, i]]));
+        }
+        // middle corner is shared by all 4
+        let middle = &points[0][[4, 4]];
+        assert!([&points[1][[4, 0]], &points[2][[0, 4]], &points[3][[0, 0]]]
+            .iter()
+            .all(|point| close(middle, point)));
+        // corner of the grid isn't shared, untouched
+        assert_eq!(points[0][[0, 0]], corner);
+        assert!(disp(&map, 0).allowed_verts == Displacement::ALL_VERTS_ALLOWED);
+
+        // power 3 north east tile next to power 2 tiles
+        let plane = map.solids[3].sides[0].plane.clone();
+        let mut high = Displacement::new_plane(plane, 9);
+        let noise = Noise::new(7);
+        high.set_heights(|point| noise.fbm(point.x, point.y, &fractal) * 64.0);
+        map.solids[3].sides[0].disp = Some(high);
+        map.sew_displacements();
+
+        let west = disp(&map, 2).points();
+        let high = disp(&map, 3);
+        let high_points = high.points();
+        for y in 0..9 {
+            let point = &high_points[[y, 0]];
+            if y % 2 == 0 {
+                assert!(close(point, &west[[y / 2, 4]]));
+                assert!(high.is_vert_allowed([y, 0]));
+            } else {
+                // T-junction, moved onto the lower power edge
+                let between = west[[y / 2, 4]].lerp(&west[[y / 2 + 1, 4]], 0.5);
+                assert!(close(point, &between));
+                assert!(!high.is_vert_allowed([y, 0]));
+            }
+        }
+        assert!(high.is_vert_allowed([1, 1]));
+    }
+
+    #[test]
+    fn resample_subdivide() {
+        let plane = Plane::new(
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 64.0, 0.0),
+            Vector3::new(64.0, 64.0, 0.0),
+        );
+        let height = |point: &Vector3<f32>| point.x * 0.5 - point.y * 0.25;
+        let close = |a: f32, b: f32| (a - b).abs() < 0.01;
+        let mut disp = Displacement::new_plane(plane, 5);
+        disp.set_heights(height);
+        disp.paint_by_height(-16.0..32.0);
+        let original = disp.clone();
+
+        // planes are resampled exactly
+        disp.resample(3);
+        assert_eq!(9, disp.width);
+        assert_eq!(81, disp.normals.inner.len());
+        for point in disp.points().inner {
+            assert!(close(height(&point), point.z), "{point:?}");
+        }
+        assert!(close(127.5, disp.alphas[[4, 4]]));
+        disp.resample(2);
+        for (a, b) in disp.points().inner.iter().zip(original.points().inner.iter()) {
+            assert!(a.dist(b) < 0.01);
+        }
+
+        let [bl, tl, tr, br] = original.subdivide();
+        assert_eq!(Vector3::new(32.0, 32.0, 0.0), tr.plane.bottom_left);
+        assert_eq!(Vector3::new(64.0, 0.0, 0.0), br.bottom_right);
+        let points = [&bl, &tl, &tr, &br].map(|disp| disp.points());
+        for point in points.iter().flat_map(|points| points.inner.iter()) {
+            assert!(close(height(point), point.z), "{point:?}");
+        }
+        for i in 0..5 {
+            let same = |a: &Vector3<f32>, b: &Vector3<f32>| a.dist(b) < 0.01;
+            // left and right halves
+            assert!(same(&points[0][[i, 4]], &points[3][[i, 0]]));
+            assert!(same(&points[1][[i, 4]], &points[2][[i, 0]]));
+            // bottom and top halves
+            assert!(same(&points[0][[4, i]], &points[1][[0, i]]));
+            assert!(same(&points[3][[4, i]], &points[2][[0, i]]));
+        }
+    }
+
+    #[test]
+    fn resample_clamped() {
+        let plane = Plane::new(
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 64.0, 0.0),
+            Vector3::new(64.0, 64.0, 0.0),
+        );
+        let mut disp = Displacement::new_plane(plane, 5);
+        disp.resample(5);
+        assert_eq!(17, disp.width);
+        assert_eq!(17 * 17, disp.distances.inner.len());
+        // last point fits in allowed_verts
+        assert!(disp.is_vert_allowed([16, 16]));
+        disp.set_vert_allowed([16, 16], false);
+        assert!(!disp.is_vert_allowed([16, 16]));
+
+        disp.resample(1);
+        assert_eq!(5, disp.width);
+        assert_eq!(25, disp.alphas.inner.len());
+    }
+
+    #[test]
+    fn projected() {
+        let mat = Material::new("DEV/DEV_MEASUREWALL01C".into());
+        let options = &SolidOptions::default().allow_frac();
+        let center = Vector3::new(64.0, -32.0, 128.0);
+
+        let sphere = sphere(&center, 256.0, 1, [&mat], options);
+        assert_eq!(6, sphere.sides.len());
+        for side in sphere.sides.iter() {
+            let disp = side.disp.as_ref().unwrap();
+            // power 1 clamped up to 2
+            assert_eq!(5, disp.width);
+            for point in disp.points().inner {
+                assert!((point.dist(&center) - 256.0).abs() < 0.1, "{point:?}");
+            }
+        }
+
+        let bounds = Bounds::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(128.0, 256.0, 64.0));
+        let boxy = rounded_box(&bounds, 6.0, 4, [&mat], options);
+        let round = rounded_box(&bounds, 2.0, 4, [&mat], options);
+        let top = |solid: &Solid| solid.sides[0].disp.as_ref().unwrap().points();
+        let (boxy, round) = (top(&boxy), top(&round));
+        // middle of the top stays on top
+        assert!((boxy[[8, 8]].z - 64.0).abs() < 0.1);
+        assert!((round[[8, 8]].z - 64.0).abs() < 0.1);
+        // corners are pulled in, more when rounder
+        let (boxy_corner, round_corner) = (&boxy[[0, 0]], &round[[0, 0]]);
+        assert!(boxy_corner.x > 0.0 && boxy_corner.z < 64.0);
+        assert!(boxy_corner.dist(&bounds.center()) > round_corner.dist(&bounds.center()));
+        for point in boxy.inner.iter().chain(round.inner.iter()) {
+            assert!(point.x > -0.1 && point.x < 128.1 && point.z > -0.1 && point.z < 64.1);
+        }
+    }
+
     #[test]
     fn normal_dist_from() {
         let input1 = Vector3::new(1.0, 1.0, 1.0);
@@ -1,5 +1,8 @@
 pub mod disp;
+pub mod noise;
+pub mod polygon;
 pub mod shape;
+pub mod terrain;
 
 use crate::prelude::{Plane, Vector2, Vector3};
 use std::ops::Range;
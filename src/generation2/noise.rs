@@ -0,0 +1,273 @@
+//! Seeded 2d gradient noise for terrain. Ex: [`terrain::from_heightmap()`].
+//!
+//! [`terrain::from_heightmap()`]: crate::generation2::terrain::from_heightmap
+
+use rand_chacha::rand_core::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// The base noise of a [`Fractal`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Basis {
+    /// Classic (improved) Perlin noise. Slightly blocky, aligned to the axes.
+    /// See <https://en.wikipedia.org/wiki/Perlin_noise>
+    #[default]
+    Perlin,
+    /// Simplex noise. Less axis aligned and smoother than Perlin.
+    /// See <https://en.wikipedia.org/wiki/Simplex_noise>
+    Simplex,
+}
+
+/// Settings for layering octaves of noise, fractal Brownian motion.
+/// See <https://en.wikipedia.org/wiki/Fractional_Brownian_motion>
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub struct Fractal {
+    pub basis: Basis,
+    /// Number of layers of noise.
+    pub octaves: u32,
+    /// Frequency of the first octave, features per unit. Ex: 1/1024 for hills
+    /// about 1024 units apart.
+    pub frequency: f32,
+    /// Frequency multiplier per octave.
+    pub lacunarity: f32,
+    /// Amplitude multiplier per octave, how rough it is.
+    pub gain: f32,
+}
+
+impl Fractal {
+    pub const fn new() -> Self {
+        Self {
+            basis: Basis::Perlin,
+            octaves: 5,
+            frequency: 1.0 / 1024.0,
+            lacunarity: 2.0,
+            gain: 0.5,
+        }
+    }
+    pub const fn basis(self, basis: Basis) -> Self {
+        Self { basis, ..self }
+    }
+    pub const fn octaves(self, octaves: u32) -> Self {
+        Self { octaves, ..self }
+    }
+    pub const fn frequency(self, frequency: f32) -> Self {
+        Self { frequency, ..self }
+    }
+    pub const fn lacunarity(self, lacunarity: f32) -> Self {
+        Self { lacunarity, ..self }
+    }
+    pub const fn gain(self, gain: f32) -> Self {
+        Self { gain, ..self }
+    }
+}
+
+impl Default for Fractal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Seeded gradient noise. The same seed always gives the same noise.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Noise {
+    /// Shuffled 0..=255, repeated so lookups don't need to wrap.
+    perm: Vec<u8>,
+}
+
+impl Noise {
+    pub fn new(seed: u64) -> Self {
+        let mut rand = ChaCha8Rng::seed_from_u64(seed);
+        let mut perm: Vec<u8> = (0..=255).collect();
+        // Fisher–Yates shuffle
+        for i in (1..perm.len()).rev() {
+            let j = rand.next_u32() as usize % (i + 1);
+            perm.swap(i, j);
+        }
+        perm.extend_from_within(..);
+        Self { perm }
+    }
+
+    #[inline]
+    fn hash(&self, x: i32, y: i32) -> u8 {
+        let x = (x & 255) as usize;
+        let y = (y & 255) as usize;
+        self.perm[self.perm[x] as usize + y]
+    }
+
+    /// Classic Perlin noise, about -1 to 1. 0 at integer coordinates.
+    pub fn perlin(&self, x: f32, y: f32) -> f32 {
+        let (x_floor, y_floor) = (x.floor(), y.floor());
+        let (xi, yi) = (x_floor as i32, y_floor as i32);
+        let (xf, yf) = (x - x_floor, y - y_floor);
+        let (u, v) = (fade(xf), fade(yf));
+
+        let bottom =
+            lerp(grad(self.hash(xi, yi), xf, yf), grad(self.hash(xi + 1, yi), xf - 1.0, yf), u);
+        let top = lerp(
+            grad(self.hash(xi, yi + 1), xf, yf - 1.0),
+            grad(self.hash(xi + 1, yi + 1), xf - 1.0, yf - 1.0),
+            u,
+        );
+        lerp(bottom, top, v).clamp(-1.0, 1.0)
+    }
+
+    /// Simplex noise, about -1 to 1.
+    /// See <https://weber.itn.liu.se/~stegu/simplexnoise/simplexnoise.pdf>
+    pub fn simplex(&self, x: f32, y: f32) -> f32 {
+        // skew and unskew factors for 2d
+        const F2: f32 = 0.366_025_42; // (sqrt(3) - 1) / 2
+        const G2: f32 = 0.211_324_87; // (3 - sqrt(3)) / 6
+
+        let skew = (x + y) * F2;
+        let (i, j) = ((x + skew).floor(), (y + skew).floor());
+        let unskew = (i + j) * G2;
+        let (x0, y0) = (x - (i - unskew), y - (j - unskew));
+        // which of the two triangles of the skewed square
+        let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+        let (x1, y1) = (x0 - i1 as f32 + G2, y0 - j1 as f32 + G2);
+        let (x2, y2) = (x0 - 1.0 + 2.0 * G2, y0 - 1.0 + 2.0 * G2);
+
+        let (i, j) = (i as i32, j as i32);
+        let corner = |hash: u8, x: f32, y: f32| {
+            let t = 0.5 - x * x - y * y;
+            if t < 0.0 {
+                0.0
+            } else {
+                t.powi(4) * grad(hash, x, y)
+            }
+        };
+        let sum = corner(self.hash(i, j), x0, y0)
+            + corner(self.hash(i + i1, j + j1), x1, y1)
+            + corner(self.hash(i + 1, j + 1), x2, y2);
+        // scale to about -1 to 1
+        (70.0 * sum).clamp(-1.0, 1.0)
+    }
+
+    /// One octave of the `basis` noise.
+    #[inline]
+    pub fn basis(&self, basis: Basis, x: f32, y: f32) -> f32 {
+        match basis {
+            Basis::Perlin => self.perlin(x, y),
+            Basis::Simplex => self.simplex(x, y),
+        }
+    }
+
+    /// Fractal Brownian motion, octaves of noise added together. About -1 to 1.
+    pub fn fbm(&self, x: f32, y: f32, fractal: &Fractal) -> f32 {
+        self.octaves(x, y, fractal, |value| value)
+    }
+
+    /// Ridged noise, sharp ridges and round valleys. Ex: mountains. 0 to 1.
+    pub fn ridged(&self, x: f32, y: f32, fractal: &Fractal) -> f32 {
+        self.octaves(x, y, fractal, |value| {
+            let ridge = 1.0 - value.abs();
+            ridge * ridge
+        })
+    }
+
+    /// Domain warped fBm, the position is moved by more fBm first. Ex: swirly
+    /// eroded looking hills. `strength` is how many units to move by. About -1 to 1.
+    ///
+    /// See <https://iquilezles.org/articles/warp/>
+    pub fn warped(&self, x: f32, y: f32, fractal: &Fractal, strength: f32) -> f32 {
+        // arbitrary offsets so the x and y warps aren't the same
+        let warp_x = self.fbm(x + 5_200.0, y + 1_300.0, fractal);
+        let warp_y = self.fbm(x - 1_700.0, y + 9_200.0, fractal);
+        self.fbm(x + warp_x * strength, y + warp_y * strength, fractal)
+    }
+
+    /// Weighted average of `fractal.octaves` octaves, each mapped by `map`.
+    fn octaves(&self, x: f32, y: f32, fractal: &Fractal, map: impl Fn(f32) -> f32) -> f32 {
+        let mut frequency = fractal.frequency;
+        let mut amplitude = 1.0;
+        let mut sum = 0.0;
+        let mut total_amplitude = 0.0;
+        for octave in 0..fractal.octaves {
+            // offset each octave so they don't all line up at the origin
+            let offset = octave as f32 * 31.7;
+            let value = self.basis(fractal.basis, x * frequency + offset, y * frequency + offset);
+            sum += map(value) * amplitude;
+            total_amplitude += amplitude;
+            frequency *= fractal.lacunarity;
+            amplitude *= fractal.gain;
+        }
+        if total_amplitude == 0.0 {
+            return 0.0;
+        }
+        sum / total_amplitude
+    }
+}
+
+/// Perlin's fade curve, 6t^5 - 15t^4 + 10t^3.
+#[inline]
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+#[inline]
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + t * (b - a)
+}
+
+/// Dot product of one of 8 gradients picked by `hash` with `(x, y)`.
+#[inline]
+fn grad(hash: u8, x: f32, y: f32) -> f32 {
+    match hash & 7 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x,
+        5 => -x,
+        6 => y,
+        _ => -y,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded() {
+        let fractal = Fractal::new().frequency(1.0 / 64.0);
+        let noise = Noise::new(1);
+        let points = (0..64).map(|i| (i as f32 * 13.3, i as f32 * -7.1));
+        let values: Vec<_> = points.clone().map(|(x, y)| noise.fbm(x, y, &fractal)).collect();
+
+        assert_eq!(
+            values,
+            points
+                .clone()
+                .map(|(x, y)| Noise::new(1).fbm(x, y, &fractal))
+                .collect::<Vec<_>>()
+        );
+        assert_ne!(
+            values,
+            points.map(|(x, y)| Noise::new(2).fbm(x, y, &fractal)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn ranges() {
+        let noise = Noise::new(0xdead_beef);
+        let fractal = Fractal::new().frequency(1.0 / 16.0);
+        let mut some_positive = false;
+        for i in 0..2000 {
+            let (x, y) = (i as f32 * 1.37, i as f32 * 0.73 + (i % 17) as f32);
+            for value in [
+                noise.perlin(x, y),
+                noise.simplex(x, y),
+                noise.fbm(x, y, &fractal),
+                noise.fbm(x, y, &fractal.clone().basis(Basis::Simplex)),
+                noise.warped(x, y, &fractal, 32.0),
+            ] {
+                assert!((-1.0..=1.0).contains(&value), "{value}");
+                some_positive |= value > 0.1;
+            }
+            assert!((0.0..=1.0).contains(&noise.ridged(x, y, &fractal)));
+        }
+        assert!(some_positive);
+        // perlin is 0 on the lattice
+        assert_eq!(0.0, noise.perlin(3.0, -7.0));
+    }
+}
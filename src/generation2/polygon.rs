@@ -0,0 +1,329 @@
+//! 2d polygons and convex decomposition.
+
+use crate::prelude::Vector2;
+use crate::utils::IterWithNext;
+
+// arbitrary, small enough for hammer units
+const EPSILON: f32 = 1e-4;
+
+/// A simple polygon (no holes, no self intersections) in 2d space.
+/// Ex: a floor plan, an outline of a road, or the profile of a curb.
+///
+/// Points can be in any winding order, see [`Polygon2::is_clockwise()`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Polygon2 {
+    pub points: Vec<Vector2<f32>>,
+}
+
+impl Polygon2 {
+    pub const fn new(points: Vec<Vector2<f32>>) -> Self {
+        Self { points }
+    }
+
+    /// An axis aligned rectangle from any two corners, in clockwise order.
+    pub fn rect(point1: Vector2<f32>, point2: Vector2<f32>) -> Self {
+        let min = Vector2::new(point1.x.min(point2.x), point1.y.min(point2.y));
+        let max = Vector2::new(point1.x.max(point2.x), point1.y.max(point2.y));
+        Self::new(vec![
+            Vector2::new(min.x, min.y),
+            Vector2::new(min.x, max.y),
+            Vector2::new(max.x, max.y),
+            Vector2::new(max.x, min.y),
+        ])
+    }
+
+    pub const fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Twice the signed area. Positive if counter clockwise, negative if clockwise.
+    /// See <https://en.wikipedia.org/wiki/Shoelace_formula>
+    fn signed_area2(&self) -> f32 {
+        IterWithNext::new(self.points.iter())
+            .map(|(current, next)| current.x * next.y - next.x * current.y)
+            .sum()
+    }
+
+    /// The area, ignoring winding order.
+    pub fn area(&self) -> f32 {
+        self.signed_area2().abs() / 2.0
+    }
+
+    /// Returns true if the points are in clockwise order (ex: East from North).
+    pub fn is_clockwise(&self) -> bool {
+        self.signed_area2() < 0.0
+    }
+
+    /// Reverse the winding order.
+    pub fn reverse(mut self) -> Self {
+        self.points.reverse();
+        self
+    }
+
+    /// Make the points be in clockwise order, the order [`prism()`] expects.
+    ///
+    /// [`prism()`]: crate::generation2::shape::prism
+    pub fn into_clockwise(self) -> Self {
+        if self.is_clockwise() {
+            self
+        } else {
+            self.reverse()
+        }
+    }
+
+    /// Make the points be in counter clockwise order.
+    pub fn into_counter_clockwise(self) -> Self {
+        if self.is_clockwise() {
+            self.reverse()
+        } else {
+            self
+        }
+    }
+
+    /// Returns true if convex, ignoring colinear points and winding order.
+    pub fn is_convex(&self) -> bool {
+        let sign = self.signed_area2().signum();
+        is_convex_indices(&self.points, &(0..self.len()).collect::<Vec<_>>(), sign)
+    }
+
+    /// Remove consecutive duplicate and colinear points.
+    pub fn simplify(mut self) -> Self {
+        self.points.dedup_by(|a, b| a.dist(b) < EPSILON);
+        while self.len() > 1 && self.points[0].dist(&self.points[self.len() - 1]) < EPSILON {
+            self.points.pop();
+        }
+
+        let mut i = 0;
+        while self.len() > 3 && i < self.len() {
+            let len = self.len();
+            let prev = &self.points[(i + len - 1) % len];
+            let current = &self.points[i];
+            let next = &self.points[(i + 1) % len];
+            if cross(prev, current, next).abs() < EPSILON {
+                self.points.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+        self
+    }
+
+    /// Split into convex polygons with the Hertel–Mehlhorn algorithm.
+    /// Triangulates by ear clipping, then removes every diagonal it can while
+    /// keeping each piece convex. Gives at most 4 times the optimal number of pieces.
+    ///
+    /// Each piece keeps the winding order of `self`.
+    ///
+    /// See <https://en.wikipedia.org/wiki/Polygon_partition#Partition_into_convex_polygons>
+    ///
+    /// # Panics
+    /// Debug asserts that `self` has at least 3 points.
+    pub fn convex_decomposition(&self) -> Vec<Polygon2> {
+        let polygon = self.clone().simplify();
+        debug_assert!(polygon.len() >= 3, "Degenerate polygon");
+        if polygon.is_convex() {
+            return vec![polygon];
+        }
+
+        let sign = polygon.signed_area2().signum();
+        let points = &polygon.points;
+        let mut pieces = ear_clip(points, sign);
+
+        // merge pieces across diagonals while they stay convex
+        let mut merged_any = true;
+        while merged_any {
+            merged_any = false;
+            'search: for a in 0..pieces.len() {
+                for b in a + 1..pieces.len() {
+                    let Some(merged) = merge_pieces(&pieces[a], &pieces[b]) else { continue };
+                    if is_convex_indices(points, &merged, sign) {
+                        pieces[a] = merged;
+                        pieces.swap_remove(b);
+                        merged_any = true;
+                        break 'search;
+                    }
+                }
+            }
+        }
+
+        pieces
+            .into_iter()
+            .map(|piece| Polygon2::new(piece.into_iter().map(|i| points[i].clone()).collect()))
+            .collect()
+    }
+}
+
+impl From<Vec<Vector2<f32>>> for Polygon2 {
+    fn from(value: Vec<Vector2<f32>>) -> Self {
+        Self::new(value)
+    }
+}
+
+/// Z of the cross product of `a -> b` and `b -> c`.
+/// Positive is a left (counter clockwise) turn.
+fn cross(a: &Vector2<f32>, b: &Vector2<f32>, c: &Vector2<f32>) -> f32 {
+    (b.x - a.x) * (c.y - b.y) - (b.y - a.y) * (c.x - b.x)
+}
+
+/// Returns true if every turn of the polygon made of `indices` into `points`
+/// turns the same way as `sign`. Colinear points are ignored.
+fn is_convex_indices(points: &[Vector2<f32>], indices: &[usize], sign: f32) -> bool {
+    let len = indices.len();
+    (0..len).all(|i| {
+        let a = &points[indices[(i + len - 1) % len]];
+        let b = &points[indices[i]];
+        let c = &points[indices[(i + 1) % len]];
+        cross(a, b, c) * sign >= -EPSILON
+    })
+}
+
+/// Returns true if `p` is inside or on the edge of the triangle `a b c` with winding `sign`.
+fn in_triangle(
+    p: &Vector2<f32>, a: &Vector2<f32>, b: &Vector2<f32>, c: &Vector2<f32>, sign: f32,
+) -> bool {
+    cross(a, b, p) * sign >= -EPSILON
+        && cross(b, c, p) * sign >= -EPSILON
+        && cross(c, a, p) * sign >= -EPSILON
+}
+
+/// Triangulate a simple polygon by ear clipping. Returns triangles as indices into `points`.
+fn ear_clip(points: &[Vector2<f32>], sign: f32) -> Vec<Vec<usize>> {
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = Vec::with_capacity(points.len() - 2);
+
+    while remaining.len() > 3 {
+        let len = remaining.len();
+        let ear = (0..len).find(|&i| {
+            let [ia, ib, ic] =
+                [remaining[(i + len - 1) % len], remaining[i], remaining[(i + 1) % len]];
+            let [a, b, c] = [&points[ia], &points[ib], &points[ic]];
+            // reflex or colinear vertexes can't be ears
+            if cross(a, b, c) * sign <= EPSILON {
+                return false;
+            }
+            // no other vertex can be inside the ear
+            remaining
+                .iter()
+                .filter(|&&j| j != ia && j != ib && j != ic)
+                .all(|&j| !in_triangle(&points[j], a, b, c, sign))
+        });
+
+        // only happens on non-simple polygons, clip anything to not loop forever
+        let i = ear.unwrap_or(0);
+        triangles.push(vec![
+            remaining[(i + len - 1) % len],
+            remaining[i],
+            remaining[(i + 1) % len],
+        ]);
+        remaining.remove(i);
+    }
+    triangles.push(remaining);
+
+    triangles
+}
+
+/// Merge two pieces if they share an edge (a diagonal). Both must have the same winding.
+fn merge_pieces(a: &[usize], b: &[usize]) -> Option<Vec<usize>> {
+    // shared edge is `p -> q` in `a` and `q -> p` in `b`
+    for (i, (p, q)) in IterWithNext::new(a.iter().copied()).enumerate() {
+        let Some(j) = IterWithNext::new(b.iter().copied()).position(|edge| edge == (q, p)) else {
+            continue;
+        };
+
+        // a up to and including p, then b after p up to before q, then the rest of a from q
+        let mut merged = Vec::with_capacity(a.len() + b.len() - 2);
+        merged.extend_from_slice(&a[..=i]);
+        let b_len = b.len();
+        // b[j] is q, b[j + 1] is p, walk from after p until q
+        let mut k = (j + 2) % b_len;
+        while k != j {
+            merged.push(b[k]);
+            k = (k + 1) % b_len;
+        }
+        merged.extend_from_slice(&a[i + 1..]);
+        return Some(merged);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn l_shape() -> Polygon2 {
+        // clockwise L
+        Polygon2::new(vec![
+            Vector2::new(0.0, 0.0),
+            Vector2::new(0.0, 256.0),
+            Vector2::new(128.0, 256.0),
+            Vector2::new(128.0, 128.0),
+            Vector2::new(256.0, 128.0),
+            Vector2::new(256.0, 0.0),
+        ])
+    }
+
+    #[test]
+    fn winding() {
+        let poly = l_shape();
+        assert!(poly.is_clockwise());
+        assert!(!poly.clone().reverse().is_clockwise());
+        assert_eq!(poly, poly.clone().reverse().into_clockwise());
+        assert_eq!(128.0 * 128.0 * 3.0, poly.area());
+    }
+
+    #[test]
+    fn convex() {
+        assert!(Polygon2::rect(Vector2::new(0.0, 0.0), Vector2::new(1.0, 1.0)).is_convex());
+        assert!(!l_shape().is_convex());
+        assert!(!l_shape().reverse().is_convex());
+    }
+
+    #[test]
+    fn simplify() {
+        let poly = Polygon2::new(vec![
+            Vector2::new(0.0, 0.0),
+            Vector2::new(0.0, 64.0),
+            Vector2::new(0.0, 128.0),
+            Vector2::new(0.0, 128.0),
+            Vector2::new(128.0, 128.0),
+            Vector2::new(128.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        ]);
+        assert_eq!(4, poly.simplify().len());
+    }
+
+    #[test]
+    fn decomposition() {
+        for poly in [l_shape(), l_shape().reverse()] {
+            let pieces = poly.convex_decomposition();
+            // optimal is 2, Hertel–Mehlhorn gives at most 4x
+            assert!((2..=4).contains(&pieces.len()), "{} pieces", pieces.len());
+            let area: f32 = pieces.iter().map(Polygon2::area).sum();
+            assert!((poly.area() - area).abs() < 1e-2);
+            for piece in pieces {
+                assert!(piece.is_convex());
+                assert_eq!(poly.is_clockwise(), piece.is_clockwise());
+            }
+        }
+
+        // a comb with 4 teeth
+        let mut comb = vec![Vector2::new(0.0, 0.0)];
+        for tooth in 0..4 {
+            let x = tooth as f32 * 64.0;
+            comb.push(Vector2::new(x, 128.0));
+            comb.push(Vector2::new(x + 32.0, 128.0));
+            comb.push(Vector2::new(x + 32.0, 32.0));
+            comb.push(Vector2::new(x + 64.0, 32.0));
+        }
+        comb.push(Vector2::new(256.0, 0.0));
+        let comb = Polygon2::new(comb);
+        let pieces = comb.convex_decomposition();
+        let area: f32 = pieces.iter().map(Polygon2::area).sum();
+        assert!((comb.area() - area).abs() < 1e-2);
+        assert!(pieces.iter().all(Polygon2::is_convex));
+    }
+}
@@ -1,10 +1,10 @@
 // mod old;
 
 use super::*;
-use crate::generation2::disp::{project_cube_to_sphere, Displacement};
+use crate::generation2::disp::project_cube_to_sphere;
+use crate::generation2::polygon::Polygon2;
 use crate::prelude::{Material, Side, Solid};
 use crate::utils::IterWithNext;
-use crate::utils::Vec2d;
 
 // TODO: solid transform
 
@@ -229,6 +229,44 @@ where
     top_bottom.chain(sides)
 }
 
+/// Like [`prism()`] but `top` and `bottom` can be in any orientation and either
+/// winding order, the winding is fixed so the normals face outward.
+/// `top` and `bottom` must have matching points and be planar.
+///
+/// "Top" is whichever side `bottom` points to.
+pub(crate) fn oriented_prism<'a>(
+    mut top: Vec<Vector3<f32>>, mut bottom: Vec<Vector3<f32>>, mats: [&'a Material<'a>; 3],
+    options: &'a SolidOptions,
+) -> Solid<'a> {
+    debug_assert_eq!(top.len(), bottom.len());
+    let axis = centroid(&top) - &centroid(&bottom);
+    // counter clockwise around `axis` when viewed from the top, reverse to clockwise
+    if ring_normal(&top).dot(&axis) > 0.0 || ring_normal(&bottom).dot(&axis) > 0.0 {
+        top.reverse();
+        bottom.reverse();
+    }
+    Solid::new(prism(top.into_iter(), bottom.into_iter(), false, mats, options).collect())
+}
+
+/// The average of some points.
+fn centroid(points: &[Vector3<f32>]) -> Vector3<f32> {
+    let sum = points.iter().fold(Vector3::origin(), |sum, point| sum + point);
+    sum / points.len() as f32
+}
+
+/// Normal of a planar polygon using Newell's method, not normalized.
+/// Points right hand rule, counter clockwise points give a normal towards the viewer.
+fn ring_normal(points: &[Vector3<f32>]) -> Vector3<f32> {
+    IterWithNext::new(points.iter()).fold(Vector3::origin(), |normal, (a, b)| {
+        normal
+            + &Vector3::new(
+                (a.y - b.y) * (a.z + b.z),
+                (a.z - b.z) * (a.x + b.x),
+                (a.x - b.x) * (a.y + b.y),
+            )
+    })
+}
+
 // TODO: sphere, arch, multi
 
 // TODO:DOCS:
@@ -312,6 +350,227 @@ pub fn cylinder<'a>(
     Solid::new(prism(top_points, bottom_points, false, mats, options).collect())
 }
 
+/// Extrude a 2d [`Polygon2`] in the Z axis. Ex: a floor plan into walls or a
+/// road outline into a road. The polygon can be concave and in either winding order,
+/// it is split into convex solids with [`Polygon2::convex_decomposition()`].
+///
+/// `mats` are the materials in the order: top, bottom, sides.
+pub fn extrude<'a>(
+    polygon: &Polygon2, z_range: Range<f32>, mats: [&'a Material<'a>; 3], options: &'a SolidOptions,
+) -> Vec<Solid<'a>> {
+    let allow_frac = options.allow_frac;
+    let to_3d = |piece: &Polygon2, z| -> Vec<_> {
+        piece
+            .points
+            .iter()
+            .map(|p| Vector3::new_with_round(p.x, p.y, z, allow_frac))
+            .collect()
+    };
+
+    polygon
+        .convex_decomposition()
+        .into_iter()
+        .map(|piece| piece.into_clockwise())
+        .map(|piece| {
+            let top = to_3d(&piece, z_range.end);
+            let bottom = to_3d(&piece, z_range.start);
+            Solid::new(prism(top.into_iter(), bottom.into_iter(), false, mats, options).collect())
+        })
+        .collect()
+}
+
+/// Sweep a 2d `profile` along a polyline `path`. Ex: a curb or wall along a road.
+/// Makes one solid per convex piece of `profile` per segment of `path`, segments
+/// are mitered at the joints so they meet without gaps or overlap.
+///
+/// `profile` is in the plane perpendicular to the path: +X is to the right of
+/// the direction of travel and +Y is up (towards +Z). For a vertical segment, right is +X.
+/// That's exact for the first segment, later segments carry its directions around each joint
+/// with the smallest rotation, so the profile can roll a little on paths that turn and climb
+/// at the same joint.
+///
+/// `mats` are the materials in the order: end caps (start and end), unused, sides.
+///
+/// # Panics
+/// Debug asserts that `path` has at least 2 points, no duplicate consecutive points,
+/// and doesn't turn back on itself.
+pub fn sweep<'a>(
+    profile: &Polygon2, path: &[Vector3<f32>], mats: [&'a Material<'a>; 3],
+    options: &'a SolidOptions,
+) -> Vec<Solid<'a>> {
+    debug_assert!(path.len() >= 2, "Path must have at least 2 points");
+    let dirs: Vec<_> =
+        path.windows(2).map(|pair| (pair[1].clone() - &pair[0]).normalize()).collect();
+    debug_assert!(dirs.iter().all(|dir| dir.x.is_finite()), "Duplicate points in path");
+
+    // miter planes at each point of the path, the ends are perpendicular
+    let miters: Vec<_> = (0..path.len())
+        .map(|i| match (i.checked_sub(1).and_then(|i| dirs.get(i)), dirs.get(i)) {
+            (Some(prev), Some(next)) => {
+                let bisector = prev.clone() + next;
+                debug_assert!(bisector.magnitude() > 1e-4, "Path turns back on itself");
+                bisector.normalize()
+            }
+            (Some(dir), None) | (None, Some(dir)) => dir.clone(),
+            (None, None) => unreachable!(),
+        })
+        .collect();
+
+    let pieces = profile.convex_decomposition();
+    let mut solids = Vec::with_capacity(pieces.len() * dirs.len());
+    let (mut right, mut up) = segment_frame(&dirs[0]);
+    for (i, dir) in dirs.iter().enumerate() {
+        if i > 0 {
+            // reflecting across the miter then across `dir` is the smallest rotation from the
+            // previous direction to `dir`, and mirrors the profile across the miter so the
+            // segments share the joint
+            let rotate = |v: &Vector3<f32>| reflect(&reflect(v, &miters[i]), dir);
+            (right, up) = (rotate(&right), rotate(&up));
+        }
+
+        // point on the profile, slid along the segment onto the miter plane of a path point
+        let project = |point: &Vector2<f32>, joint: usize| {
+            let on_start = path[i].clone() + &(right.clone() * point.x) + &(up.clone() * point.y);
+            let normal = &miters[joint];
+            let t = (path[joint].clone() - &on_start).dot(normal) / dir.dot(normal);
+            let p = on_start + &(dir.clone() * t);
+            Vector3::new_with_round(p.x, p.y, p.z, options.allow_frac)
+        };
+
+        for piece in pieces.iter() {
+            let top = piece.points.iter().map(|point| project(point, i + 1)).collect();
+            let bottom = piece.points.iter().map(|point| project(point, i)).collect();
+            solids.push(oriented_prism(top, bottom, [mats[0], mats[0], mats[2]], options));
+        }
+    }
+    solids
+}
+
+/// Reflect `v` across the plane through the origin with the normalized `normal`.
+fn reflect(v: &Vector3<f32>, normal: &Vector3<f32>) -> Vector3<f32> {
+    v.clone() - &(normal.clone() * (2.0 * v.dot(normal)))
+}
+
+/// Right and up directions perpendicular to a normalized direction of travel.
+/// Up is towards +Z, for a vertical direction right is +X.
+fn segment_frame(dir: &Vector3<f32>) -> (Vector3<f32>, Vector3<f32>) {
+    let right = match dir.cross(&Vector3::new(0.0, 0.0, 1.0)) {
+        right if right.magnitude() < 1e-4 => Vector3::new(1.0, 0.0, 0.0),
+        right => right.normalize(),
+    };
+    let up = right.cross(dir);
+    (right, up)
+}
+
+/// A wireframe or lattice from a graph of edges. Ex: trusses, scaffolding, fences,
+/// or a wireframe of another solid with [`Solid::edges()`].
+///
+/// Makes a strut for each edge, a prism with `sides` sides that is `thickness`
+/// wide, and a cube `thickness` wide for each node where edges meet to cover the joints.
+///
+/// `mats` are the materials in the order: strut ends, strut sides, node caps.
+///
+/// # Panics
+/// Debug asserts that no edge has the same start and end.
+pub fn wireframe<'a>(
+    edges: &[(Vector3<f32>, Vector3<f32>)], thickness: f32, mut sides: u32,
+    mats: [&'a Material<'a>; 3], options: &'a SolidOptions,
+) -> Vec<Solid<'a>> {
+    const EPSILON: f32 = 1e-2;
+    let radius = thickness / 2.0;
+    clamp_promote(radius, &mut sides, options);
+
+    let delta_angle = std::f32::consts::TAU / sides as f32;
+    let ring = |center: &Vector3<f32>, right: &Vector3<f32>, up: &Vector3<f32>| -> Vec<_> {
+        (0..sides)
+            .map(|n| {
+                let angle = delta_angle * n as f32;
+                let p = center.clone()
+                    + &(right.clone() * (radius * angle.cos()))
+                    + &(up.clone() * (radius * angle.sin()));
+                Vector3::new_with_round(p.x, p.y, p.z, options.allow_frac)
+            })
+            .collect()
+    };
+
+    let mut solids = Vec::with_capacity(edges.len() * 2);
+    // (node, number of edges touching it)
+    let mut nodes: Vec<(&Vector3<f32>, u32)> = Vec::new();
+    for (start, end) in edges.iter() {
+        debug_assert!(start.dist(end) > EPSILON, "Degenerate edge");
+        let dir = (end.clone() - start).normalize();
+        let (right, up) = segment_frame(&dir);
+        let top = ring(end, &right, &up);
+        let bottom = ring(start, &right, &up);
+        solids.push(oriented_prism(top, bottom, [mats[0], mats[0], mats[1]], options));
+
+        for point in [start, end] {
+            match nodes.iter_mut().find(|(node, _)| node.dist(point) < EPSILON) {
+                Some((_, count)) => *count += 1,
+                None => nodes.push((point, 1)),
+            }
+        }
+    }
+
+    let caps = nodes.into_iter().filter(|(_, count)| *count >= 2).map(|(node, _)| {
+        let bounds = Bounds::new(node.clone() - radius, node.clone() + radius);
+        cube(&bounds, &[mats[2]; 6], options)
+    });
+    solids.extend(caps);
+    solids
+}
+
+/// Text made out of brushes using a built-in 5x7 pixel font. Ex: signs, map credits,
+/// or labeling generated layouts for debugging in game.
+/// Lowercase is drawn as uppercase and unknown characters are drawn as `?`.
+/// Each `\n` starts a new line below.
+///
+/// The text is on `plane`, starting from the bottom left of the first line at
+/// `plane.bottom_left`, reading towards `plane.top_right` from `plane.top_left`, and
+/// up towards `plane.top_left`. Text comes out of the plane `depth` units along its normal.
+/// Ex: `Plane::new(origin, origin + north, origin + north + east)` is readable from above,
+/// facing north.
+///
+/// `font_size` is the height of a line. Use a multiple of 7 without `allow_frac`
+/// to keep the pixels the same size.
+///
+/// `mats` are the materials in the order: front, back, sides.
+pub fn text<'a>(
+    text: &str, plane: &Plane, font_size: f32, depth: f32, mats: [&'a Material<'a>; 3],
+    options: &'a SolidOptions,
+) -> Vec<Solid<'a>> {
+    let pixel = font_size / font::HEIGHT as f32;
+    let right = (plane.top_right.clone() - &plane.top_left).normalize() * pixel;
+    let up = (plane.top_left.clone() - &plane.bottom_left).normalize() * pixel;
+    let out = plane.normal() * depth;
+
+    // point on the plane in pixels relative to the bottom left of the first line
+    let to_3d = |x: f32, y: f32, depth: &Vector3<f32>| {
+        let p = plane.bottom_left.clone() + &(right.clone() * x) + &(up.clone() * y) + depth;
+        Vector3::new_with_round(p.x, p.y, p.z, options.allow_frac)
+    };
+
+    let mut solids = Vec::new();
+    for (line_num, line) in text.lines().enumerate() {
+        // 1 pixel between characters, 2 between lines
+        let line_top = font::HEIGHT as f32 - (line_num * (font::HEIGHT + 2)) as f32;
+        for (char_num, char) in line.chars().enumerate() {
+            let char_left = (char_num * (font::WIDTH + 1)) as f32;
+            for (x, y, width, height) in font::glyph_rects(&font::glyph(char)) {
+                let left = char_left + x as f32;
+                let right = left + width as f32;
+                let top = line_top - y as f32;
+                let bottom = top - height as f32;
+                let corners = [(left, bottom), (left, top), (right, top), (right, bottom)];
+                let front = corners.iter().map(|&(x, y)| to_3d(x, y, &out)).collect();
+                let back = corners.iter().map(|&(x, y)| to_3d(x, y, &Vector3::origin())).collect();
+                solids.push(oriented_prism(front, back, mats, options));
+            }
+        }
+    }
+    solids
+}
+
 // TODO: layer sides and side sides for nice square faces sphere
 // TODO:FEATURE: add prism support for len and len*2 iters for nice caps in Hammer
 // https://en.wikipedia.org/wiki/Square_antiprism
@@ -331,71 +590,132 @@ pub fn cylinder<'a>(
 pub fn sphere_globe<'a>(
     bounds: &Bounds, sides: u32, mats: [&'a Material<'a>; 3], options: &'a SolidOptions,
 ) -> Vec<Solid<'a>> {
-    let x_radius = bounds.x_len() / 2.0;
-    let y_radius = bounds.y_len() / 2.0;
+    dome(bounds, -90.0..90.0, sides, mats, options)
+}
+
+/// Part of a [`sphere_globe()`] between two latitudes, like the layers of a globe.
+/// Ex: `0.0..90.0` is a hemisphere, `-90.0..90.0` is the whole sphere, and
+/// `45.0..90.0` is a shallow cap.
+///
+/// `bounds` are the bounds of the whole sphere. Ex: for a hemisphere sitting on
+/// z=0 that is 256 tall, the bounds are from z=-256 to z=256.
+/// `latitudes` are in degrees from -90 (bottom) to 90 (top).
+/// There are `sides` layers for the whole sphere, so fewer for part of one.
+///
+/// `mats` are the materials in the order: top insides, bottom insides, sides.
+/// Solids are from top to bottom, each with sides in the same order as [`prism()`].
+pub fn dome<'a>(
+    bounds: &Bounds, latitudes: Range<f32>, sides: u32, mats: [&'a Material<'a>; 3],
+    options: &'a SolidOptions,
+) -> Vec<Solid<'a>> {
+    let center = bounds.center();
     let z_radius = bounds.z_len() / 2.0;
+    let rings = latitude_rings(center.z, z_radius, latitudes, sides);
+    layered(&center, bounds.x_len() / 2.0, bounds.y_len() / 2.0, &rings, sides, mats, options)
+}
+
+/// A cone with the top cut off, a sphere layer. The bottom fills `bounds` and the
+/// top is the bottom scaled by `top_scale`. Ex: 0.0 is a [`spike()`] and 1.0 is a [`cylinder()`].
+///
+/// `mats` are the materials in the order: top, bottom, sides.
+///
+/// # Panics
+/// - `bounds` must have a height, after rounding unless `options.allow_frac`.
+/// - `bounds` must be at least half a unit wide and long.
+///
+/// Either would be degenerate and have no volume.
+#[doc(alias = "truncated_cone")]
+pub fn frustum<'a>(
+    bounds: &Bounds, top_scale: f32, sides: u32, mats: [&'a Material<'a>; 3],
+    options: &'a SolidOptions,
+) -> Solid<'a> {
+    let rings = [(bounds.max.z, top_scale), (bounds.min.z, 1.0)];
     let center = bounds.center();
+    let mut solids =
+        layered(&center, bounds.x_len() / 2.0, bounds.y_len() / 2.0, &rings, sides, mats, options);
+    solids.pop().expect("Degenerate frustum")
+}
 
-    // get height of point on sphere from top to bottom at constant angle
-    let delta_angle = std::f64::consts::TAU / sides as f64;
-    let height_at_angles = (0..=sides).map(move |n| {
-        let angle = delta_angle * n as f64 / 2.0;
-        let z = z_radius as f64 * angle.cos();
-        if options.allow_frac {
-            z as f32
-        } else {
-            (z as f32).round()
-        }
-    });
+/// A cylinder with a hemisphere on the top and bottom. The hemispheres are as
+/// tall as the smallest radius, or half the height if shorter.
+/// There are `sides` layers for both hemispheres combined.
+///
+/// `mats` are the materials in the order: top insides, bottom insides, sides.
+/// Solids are from top to bottom, top hemisphere, cylinder, then bottom hemisphere.
+/// Each has sides in the same order as [`prism()`].
+pub fn capsule<'a>(
+    bounds: &Bounds, sides: u32, mats: [&'a Material<'a>; 3], options: &'a SolidOptions,
+) -> Vec<Solid<'a>> {
+    let center = bounds.center();
+    let x_radius = bounds.x_len() / 2.0;
+    let y_radius = bounds.y_len() / 2.0;
+    let cap_height = x_radius.min(y_radius).min(bounds.z_len() / 2.0);
 
-    // TODO: impl DoubleEndedIterator for IterWithNext
-    // Iter from up to down of top AND bottom circle heights
-    let heights = height_at_angles.collect::<Vec<_>>();
-    let heights = heights.windows(2);
-    let layers = heights.map(|height| {
-        let height_top_from_center = height[0];
-        let height_bottom_from_center = height[1];
-
-        // get radii for xy of top and bottom
-        let top_radius_x =
-            radius_at_sphere_height(x_radius, height_top_from_center, options.allow_frac);
-        let top_radius_y =
-            radius_at_sphere_height(y_radius, height_top_from_center, options.allow_frac);
-        let bottom_radius_x =
-            radius_at_sphere_height(x_radius, height_bottom_from_center, options.allow_frac);
-        let bottom_radius_y =
-            radius_at_sphere_height(y_radius, height_bottom_from_center, options.allow_frac);
-
-        // make top and bottom circles/points
-        let top_center = Vector3 { z: height_top_from_center, ..center };
-        let bottom_center = Vector3 { z: height_bottom_from_center, ..center };
-
-        let top_circle = ellipse_verts(top_center, top_radius_x, top_radius_y, sides, options);
-        let bottom_circle =
-            ellipse_verts(bottom_center, bottom_radius_x, bottom_radius_y, sides, options);
-
-        // TODO: allow choosing prefer top/bottom/auto
-        Solid::new(prism(top_circle, bottom_circle, false, mats, options).collect::<Vec<_>>())
-    });
+    let top = latitude_rings(bounds.max.z - cap_height, cap_height, 0.0..90.0, sides);
+    let bottom = latitude_rings(bounds.min.z + cap_height, cap_height, -90.0..0.0, sides);
+    // top and bottom equators connect to make the cylinder
+    let rings: Vec<_> = top.into_iter().chain(bottom).collect();
+    layered(&center, x_radius, y_radius, &rings, sides, mats, options)
+}
 
-    layers.collect()
+/// Rings from top to bottom `(z, scale)` of an ellipsoid between `latitudes` in degrees.
+/// `sides` is the number of layers of the whole ellipsoid.
+fn latitude_rings(
+    center_z: f32, z_radius: f32, latitudes: Range<f32>, sides: u32,
+) -> Vec<(f32, f32)> {
+    let [bottom, top] =
+        [latitudes.start, latitudes.end].map(|lat| lat.clamp(-90.0, 90.0).to_radians());
+    let layers = ((top - bottom) / std::f32::consts::PI * sides as f32).ceil().max(1.0) as u32;
+    let delta_angle = (top - bottom) as f64 / layers as f64;
+    (0..=layers)
+        .map(|n| {
+            let angle = top as f64 - delta_angle * n as f64;
+            let z = center_z as f64 + z_radius as f64 * angle.sin();
+            (z as f32, angle.cos() as f32)
+        })
+        .collect()
 }
 
-/// See <https://en.wikipedia.org/wiki/Circle_of_a_sphere>
-fn radius_at_sphere_height(radius: f32, height_from_center: f32, allow_frac: bool) -> f32 {
+/// Stack of frustums from rings `(z, scale)` from top to bottom. Ex: layers of a sphere.
+/// Each ring is an ellipse with radii scaled by `scale`, a tiny ring is a point.
+/// Layers with no height or that are a line are skipped.
+fn layered<'a>(
+    center: &Vector3<f32>, x_radius: f32, y_radius: f32, rings: &[(f32, f32)], sides: u32,
+    mats: [&'a Material<'a>; 3], options: &'a SolidOptions,
+) -> Vec<Solid<'a>> {
     // make sure that top/bottom of sphere is actually one point
     const EPSILON: f32 = 0.25; // arbitrary
-    if (radius - height_from_center).abs() < EPSILON {
-        return 0.0;
-    }
-
-    let height = height_from_center;
-    let radius = f32::sqrt(radius * radius - height * height);
-    if allow_frac {
-        radius
-    } else {
-        radius.round()
-    }
+    let rings: Vec<_> = rings
+        .iter()
+        .map(|&(z, scale)| {
+            let z = if options.allow_frac { z } else { z.round() };
+            let center = Vector3 { z, ..center.clone() };
+            let (x_radius, y_radius) = (x_radius * scale, y_radius * scale);
+            if x_radius.min(y_radius) < EPSILON {
+                (center, None)
+            } else {
+                let ring = ellipse_verts(center.clone(), x_radius, y_radius, sides, options);
+                (center, Some(ring.collect::<Vec<_>>()))
+            }
+        })
+        .collect();
+
+    rings
+        .windows(2)
+        .filter(|pair| pair[0].0.z != pair[1].0.z)
+        .filter_map(|pair| {
+            let [(top_center, top), (bottom_center, bottom)] = [&pair[0], &pair[1]];
+            let (top, bottom) = match (top, bottom) {
+                (Some(top), Some(bottom)) => (top.clone(), bottom.clone()),
+                (None, Some(bottom)) => (vec![top_center.clone(); bottom.len()], bottom.clone()),
+                (Some(top), None) => (top.clone(), vec![bottom_center.clone(); top.len()]),
+                (None, None) => return None,
+            };
+            Some(Solid::new(
+                prism(top.into_iter(), bottom.into_iter(), false, mats, options).collect(),
+            ))
+        })
+        .collect()
 }
 
 // TODO: automatic texturing
@@ -416,57 +736,9 @@ fn radius_at_sphere_height(radius: f32, height_from_center: f32, allow_frac: boo
 ///
 /// See also: [`sphere_globe()`]
 pub fn sphere<'a>(
-    bounds: &Bounds, mut power: u32, mats: [&'a Material<'a>; 1], options: &'a SolidOptions,
+    bounds: &Bounds, power: u32, mats: [&'a Material<'a>; 1], options: &'a SolidOptions,
 ) -> Solid<'a> {
-    if power < 2 {
-        // NOTE: hammer seems to support power 1 displacements O_O
-        // bsp seems to definitely not support it
-        eprintln!("[sphere()] power clamped to 2");
-        power = 1;
-    } else if power > 4 {
-        eprintln!("[sphere()] power clamped to 4");
-        power = 4;
-    };
-    let size = Displacement::power_to_len(power);
-
-    let mut cube = cube(bounds, &[mats[0]; 6], options);
-
-    for side in cube.sides.iter_mut() {
-        // create a displacement side with points in default pos
-        let mut disp = Displacement::new_plane(side.plane.clone(), size);
-        let ideal = disp.ideal_points();
-
-        // Project each point
-        let projected = ideal.inner.iter().map(|p| {
-            // convert to unit coords, project, convert back to real coords
-            let unit = bounds.point_to_unit(p);
-            let sphere_point = project_cube_to_sphere(&unit);
-            bounds.unit_to_point(&sphere_point)
-        });
-
-        // convert ideal and projected to dir and dists
-        let mut dirs = Vec2d::new(Vec2d::strides(size));
-        let mut dists = Vec2d::new(Vec2d::strides(size));
-        let alphas = Vec2d::from_parts(vec![0.0; disp.width * disp.width], Vec2d::strides(size));
-        for (ideal, projected) in ideal.inner.iter().zip(projected) {
-            // let (mut dir, dist) = ideal.dir_and_dist(&projected);
-            let (mut dir, dist) = ideal.dir_and_dist(&projected);
-            // remove nans TODO:DOCS: why it make them? cant explain, too tired rn
-            if dir.x.is_nan() {
-                // eprintln!("DIR IS NAN: {}", dir);
-                dir = Vector3::origin();
-            }
-            dirs.inner.push(dir);
-            dists.inner.push(dist);
-        }
-        disp.normals = dirs;
-        disp.distances = dists;
-        disp.alphas = alphas;
-
-        side.disp = Some(disp);
-    }
-
-    cube
+    disp::projected_cube(bounds, power, mats, options, project_cube_to_sphere)
 }
 
 // // Scale a point in a bounds to a unit vector (-1..=1 on all axes)
@@ -582,5 +854,7 @@ fn remap_test() {
 //     test(1.0, -100.0, 200.0, 200.0);
 // }
 
+mod font;
+
 #[cfg(test)]
 mod tests;
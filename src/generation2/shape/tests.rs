@@ -1,5 +1,5 @@
 use crate::map::Map;
-use crate::prelude::Vector3;
+use crate::prelude::{Vector2, Vector3};
 use crate::vmf::{block_to_solid, ToLower};
 use crate::StrType;
 use std::borrow::Cow;
@@ -415,3 +415,189 @@ fn test_order() {
     // TODO ADD SOLID2 STUFF, RECONCILE RANODM FLAOTING FNS
     unimplemented!("test order")
 }
+
+/// Returns true if every side's normal points away from the center of the solid.
+fn normals_outward(solid: &Solid) -> bool {
+    let center = centroid(&solid.vertices());
+    solid
+        .sides
+        .iter()
+        .all(|side| side.plane.normal().dot(&(side.plane.bottom_left.clone() - &center)) > 0.0)
+}
+
+#[test]
+fn extrude_sweep() {
+    let dev_person = Material::new(Cow::Borrowed("DEV/DEV_MEASUREWALL01C"));
+    let mats = [&dev_person; 3];
+    let options = &SolidOptions::default();
+
+    // L shape, counter clockwise
+    let l_shape = Polygon2::new(vec![
+        Vector2::new(0.0, 0.0),
+        Vector2::new(256.0, 0.0),
+        Vector2::new(256.0, 128.0),
+        Vector2::new(128.0, 128.0),
+        Vector2::new(128.0, 256.0),
+        Vector2::new(0.0, 256.0),
+    ]);
+    let solids = extrude(&l_shape, 0.0..64.0, mats, options);
+    assert!(solids.len() >= 2);
+    for solid in solids.iter() {
+        assert!(normals_outward(solid));
+        assert!(solid.sides.iter().all(|side| (0.0..=64.0).contains(&side.plane.top_left.z)));
+    }
+
+    // curb along a path with a bend
+    let profile = Polygon2::rect(Vector2::new(-8.0, 0.0), Vector2::new(8.0, 16.0));
+    let path = [
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(512.0, 0.0, 0.0),
+        Vector3::new(512.0, 512.0, 64.0),
+    ];
+    let solids = sweep(&profile, &path, mats, options);
+    assert_eq!(2, solids.len());
+    assert!(solids.iter().all(normals_outward));
+    // miter at the bend is shared by both segments
+    let end_cap = &solids[0].sides[0].plane;
+    let start_cap = &solids[1].sides[1].plane;
+    assert_eq!(end_cap.normal().dot(&start_cap.normal()).round(), -1.0);
+}
+
+#[test]
+fn sweep_joints() {
+    let dev_person = Material::new(Cow::Borrowed("DEV/DEV_MEASUREWALL01C"));
+    let mats = [&dev_person; 3];
+    let options = &SolidOptions::default().allow_frac();
+
+    let profile = Polygon2::rect(Vector2::new(-10.0, 0.0), Vector2::new(10.0, 20.0));
+    let v = Vector3::new;
+    let paths = [
+        // turns
+        [v(0.0, 0.0, 0.0), v(100.0, 0.0, 0.0), v(100.0, 100.0, 0.0)],
+        // slope changes
+        [v(0.0, 0.0, 0.0), v(100.0, 0.0, 0.0), v(200.0, 0.0, 100.0)],
+        // turns and climbs
+        [v(0.0, 0.0, 0.0), v(100.0, 0.0, 0.0), v(200.0, 100.0, 100.0)],
+        [v(0.0, 0.0, 0.0), v(100.0, 0.0, 100.0), v(100.0, 100.0, 200.0)],
+    ];
+    for path in paths {
+        let solids = sweep(&profile, &path, mats, options);
+        assert_eq!(2, solids.len());
+        assert!(solids.iter().all(normals_outward));
+        // a flat first segment has its profile up from the path
+        if path[0].z == path[1].z {
+            assert!(solids[0].vertices().iter().all(|v| (-0.01..20.01).contains(&v.z)));
+        }
+
+        // vertices of each segment on the miter plane at the joint
+        let miter = ((path[1].clone() - &path[0]).normalize()
+            + &(path[2].clone() - &path[1]).normalize())
+            .normalize();
+        let joint = |solid: &Solid| -> Vec<_> {
+            let vertices = solid.vertices().into_iter();
+            vertices.filter(|v| (v.clone() - &path[1]).dot(&miter).abs() < 0.01).collect()
+        };
+        let (a, b) = (joint(&solids[0]), joint(&solids[1]));
+        assert_eq!(4, a.len(), "{path:?}");
+        assert_eq!(4, b.len(), "{path:?}");
+        for v in a.iter() {
+            assert!(b.iter().any(|w| v.dist(w) < 0.01), "{path:?}: {v:?} not in {b:?}");
+        }
+    }
+}
+
+#[test]
+fn wireframe_cube() {
+    let dev_person = Material::new(Cow::Borrowed("DEV/DEV_MEASUREWALL01C"));
+    let mats = [&dev_person; 6];
+    let options = &SolidOptions::default();
+
+    let bounds = Bounds::new(Vector3::new(-128.0, -128.0, 0.0), Vector3::new(128.0, 128.0, 256.0));
+    let edges = cube(&bounds, &mats, options).edges();
+    let solids = wireframe(&edges, 16.0, 8, [&dev_person; 3], options);
+    // 12 struts, 8 corners
+    assert_eq!(12 + 8, solids.len());
+    assert!(solids.iter().all(normals_outward));
+    assert!(solids[..12].iter().all(|solid| solid.sides.len() == 8 + 2));
+}
+
+#[test]
+fn text_glyphs() {
+    let dev_person = Material::new(Cow::Borrowed("DEV/DEV_MEASUREWALL01C"));
+    let mats = [&dev_person; 3];
+    let options = &SolidOptions::default();
+
+    // readable from above, facing north
+    let plane = Plane::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 1.0, 0.0),
+        Vector3::new(1.0, 1.0, 0.0),
+    );
+    // top bar, stem, bottom bar
+    let solids = text("I", &plane, 70.0, 8.0, mats, options);
+    assert_eq!(3, solids.len());
+    assert!(solids.iter().all(normals_outward));
+    let stem = &solids[1];
+    let vertices = stem.vertices();
+    assert!(vertices.contains(&Vector3::new(20.0, 10.0, 0.0)));
+    assert!(vertices.contains(&Vector3::new(30.0, 60.0, 8.0)));
+
+    // lowercase is uppercase, spaces are empty
+    let upper = text("ROOM 3", &plane, 70.0, 8.0, mats, options);
+    assert_eq!(upper, text("room 3", &plane, 70.0, 8.0, mats, options));
+    assert!(text(" \n ", &plane, 70.0, 8.0, mats, options).is_empty());
+}
+
+#[test]
+fn sphere_layers() {
+    let dev_person = Material::new(Cow::Borrowed("DEV/DEV_MEASUREWALL01C"));
+    let mats = [&dev_person; 3];
+    let options = &SolidOptions::default();
+    let z_range = |solids: &[Solid]| {
+        let zs = solids.iter().flat_map(Solid::vertices).map(|vertex| vertex.z.round());
+        zs.fold((f32::MAX, f32::MIN), |(min, max), z| (min.min(z), max.max(z)))
+    };
+
+    // off the origin to catch forgetting the center
+    let bounds = Bounds::new(Vector3::new(0.0, 0.0, 512.0), Vector3::new(512.0, 256.0, 1024.0));
+    let globe = sphere_globe(&bounds, 8, mats, options);
+    assert_eq!(8, globe.len());
+    assert_eq!((512.0, 1024.0), z_range(&globe));
+    assert!(globe.iter().all(normals_outward));
+
+    let hemisphere = dome(&bounds, 0.0..90.0, 8, mats, options);
+    assert_eq!(4, hemisphere.len());
+    assert_eq!((768.0, 1024.0), z_range(&hemisphere));
+    // same layers as the top of the globe
+    assert_eq!(globe[..4], hemisphere[..]);
+
+    let solid = frustum(&bounds, 0.5, 8, mats, options);
+    assert_eq!(8 + 2, solid.sides.len());
+    assert!(normals_outward(&solid));
+    assert_eq!(frustum(&bounds, 0.0, 8, mats, options).sides.len(), 8 + 1);
+
+    let solids = capsule(&bounds, 8, mats, options);
+    // 2 hemispheres and a cylinder
+    assert_eq!(4 + 1 + 4, solids.len());
+    assert_eq!((512.0, 1024.0), z_range(&solids));
+    assert!(solids.iter().all(normals_outward));
+}
+
+#[test]
+#[should_panic(expected = "Path turns back on itself")]
+fn sweep_reversed() {
+    let dev_person = Material::new(Cow::Borrowed("DEV/DEV_MEASUREWALL01C"));
+    let options = &SolidOptions::default();
+    let profile = Polygon2::rect(Vector2::new(-8.0, 0.0), Vector2::new(8.0, 16.0));
+    let path = [Vector3::new(0.0, 0.0, 0.0), Vector3::new(128.0, 0.0, 0.0), Vector3::origin()];
+    sweep(&profile, &path, [&dev_person; 3], options);
+}
+
+#[test]
+#[should_panic(expected = "Degenerate frustum")]
+fn frustum_flat() {
+    let dev_person = Material::new(Cow::Borrowed("DEV/DEV_MEASUREWALL01C"));
+    let options = &SolidOptions::default();
+    let bounds = Bounds::new(Vector3::new(0.0, 0.0, 64.0), Vector3::new(256.0, 256.0, 64.0));
+    frustum(&bounds, 0.5, 8, [&dev_person; 3], options);
+}
@@ -0,0 +1,479 @@
+//! Displacement terrain from heightmaps.
+
+use super::*;
+use crate::generation2::disp::Displacement;
+use crate::generation2::shape::cube;
+use crate::prelude::{Material, Solid};
+use crate::utils::Vec2d;
+use std::io;
+use std::path::Path;
+
+/// How far below z=0 the bottom of each terrain tile is.
+pub const TILE_DEPTH: f32 = 16.0;
+
+/// Terrain made of a grid of displacement tiles from a heightmap.
+///
+/// Each tile is a `tile_size` square brush from `-TILE_DEPTH` to 0 with its top
+/// displaced by `power` (2, 3, or 4), starting at the origin and going north east.
+/// There are enough tiles for one displacement vertex per heightmap pixel, rounding up.
+/// The heightmap is stretched over the whole terrain, with row 0 to the north and column 0 to the west.
+/// Heights are added to z=0 and can be negative.
+///
+/// Heights are sampled once per vertex of the whole grid, so edges shared by
+/// two tiles are always exactly the same.
+///
+/// `mats` are the materials in the order: top (the displacement), others.
+/// Tiles are ordered from west to east, then south to north.
+///
+/// # Panics
+/// Panics if `heights` is empty or has a width of 0.
+pub fn from_heightmap<'a>(
+    heights: &Vec2d<f32>, tile_size: f32, mut power: u32, mats: [&'a Material<'a>; 2],
+    options: &'a SolidOptions,
+) -> Vec<Solid<'a>> {
+    if !(2..=4).contains(&power) {
+        power = power.clamp(2, 4);
+        eprintln!("[from_heightmap()] power clamped to {power}");
+    }
+    let len = Displacement::power_to_len(power);
+    let [map_width, map_height] = heightmap_size(heights);
+    assert!(map_width > 0 && map_height > 0, "Empty heightmap");
+
+    let tiles_x = ((map_width - 1) as f32 / (len - 1) as f32).ceil().max(1.0) as usize;
+    let tiles_y = ((map_height - 1) as f32 / (len - 1) as f32).ceil().max(1.0) as usize;
+    // vertexes in the whole terrain
+    let verts_x = tiles_x * (len - 1) + 1;
+    let verts_y = tiles_y * (len - 1) + 1;
+
+    let other = mats[1];
+    let mats = [mats[0], other, other, other, other, other];
+    let mut solids = Vec::with_capacity(tiles_x * tiles_y);
+    for tile_y in 0..tiles_y {
+        for tile_x in 0..tiles_x {
+            let min =
+                Vector3::new(tile_x as f32 * tile_size, tile_y as f32 * tile_size, -TILE_DEPTH);
+            let max = Vector3::new(min.x + tile_size, min.y + tile_size, 0.0);
+            let mut tile = cube(&Bounds::new(min, max), &mats, options);
+
+            let top = &mut tile.sides[0];
+            let mut disp = Displacement::new_plane(top.plane.clone(), len);
+            // points go east from the south west corner, then north
+            for y in 0..len {
+                for x in 0..len {
+                    // global vertex, north is row 0 in the heightmap
+                    let global_x = tile_x * (len - 1) + x;
+                    let global_y = verts_y - 1 - (tile_y * (len - 1) + y);
+                    let u = global_x as f32 / (verts_x - 1) as f32 * (map_width - 1) as f32;
+                    let v = global_y as f32 / (verts_y - 1) as f32 * (map_height - 1) as f32;
+                    let height = sample_bilinear(heights, u, v);
+
+                    let normal = Vector3::new(0.0, 0.0, height.signum());
+                    disp.normals.inner.push(normal);
+                    disp.distances.inner.push(height.abs());
+                    disp.alphas.inner.push(0.0);
+                }
+            }
+            top.disp = Some(disp);
+            solids.push(tile);
+        }
+    }
+    solids
+}
+
+/// A heightmap from a function of world position. Ex: noise from [`Noise`].
+///
+/// Pixels are `pixel_size` apart with the same layout as [`from_heightmap()`]:
+/// the bottom left pixel is at the origin and row 0 is to the north.
+/// For one pixel per displacement vertex, use a `pixel_size` of `tile_size / 2^power`
+/// and a `width` of `tiles * 2^power + 1`.
+///
+/// [`Noise`]: crate::generation2::noise::Noise
+pub fn heightmap_from_fn(
+    width: usize, height: usize, pixel_size: f32, mut f: impl FnMut(f32, f32) -> f32,
+) -> Vec2d<f32> {
+    let mut heights = Vec2d::with_capacity(width * height, Vec2d::strides(width));
+    for row in 0..height {
+        let y = (height - 1 - row) as f32 * pixel_size;
+        for col in 0..width {
+            heights.inner.push(f(col as f32 * pixel_size, y));
+        }
+    }
+    heights
+}
+
+/// Erosion-lite. Simple thermal erosion that moves material from steep
+/// slopes down to their neighbors, rounding off sharp peaks and filling pits.
+/// `talus` is the greatest height difference between neighboring pixels that is left alone.
+///
+/// See <https://web.mit.edu/cesium/Public/terrain.pdf>
+pub fn erode_thermal(heights: &mut Vec2d<f32>, talus: f32, iterations: u32) {
+    let [width, height] = heightmap_size(heights);
+    let mut deltas = vec![0.0; heights.inner.len()];
+    for _ in 0..iterations {
+        deltas.iter_mut().for_each(|delta| *delta = 0.0);
+        for row in 0..height {
+            for col in 0..width {
+                let current = heights[[row, col]];
+                let neighbors = [(0, -1), (0, 1), (-1, 0), (1, 0)]
+                    .into_iter()
+                    .map(|(dy, dx)| (row as isize + dy, col as isize + dx))
+                    .filter(|&(y, x)| {
+                        (0..height as isize).contains(&y) && (0..width as isize).contains(&x)
+                    })
+                    .map(|(y, x)| (y as usize, x as usize));
+
+                // steeper than talus
+                let steep: Vec<_> = neighbors
+                    .map(|(y, x)| ([y, x], current - heights[[y, x]] - talus))
+                    .filter(|&(_, excess)| excess > 0.0)
+                    .collect();
+                let total: f32 = steep.iter().map(|(_, excess)| excess).sum();
+                let Some(max) = steep.iter().map(|&(_, excess)| excess).reduce(f32::max) else {
+                    continue;
+                };
+
+                // move half the steepest excess, split between the steep neighbors
+                let amount = max / 2.0;
+                deltas[row * width + col] -= amount;
+                for ([y, x], excess) in steep {
+                    deltas[y * width + x] += amount * excess / total;
+                }
+            }
+        }
+        heights
+            .inner
+            .iter_mut()
+            .zip(deltas.iter())
+            .for_each(|(height, delta)| *height += delta);
+    }
+}
+
+/// Flatten the heightmap to `target` where the `mask` is 1, not at all where it is 0,
+/// and blend in between. Ex: a flat area for a building or spawn. See [`circle_mask()`].
+///
+/// # Panics
+/// Debug asserts `mask` is the same size as `heights`.
+pub fn flatten(heights: &mut Vec2d<f32>, mask: &Vec2d<f32>, target: f32) {
+    debug_assert_eq!(heightmap_size(heights), heightmap_size(mask));
+    for (height, &weight) in heights.inner.iter_mut().zip(mask.inner.iter()) {
+        *height = disp::lerp(*height, target, weight.clamp(0.0, 1.0));
+    }
+}
+
+/// A mask for [`flatten()`] that is 1 within `radius` pixels of `center` (column, row),
+/// fading smoothly to 0 over `falloff` more pixels.
+pub fn circle_mask(
+    width: usize, height: usize, center: Vector2<f32>, radius: f32, falloff: f32,
+) -> Vec2d<f32> {
+    let mut mask = Vec2d::with_capacity(width * height, Vec2d::strides(width));
+    for row in 0..height {
+        for col in 0..width {
+            let dist = Vector2::new(col as f32, row as f32).dist(&center);
+            let t = if falloff <= 0.0 {
+                (dist <= radius) as u8 as f32
+            } else {
+                1.0 - ((dist - radius) / falloff).clamp(0.0, 1.0)
+            };
+            // smoothstep
+            mask.inner.push(t * t * (3.0 - 2.0 * t));
+        }
+    }
+    mask
+}
+
+/// Paint the displacements of `solids` and use the `blend` material on them.
+/// The blend should be a `WorldVertexTransition` material, that blends between
+/// 2 textures by the alphas. Ex: grass to dirt.
+///
+/// # Examples
+/// ```
+/// # use source_map_gen::generation2::{terrain, SolidOptions};
+/// # use source_map_gen::prelude::*;
+/// # let heights = terrain::parse_raw(&[0; 81], 9, false, 0.0).unwrap();
+/// # let options = &SolidOptions::new();
+/// let grass_dirt = Material::new("nature/blendgrassdirt01".into());
+/// let nodraw = Material::NODRAW;
+/// let mut tiles = terrain::from_heightmap(&heights, 512.0, 2, [&grass_dirt, &nodraw], options);
+/// // grass on flat ground, dirt on steep slopes
+/// terrain::paint(&mut tiles, &grass_dirt, |disp| {
+///     disp.paint_by_slope(30.0..45.0);
+/// });
+/// ```
+pub fn paint<'a>(
+    solids: &mut [Solid<'a>], blend: &Material<'a>, mut paint: impl FnMut(&mut Displacement),
+) {
+    let sides = solids.iter_mut().flat_map(|solid| solid.sides.iter_mut());
+    for side in sides {
+        let Some(disp) = side.disp.as_mut() else { continue };
+        paint(disp);
+        side.texture.material = blend.as_ref().material.clone();
+    }
+}
+
+/// `[width, height]` of a heightmap.
+fn heightmap_size<T>(heights: &Vec2d<T>) -> [usize; 2] {
+    let width = heights.strides[0];
+    let height = heights.inner.len().checked_div(width).unwrap_or(0);
+    [width, height]
+}
+
+/// Sample a heightmap at fractional column `u` and row `v`, clamped to the edges.
+/// See <https://en.wikipedia.org/wiki/Bilinear_interpolation>
+pub(crate) fn sample_bilinear(heights: &Vec2d<f32>, u: f32, v: f32) -> f32 {
+    let [width, height] = heightmap_size(heights);
+    let u = u.clamp(0.0, (width - 1) as f32);
+    let v = v.clamp(0.0, (height - 1) as f32);
+    let (x0, y0) = (u.floor() as usize, v.floor() as usize);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (t_x, t_y) = (u - x0 as f32, v - y0 as f32);
+
+    let top = disp::lerp(heights[[y0, x0]], heights[[y0, x1]], t_x);
+    let bottom = disp::lerp(heights[[y1, x0]], heights[[y1, x1]], t_x);
+    disp::lerp(top, bottom, t_y)
+}
+
+/// Load a grayscale PGM (`P2` or `P5`, 8 or 16 bit) heightmap.
+/// White is `scale` units high and black is 0.
+///
+/// See <https://netpbm.sourceforge.net/doc/pgm.html>
+///
+/// # Errors
+///
+/// Returns an error if the file couldn't be read or isn't a valid PGM.
+pub fn load_pgm(path: impl AsRef<Path>, scale: f32) -> io::Result<Vec2d<f32>> {
+    parse_pgm(&std::fs::read(path)?, scale)
+}
+
+/// Load a headerless, row major, grayscale heightmap that is `width` pixels wide.
+/// Pixels are 8 bit or 16 bit little endian, like `.r16` exported from most terrain tools.
+/// White is `scale` units high and black is 0.
+///
+/// # Errors
+///
+/// Returns an error if the file couldn't be read, or if it isn't whole rows of pixels.
+pub fn load_raw(
+    path: impl AsRef<Path>, width: usize, sixteen_bit: bool, scale: f32,
+) -> io::Result<Vec2d<f32>> {
+    parse_raw(&std::fs::read(path)?, width, sixteen_bit, scale)
+}
+
+/// See [`load_pgm()`].
+///
+/// # Errors
+///
+/// Returns an error if `bytes` isn't a valid PGM.
+pub fn parse_pgm(bytes: &[u8], scale: f32) -> io::Result<Vec2d<f32>> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, format!("PGM: {msg}"));
+
+    let mut index = 0;
+    let next_number = |index: &mut usize| -> io::Result<usize> {
+        let token = pgm_token(bytes, index).ok_or_else(|| invalid("unexpected end of file"))?;
+        std::str::from_utf8(token)
+            .ok()
+            .and_then(|str| str.parse().ok())
+            .ok_or_else(|| invalid("expected a number"))
+    };
+
+    let is_binary = match pgm_token(bytes, &mut index) {
+        Some(b"P2") => false,
+        Some(b"P5") => true,
+        _ => return Err(invalid("not a grayscale PGM, expected P2 or P5")),
+    };
+    let width = next_number(&mut index)?;
+    let height = next_number(&mut index)?;
+    let max_value = next_number(&mut index)?;
+    if width == 0 || height == 0 || !(1..=u16::MAX as usize).contains(&max_value) {
+        return Err(invalid("invalid size or max value"));
+    }
+
+    let count = width * height;
+    let values: Vec<usize> = if is_binary {
+        // single whitespace after max value
+        let data = bytes.get(index + 1..).unwrap_or_default();
+        if max_value < 256 {
+            data.iter().take(count).map(|&byte| byte as usize).collect()
+        } else {
+            data.chunks_exact(2)
+                .take(count)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]) as usize)
+                .collect()
+        }
+    } else {
+        (0..count).map_while(|_| next_number(&mut index).ok()).collect()
+    };
+    if values.len() != count {
+        return Err(invalid("not enough pixels"));
+    }
+
+    let max_value = max_value as f32;
+    let heights = values.into_iter().map(|value| value as f32 / max_value * scale).collect();
+    Ok(Vec2d::from_parts(heights, Vec2d::strides(width)))
+}
+
+/// The next whitespace separated token in a PGM header, skipping comments
+/// from `#` to the end of the line. Advances `index` to after the token.
+fn pgm_token<'b>(bytes: &'b [u8], index: &mut usize) -> Option<&'b [u8]> {
+    loop {
+        match bytes.get(*index)? {
+            b'#' => {
+                while bytes.get(*index).is_some_and(|&byte| byte != b'\n') {
+                    *index += 1;
+                }
+            }
+            byte if byte.is_ascii_whitespace() => *index += 1,
+            _ => break,
+        }
+    }
+    let start = *index;
+    while bytes.get(*index).is_some_and(|byte| !byte.is_ascii_whitespace()) {
+        *index += 1;
+    }
+    Some(&bytes[start..*index])
+}
+
+/// See [`load_raw()`].
+///
+/// # Errors
+///
+/// Returns an error if `bytes` isn't whole rows of pixels.
+pub fn parse_raw(
+    bytes: &[u8], width: usize, sixteen_bit: bool, scale: f32,
+) -> io::Result<Vec2d<f32>> {
+    let bytes_per_pixel = if sixteen_bit { 2 } else { 1 };
+    if width == 0 || bytes.is_empty() || !bytes.len().is_multiple_of(width * bytes_per_pixel) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Raw heightmap: not whole rows of pixels",
+        ));
+    }
+
+    let heights = if sixteen_bit {
+        let max = u16::MAX as f32;
+        bytes
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]) as f32 / max * scale)
+            .collect()
+    } else {
+        bytes.iter().map(|&byte| byte as f32 / u8::MAX as f32 * scale).collect()
+    };
+    Ok(Vec2d::from_parts(heights, Vec2d::strides(width)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation2::noise::{Basis, Fractal, Noise};
+    use approx::abs_diff_eq;
+
+    #[test]
+    fn noise_tiles() {
+        let mat = Material::new("DEV/DEV_MEASUREWALL01C".into());
+        let options = &SolidOptions::default().allow_frac();
+        let noise = Noise::new(42);
+        let fractal = Fractal::new().frequency(1.0 / 512.0);
+
+        // 2 tiles side by side filled directly, noise at the world position
+        let mut points = Vec::new();
+        for x in [0.0, 512.0] {
+            let bounds =
+                Bounds::new(Vector3::new(x, 0.0, -16.0), Vector3::new(x + 512.0, 512.0, 0.0));
+            let mut tile = cube(&bounds, &[&mat; 6], options);
+            let mut disp = Displacement::new_plane(tile.sides[0].plane.clone(), 9);
+            disp.set_heights(|point| noise.warped(point.x, point.y, &fractal, 64.0) * 256.0);
+            points.push(disp.points());
+            tile.sides[0].disp = Some(disp);
+        }
+        for i in 0..9 {
+            assert_eq!(points[0][[i, 8]], points[1][[i, 0]]);
+        }
+        assert!(points[0].inner.iter().any(|point| point.z.abs() > 1.0));
+    }
+
+    #[test]
+    fn erode_flatten() {
+        let noise = Noise::new(7);
+        let fractal = Fractal::new().basis(Basis::Simplex).frequency(1.0 / 256.0);
+        let mut heights =
+            heightmap_from_fn(33, 33, 32.0, |x, y| noise.ridged(x, y, &fractal) * 512.0);
+        let max_slope = |heights: &Vec2d<f32>| {
+            let rows = heights.rows().collect::<Vec<_>>();
+            rows.iter()
+                .flat_map(|row| row.windows(2).map(|pair| (pair[0] - pair[1]).abs()))
+                .fold(0.0, f32::max)
+        };
+        let total: f32 = heights.inner.iter().sum();
+        let before = max_slope(&heights);
+        erode_thermal(&mut heights, 8.0, 20);
+        assert!(max_slope(&heights) < before);
+        // erosion only moves material around
+        let after: f32 = heights.inner.iter().sum();
+        assert!((total - after).abs() / total < 1e-3);
+
+        let mask = circle_mask(33, 33, Vector2::new(16.0, 16.0), 4.0, 4.0);
+        flatten(&mut heights, &mask, 100.0);
+        assert_eq!(100.0, heights[[16, 16]]);
+        assert_eq!(100.0, heights[[16, 20]]);
+        assert_ne!(100.0, heights[[0, 0]]);
+    }
+
+    #[test]
+    fn pgm() {
+        let ascii = b"P2\n# a comment\n3 2\n4\n0 1 2\n3 4 0\n";
+        let heights = parse_pgm(ascii, 8.0).unwrap();
+        assert_eq!(vec![0.0, 2.0, 4.0, 6.0, 8.0, 0.0], heights.inner);
+        assert_eq!(6.0, heights[[1, 0]]);
+
+        let binary = [b"P5 3 2 255\n".as_slice(), &[0, 51, 102, 153, 204, 255]].concat();
+        let heights = parse_pgm(&binary, 100.0).unwrap();
+        let truth = [0.0, 20.0, 40.0, 60.0, 80.0, 100.0];
+        assert!(heights.inner.iter().zip(truth).all(|(h, truth)| abs_diff_eq!(
+            *h,
+            truth,
+            epsilon = 1e-3
+        )));
+
+        let wide = [b"P5 1 2 65535\n".as_slice(), &[0xff, 0xff, 0, 0]].concat();
+        assert_eq!(vec![1.0, 0.0], parse_pgm(&wide, 1.0).unwrap().inner);
+
+        assert!(parse_pgm(b"P6 1 1 255\n\0\0\0", 1.0).is_err());
+        assert!(parse_pgm(b"P2 2 2 255\n0 0 0", 1.0).is_err());
+    }
+
+    #[test]
+    fn raw() {
+        let heights = parse_raw(&[0, 255, 0, 255], 2, false, 2.0).unwrap();
+        assert_eq!(vec![0.0, 2.0, 0.0, 2.0], heights.inner);
+        let heights = parse_raw(&[0xff, 0xff, 0, 0], 2, true, 2.0).unwrap();
+        assert_eq!(vec![2.0, 0.0], heights.inner);
+        assert!(parse_raw(&[0, 0, 0], 2, false, 1.0).is_err());
+    }
+
+    #[test]
+    fn tiles_match() {
+        let mat = Material::new("DEV/DEV_MEASUREWALL01C".into());
+        let nodraw = Material::NODRAW;
+        let options = &SolidOptions::default().allow_frac();
+        // 9x9 pixels is exactly 2x2 power 2 tiles
+        let width = 9;
+        let heights = (0..width * width).map(|i| ((i * 7) % 13) as f32 * 8.0 - 32.0).collect();
+        let heights = Vec2d::from_parts(heights, Vec2d::strides(width));
+        let tiles = from_heightmap(&heights, 256.0, 2, [&mat, &nodraw], options);
+        assert_eq!(4, tiles.len());
+
+        let points: Vec<_> =
+            tiles.iter().map(|tile| tile.sides[0].disp.as_ref().unwrap().points()).collect();
+        let len = 5;
+        for i in 0..len {
+            // west and east tiles share a column
+            assert_eq!(points[0][[i, len - 1]], points[1][[i, 0]]);
+            assert_eq!(points[2][[i, len - 1]], points[3][[i, 0]]);
+            // south and north tiles share a row
+            assert_eq!(points[0][[len - 1, i]], points[2][[0, i]]);
+            assert_eq!(points[1][[len - 1, i]], points[3][[0, i]]);
+        }
+
+        // south west corner is the bottom left of the heightmap, north east is the top right
+        assert_eq!(Vector3::new(0.0, 0.0, heights[[8, 0]]), points[0][[0, 0]]);
+        assert_eq!(Vector3::new(512.0, 512.0, heights[[0, 8]]), points[3][[4, 4]]);
+    }
+}
@@ -5,12 +5,10 @@ mod cie;
 
 pub use cie::*;
 
-use rgb::RGB;
+use crate::source::ColorBrightness;
+use rgb::{ComponentMap, RGB};
 use std::ops::{Deref, DerefMut};
 
-//https://cormusa.org/wp-content/uploads/2018/04/CORM_2011_Calculation_of_CCT_and_Duv_and_Practical_Conversion_Formulae.pdf
-// TODO: clean
-
 /// Linear sRGB in the range 0 to 1
 #[derive(Clone, Debug, Default, PartialEq, PartialOrd)]
 pub struct SrgbLinear(pub RGB<f64>);
@@ -40,59 +38,28 @@ impl SrgbLinear {
         Self(rgb)
     }
 
-    // /// Convert from sRGB in the range 0 to 255 into linear sRGB in the range 0 to 1.
-    // pub fn from_srgb(srgb: RGB<u8>) -> Self {
-    //     let RGB { r, g, b } = srgb;
-
-    //     let rgb = RGB {
-    //         r: (r as f64 / 255.0).powf(2.2),
-    //         g: (g as f64 / 255.0).powf(2.2),
-    //         b: (b as f64 / 255.0).powf(2.2),
-    //     };
-    //     Self(rgb)
-    // }
-
-    // /// To normal sRGB in range 0 to 255.
-    // /// TODO: verify
-    // pub fn to_srgb(&self) -> RGB<u8> {
-    //     /// 1.0 / 2.2
-    //     const INVERSE_GAMMA: f64 = 0.45454545454545454;
-    //     let r = (self.r.powf(INVERSE_GAMMA) * 255.0) as u8;
-    //     let g = (self.g.powf(INVERSE_GAMMA) * 255.0) as u8;
-    //     let b = (self.b.powf(INVERSE_GAMMA) * 255.0) as u8;
-    //     RGB { r, g, b }
-    // }
-
-    /// Convert from sRGB in the range 0 to 255 into linear sRGB in the range 0 to 1.
+    /// To normal, gamma corrected sRGB in the range 0 to 255.
+    /// Channels outside of 0 to 1 are clamped.
     pub fn to_srgb(&self) -> RGB<u8> {
-        // let RGB { r, g, b } = self;
-
-        // let rgb = RGB {
-        //     r: (r as f64 / 255.0).powf(2.2),
-        //     g: (g as f64 / 255.0).powf(2.2),
-        //     b: (b as f64 / 255.0).powf(2.2),
-        // };
-        // Self(rgb)
-        // eprintln!("{:?}", self);
-        const INVERSE_GAMMA: f64 = 0.45454545454545454;
-
-        let r = ((self.r).powf(INVERSE_GAMMA) * 255.0) as u8;
-        let g = ((self.g).powf(INVERSE_GAMMA) * 255.0) as u8;
-        let b = ((self.b).powf(INVERSE_GAMMA) * 255.0) as u8;
-        RGB { r, g, b }
+        let gamma = |c: f64| {
+            let c = c.clamp(0.0, 1.0);
+            let c = if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+            (c * 255.0).round() as u8
+        };
+        RGB { r: gamma(self.r), g: gamma(self.g), b: gamma(self.b) }
     }
 
-    /// To normal sRGB in range 0 to 255.
-    /// TODO: verify
+    /// From normal, gamma corrected sRGB in the range 0 to 255.
     pub fn from_srgb(srgb: &RGB<u8>) -> Self {
-        /// 1.0 / 2.2
-        // const INVERSE_GAMMA: f64 = 0.45454545454545454;
-        // const INVERSE_GAMMA: f64 = 0.45454545454545454;
-        const GAMMA: f64 = 2.2;
-        let r = (srgb.r as f64 / 255.0).powf(GAMMA);
-        let g = (srgb.g as f64 / 255.0).powf(GAMMA);
-        let b = (srgb.b as f64 / 255.0).powf(GAMMA);
-        SrgbLinear(RGB { r, g, b })
+        let linear = |c: u8| {
+            let c = c as f64 / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        SrgbLinear(RGB { r: linear(srgb.r), g: linear(srgb.g), b: linear(srgb.b) })
     }
 
     /// Convert to CIE XYZ. See [`Xyz`]
@@ -102,16 +69,6 @@ impl SrgbLinear {
     /// <https://en.wikipedia.org/wiki/CIE_1931_color_space>.
     pub fn to_xyz(&self) -> Xyz {
         let RGB { r, g, b } = self.0;
-        // fn c_to_linear(c: f64) -> f64 {
-        //     if c <= 0.04045 {
-        //         c / 12.92
-        //     } else {
-        //         ((c + 0.055) / 1.055).powf(2.4)
-        //     }
-        // }
-        // let r = c_to_linear(r);
-        // let g = c_to_linear(g);
-        // let b = c_to_linear(b);
         let r = r * 100.0;
         let g = g * 100.0;
         let b = b * 100.0;
@@ -191,132 +148,53 @@ fn temp_to_rgb_simple(kelvin: f64) -> (u8, u8, u8) {
     (red, green, blue)
 }
 
-// /// Convert from correlated color temperature (CCT) to CIE 1931 xy.
-// /// Accurate from ~4,000K to ~25,000K
-// /// Adapted from <http://www.brucelindbloom.com/index.html?Eqn_T_to_xy.html>.
-// pub fn cct_to_xy(cct: f64) -> Xy {
-//     let cct = cct;
-//     let cct_2 = cct * cct;
-//     let cct_3 = cct_2 * cct;
-//     let ten_3_t = 10e3 / cct;
-//     let ten_6_t_2 = 10e6 / cct_2;
-//     let ten_9_t_3 = 10e9 / cct_3;
-//     let x = if cct <= 7000.0 {
-//         // accurate down to 4000k
-//         -4.6070 * ten_9_t_3 + 2.9678 * ten_6_t_2 + 0.9911 * ten_3_t + 0.244063
-//     } else {
-//         // accurate up to 25000k
-//         dbg!("e");
-//         -2.0064 * ten_9_t_3 + 1.9018 * ten_6_t_2 + 0.24748 * ten_3_t + 0.237040
-//     };
-//     let x = x / 10.0; // why is this nessessary??
-//     let y = -3.000 * x * x + 2.870 * x - 0.275;
-//     // dbg!(x,y, cct);
-//     Xy { x, y }
-// }
-
-// WRONG
-// pub fn cct_to_xy(cct: f64) -> Xy {
-//     let t = cct;
-//     let t_2 = t * t;
-//     let t_3 = t_2 * t;
-//     let ten_9_t_3 = 10e9 / t_3;
-//     let ten_6_t_2 = 10e6 / t_2;
-//     let ten_3_t = 10e3 / t;
-
-//     let x = if t <= 4000.0 {
-//         // accurate down to 1667K
-//         // assert!(t >= 1667.0);
-//         // assert!(t <= 4000.0);
-//         eprint!("x1 ");
-//         ( -0.2661239 * ten_9_t_3 - 0.2343589 * ten_6_t_2 + 0.8776956 * ten_3_t + 0.179910 )
-//     } else {
-//         // accurate up to 25000k
-//         // assert!(t >= 4000.0);
-//         // assert!(t <= 25000.0);
-//         eprint!("x2 ");
-//         ( -3.0258469 * ten_9_t_3 + 2.1070379 * ten_6_t_2 + 0.2226347 * ten_3_t + 0.240390 )
-//     };
-
-//     let x_2 = x * x;
-//     let x_3 = x_2 * x;
-
-//     let y = if t <= 2222.0 {
-//         // accurate down to 1667K
-//         assert!(t >= 1667.0);
-//         assert!(t <= 2222.0);
-//         eprintln!("y1");
-//         -1.1063814 * x_3 - 1.34811020 * x_2 + 2.18555832 * x - 0.20219683
-//     } else if t <= 4000.0 {
-//         assert!(t >= 2222.0);
-//         assert!(t <= 4000.0);
-//         // accurate
-//         eprintln!("y2");
-//         -0.9549476 * x_3 - 1.37418593 * x_2 + 2.09137015 * x - 0.16748867
-//     } else {
-//         assert!(t >= 4000.0);
-//         // assert!(t <= 25000.0);
-//         // accurate up to 25000K
-//         eprintln!("y3");
-//         3.0817580 * x_3 - 5.87338670 * x_2 + 3.75112997 * x - 0.37001483
-//     };
-
-//     if x > 0.75 || y > 0.8 {
-//         eprintln!("imaginary");
-//     }
-
-//     dbg!(x,y);
-
-//     Xy { x, y }
-// }
-
 /// Convert sRGB to corelated color temperature (cct).
 /// Input is your normal, gamma corrected (not linear) sRGB values.
 pub fn rgb_to_temp(srgb: RGB<u8>) -> f64 {
-    // let rgb = Rgb { r: todo!(), g: todo!(), b: todo!() };
-    // let mut rgb = SrgbLinear::from_linear_u8(rgb);
+    let (cct, _) = rgb_to_temp_duv(srgb);
+    cct
+}
 
-    if srgb.r == 230 {
-        dbg!(&srgb);
-    }
-    let rgb = SrgbLinear::from_srgb(&srgb);
-    if srgb.r == 230 {
-        dbg!(&rgb);
-    }
+/// Convert sRGB to corelated color temperature (cct) and D<sub>uv</sub>.
+/// Input is your normal, gamma corrected (not linear) sRGB values.
+/// See [`xy_to_cct_duv`].
+pub fn rgb_to_temp_duv(srgb: RGB<u8>) -> (f64, f64) {
+    let (xy, _) = SrgbLinear::from_srgb(&srgb).to_xyz().to_xyy();
+    xy_to_cct_duv(xy)
+}
 
-    // let input = srgb;
-    // let rgb: SrgbLinear = SrgbLinear::from_linear(srgb);
-    // let mut rgb = srgb_to_linear(rgb);
-    // rgb.normalize_mut();
-    let XYZ = rgb.to_xyz();
-    if srgb.r == 230 {
-        dbg!(&XYZ);
-    }
-    let (xy, _) = XYZ.to_xyy();
-    if srgb.r == 230 {
-        dbg!(&xy);
-    }
+/// The sRGB color of a color temperature, with the brightest channel at 255.
+pub fn temp_to_rgb(cct: f64) -> RGB<u8> {
+    temp_duv_to_rgb(cct, 0.0)
+}
 
-    let (cct, _) = xy_to_cct_duv(xy);
-    if srgb.r == 230 {
-        dbg!(&cct);
-    }
-    cct
+/// The sRGB color of a color temperature and tint, with the brightest channel at 255.
+/// See [`cct_duv_to_xy`].
+pub fn temp_duv_to_rgb(cct: f64, duv: f64) -> RGB<u8> {
+    temp_duv_to_linear(cct, duv).to_srgb()
 }
 
-pub fn temp_to_rgb(cct: f64) -> RGB<u8> {
-    if cct as i32 == 3805 {
-        eprintln!("e\n");
-        let xy = dbg!(cct_to_xy(cct));
-        let xyz = dbg!(xy.to_xyz(50.0));
-        let rgb = dbg!(xyz.to_rgb());
-    }
-    let xy = cct_to_xy(cct);
-    let xyz = xy.to_xyz(100.0);
-    let mut rgb = xyz.to_rgb();
-    // dbg!(&xy);
+/// Normalized linear sRGB of a color temperature and tint. Out of gamut channels are 0.
+fn temp_duv_to_linear(cct: f64, duv: f64) -> SrgbLinear {
+    let mut rgb = cct_duv_to_xy(cct, duv).to_xyz(100.0).to_rgb();
+    rgb.0 = rgb.map(|c| c.max(0.0));
     rgb.normalize_mut();
-    rgb.to_srgb()
+    rgb
+}
+
+/// A `_light` value of a color temperature and tint, with `brightness` in the brightest channel.
+///
+/// Source converts colors with a gamma of 2.2, not sRGB's, see [`ColorBrightness::to_linear`].
+pub fn temp_to_light(cct: f64, duv: f64, brightness: f64) -> ColorBrightness {
+    let rgb = temp_duv_to_linear(cct, duv);
+    ColorBrightness::from_linear(rgb.map(|c| c * brightness))
+}
+
+/// A `_lightHDR` value of a color temperature and tint, for a `_lightscaleHDR` of `scale`.
+/// See [`temp_to_light`].
+pub fn temp_to_light_hdr(cct: f64, duv: f64, brightness: f64, scale: f64) -> ColorBrightness {
+    let rgb = temp_duv_to_linear(cct, duv);
+    ColorBrightness::from_linear_hdr(rgb.map(|c| c * brightness), scale)
 }
 
 #[cfg(test)]
@@ -372,7 +250,7 @@ mod tests {
         let new_rgb = new_rgb.to_srgb();
         dbg!(&new_rgb);
         assert!(abs_diff_eq!(255, new_rgb.r, epsilon = 0));
-        assert!(abs_diff_eq!(205, new_rgb.g, epsilon = 0));
+        assert!(abs_diff_eq!(206, new_rgb.g, epsilon = 0));
         assert!(abs_diff_eq!(154, new_rgb.b, epsilon = 0));
 
         // back to rgb, scaled brightness
@@ -394,5 +272,140 @@ mod tests {
         assert!(abs_diff_eq!(input.b, new_rgb2.b, epsilon = 20));
     }
 
-    // fn
+    /// Planckian locus from <https://en.wikipedia.org/wiki/Planckian_locus> and
+    /// <https://www.waveformlighting.com/files/blackBodyLocus_1.txt>.
+    #[test]
+    fn planckian_locus() {
+        let table = [
+            (1000.0, 0.6528, 0.3444),
+            (2000.0, 0.5267, 0.4133),
+            (3000.0, 0.4369, 0.4041),
+            (4000.0, 0.3805, 0.3768),
+            (5000.0, 0.3451, 0.3516),
+            (6500.0, 0.3135, 0.3237),
+            (10000.0, 0.2807, 0.2884),
+            (20000.0, 0.2565, 0.2574),
+            // illuminant A, defined with c2 = 1.435e-2 instead of 1.4388e-2
+            (2856.0 * 1.4388 / 1.435, 0.44757, 0.40745),
+        ];
+        for (cct, x, y) in table {
+            let xy = cct_to_xy(cct);
+            assert!(abs_diff_eq!(x, xy.x, epsilon = 5e-4), "{cct}K: {xy:?}");
+            assert!(abs_diff_eq!(y, xy.y, epsilon = 5e-4), "{cct}K: {xy:?}");
+        }
+
+        // continuous and heading towards infinity, (0.2399, 0.2342)
+        let [a, b] = [14999.9, 15000.1].map(cct_to_xy);
+        assert!(abs_diff_eq!(a.x, b.x, epsilon = 1e-6) && abs_diff_eq!(a.y, b.y, epsilon = 1e-6));
+        let xy = cct_to_xy(40000.0);
+        assert!(xy.x > 0.2399 && xy.x < 0.2565 && xy.y > 0.2342 && xy.y < 0.2574, "{xy:?}");
+        let xy = cct_to_xy(1e12);
+        assert!(
+            abs_diff_eq!(0.2399, xy.x, epsilon = 1e-4)
+                && abs_diff_eq!(0.2342, xy.y, epsilon = 1e-4)
+        );
+    }
+
+    #[test]
+    fn duv() {
+        // D65: 6504K, +0.0032
+        let (cct, duv) = xy_to_cct_duv(Xy::D65);
+        assert!(abs_diff_eq!(6504.0, cct, epsilon = 5.0), "{cct}");
+        assert!(abs_diff_eq!(0.0032, duv, epsilon = 1e-4), "{duv}");
+        let xy = cct_duv_to_xy(6504.0, 0.0032);
+        assert!(
+            abs_diff_eq!(Xy::D65.x, xy.x, epsilon = 2e-4)
+                && abs_diff_eq!(Xy::D65.y, xy.y, epsilon = 2e-4)
+        );
+
+        for cct in [1000.0, 1500.0, 2700.0, 4000.0, 6500.0, 10000.0, 20000.0, 40000.0] {
+            for duv in [-0.02, -0.01, 0.0, 0.01, 0.02] {
+                let (new_cct, new_duv) = xy_to_cct_duv(cct_duv_to_xy(cct, duv));
+                assert!(
+                    abs_diff_eq!(cct, new_cct, epsilon = cct * 2e-3),
+                    "{cct}K {duv}: {new_cct}K"
+                );
+                assert!(abs_diff_eq!(duv, new_duv, epsilon = 1e-9), "{cct}K {duv}: {new_duv}");
+            }
+        }
+
+        // green above, magenta below
+        let [green, magenta] = [0.02, -0.02].map(|duv| temp_duv_to_rgb(5000.0, duv));
+        assert!(green.g > magenta.g && green.r < magenta.r && green.b < magenta.b);
+    }
+
+    /// <http://www.brucelindbloom.com/index.html?Eqn_ChromAdapt.html>
+    #[test]
+    fn adaptation() {
+        let white = Xy::D65.to_xyz(100.0).adapt(&Xy::D65, &Xy::D50);
+        assert!(abs_diff_eq!(96.422, white.X, epsilon = 0.02));
+        assert!(abs_diff_eq!(100.0, white.Y, epsilon = 0.02));
+        assert!(abs_diff_eq!(82.521, white.Z, epsilon = 0.02));
+
+        // first column of the D65 to D50 matrix
+        let red = Xyz { X: 100.0, Y: 0.0, Z: 0.0 }.adapt(&Xy::D65, &Xy::D50);
+        assert!(abs_diff_eq!(104.7811, red.X, epsilon = 0.02));
+        assert!(abs_diff_eq!(2.9542, red.Y, epsilon = 0.02));
+        assert!(abs_diff_eq!(-0.9235, red.Z, epsilon = 0.02));
+
+        let back = red.adapt(&Xy::D50, &Xy::D65);
+        assert!(
+            abs_diff_eq!(100.0, back.X, epsilon = 1e-3)
+                && abs_diff_eq!(0.0, back.Y, epsilon = 1e-3)
+        );
+    }
+
+    #[test]
+    fn srgb() {
+        let white = Xy::D65.to_xyz(100.0).to_rgb();
+        for c in [white.r, white.g, white.b] {
+            assert!(abs_diff_eq!(1.0, c, epsilon = 1e-3), "{white:?}");
+        }
+        assert_eq!(RGB::new(255, 255, 255), white.to_srgb());
+        // 0.5 linear is 188 in sRGB, not 186 with a gamma of 2.2
+        assert_eq!(RGB::new(188, 0, 255), SrgbLinear(RGB::new(0.5, -0.1, 1.1)).to_srgb());
+        for c in [0, 10, 128, 255] {
+            let rgb = RGB::new(c, c, c);
+            assert_eq!(rgb, SrgbLinear::from_srgb(&rgb).to_srgb());
+        }
+
+        let (cct, duv) = rgb_to_temp_duv(RGB::new(255, 255, 255));
+        assert!(
+            abs_diff_eq!(6504.0, cct, epsilon = 10.0) && abs_diff_eq!(0.0032, duv, epsilon = 3e-4)
+        );
+        let rgb = temp_duv_to_rgb(6504.0, 0.0032);
+        assert!(rgb.r >= 254 && rgb.g >= 254 && rgb.b >= 254, "{rgb:?}");
+        // warm and cool
+        assert_eq!(255, temp_to_rgb(2700.0).r);
+        assert!(temp_to_rgb(2700.0).b < 150);
+        assert_eq!(255, temp_to_rgb(20000.0).b);
+        assert!(temp_to_rgb(20000.0).r < 200);
+    }
+
+    #[test]
+    fn source_light() {
+        // vrad: (c / 255) ^ 2.2 * brightness
+        let light = ColorBrightness::new(255, 128, 0, 200);
+        let linear = light.to_linear();
+        assert!(abs_diff_eq!(200.0, linear.r, epsilon = 1e-9));
+        assert!(abs_diff_eq!(43.90, linear.g, epsilon = 0.01));
+        assert_eq!(0.0, linear.b);
+        assert_eq!(light, ColorBrightness::from_linear(linear));
+        assert_eq!(
+            ColorBrightness::new(255, 128, 0, 400),
+            ColorBrightness::from_linear_hdr(linear, 0.5)
+        );
+        assert_eq!(
+            ColorBrightness::new(0, 0, 0, 0),
+            ColorBrightness::from_linear(RGB::new(0.0, -1.0, 0.0))
+        );
+
+        let light = temp_to_light(6504.0, 0.0032, 300.0);
+        assert_eq!(300, light.a);
+        assert!(light.r >= 254 && light.g >= 254 && light.b >= 254, "{light}");
+        let light = temp_to_light(2700.0, 0.0, 300.0);
+        let hdr = temp_to_light_hdr(2700.0, 0.0, 300.0, 0.5);
+        assert_eq!((light.r, light.g, light.b, 600), (hdr.r, hdr.g, hdr.b, hdr.a));
+        assert!(abs_diff_eq!(300.0, light.to_linear().r, epsilon = 1e-9));
+    }
 }
@@ -0,0 +1,310 @@
+//! Time of day lighting. Keyframes of sun, ambient, fog and shadow colors over a day.
+
+use std::f64::consts::PI;
+
+use rgb::RGB8;
+
+use crate::{
+    light::{colorspace::temp_to_rgb, pitch_to_rgb, GlobalLighting},
+    map::Angles,
+    source::{ColorBrightness, EnvFogController, GlobalLightShadows, ShadowControl},
+};
+
+/// Lighting at an hour of the day. Interpolated by [`DayCycle`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct LightKeyframe {
+    /// Hour of the day, 0 to 24.
+    pub hour: f64,
+    /// Color and brightness of the sun, or moon at night.
+    pub sun_color: ColorBrightness,
+    /// Color and brightness of the sky.
+    pub amb_color: ColorBrightness,
+    pub fog_color: RGB8,
+    /// Distance fog starts.
+    pub fog_start: f64,
+    /// Distance fog is fully dense.
+    pub fog_end: f64,
+    /// Color of dynamic shadows, see [`ShadowControl`].
+    pub shadow_color: RGB8,
+}
+
+impl LightKeyframe {
+    /// Linearly interpolate all colors and distances, keeps `self.hour`.
+    pub fn lerp(&self, other: &Self, t: f64) -> Self {
+        let lerp = |a: f64, b: f64| a + (b - a) * t;
+        let lerp_rgb = |a: &RGB8, b: &RGB8| {
+            let [r, g, b] = [(a.r, b.r), (a.g, b.g), (a.b, b.b)]
+                .map(|(a, b)| lerp(a as f64, b as f64).round() as u8);
+            RGB8::new(r, g, b)
+        };
+        let lerp_color = |a: &ColorBrightness, b: &ColorBrightness| {
+            let RGB8 { r, g, b: blue } =
+                lerp_rgb(&RGB8::new(a.r, a.g, a.b), &RGB8::new(b.r, b.g, b.b));
+            ColorBrightness::new(r, g, blue, lerp(a.a as f64, b.a as f64).round() as i32)
+        };
+        Self {
+            hour: self.hour,
+            sun_color: lerp_color(&self.sun_color, &other.sun_color),
+            amb_color: lerp_color(&self.amb_color, &other.amb_color),
+            fog_color: lerp_rgb(&self.fog_color, &other.fog_color),
+            fog_start: lerp(self.fog_start, other.fog_start),
+            fog_end: lerp(self.fog_end, other.fog_end),
+            shadow_color: lerp_rgb(&self.shadow_color, &other.shadow_color),
+        }
+    }
+}
+
+/// Common lighting conditions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LightingPreset {
+    /// Clear sky, sun high up.
+    Noon,
+    /// Low warm sun before sunset.
+    GoldenHour,
+    /// Grey, soft, low contrast daylight.
+    Overcast,
+    /// Clear night lit by a dim blue moon.
+    Moonlit,
+}
+
+impl LightingPreset {
+    pub fn lighting<S: Default>(self) -> GlobalLightShadows<S> {
+        match self {
+            Self::Noon => DayCycle::clear().at(12.0),
+            Self::GoldenHour => DayCycle::clear().at(DayCycle::GOLDEN_HOUR),
+            Self::Overcast => DayCycle::overcast().at(12.0),
+            Self::Moonlit => DayCycle::clear().at(0.0),
+        }
+    }
+}
+
+/// Lighting over a day, keyframes are interpolated in between.
+///
+/// The sun goes in an arc from east to west between `sunrise` and `sunset`,
+/// the moon follows the same arc at night.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DayCycle {
+    /// Sorted by hour.
+    pub keyframes: Vec<LightKeyframe>,
+    /// Hour the sun rises in the east.
+    pub sunrise: f64,
+    /// Hour the sun sets in the west.
+    pub sunset: f64,
+    /// Degrees above the horizon of the sun at noon, and moon at midnight.
+    pub max_elevation: f64,
+    /// Yaw the light points at noon. 90 (+Y) if the sun is in the south, like north of the
+    /// tropics. See [`time`](super::time) for +Y being north.
+    pub noon_yaw: f64,
+}
+
+impl Default for DayCycle {
+    fn default() -> Self {
+        Self::clear()
+    }
+}
+
+impl DayCycle {
+    /// Hour of warm low sunlight, before sunset.
+    pub const GOLDEN_HOUR: f64 = 17.5;
+    /// The lowest the sun or moon is lit from, so the ground is still lit.
+    const MIN_ELEVATION: f64 = 5.0;
+
+    /// Clear sky. Warm dawn and dusk, white noon, dark blue night.
+    pub fn clear() -> Self {
+        let (sunrise, sunset, max_elevation) = (6.0, 18.0, 60.0);
+        let sun = |elevation: f64, brightness| {
+            let RGB8 { r, g, b } = pitch_to_rgb(elevation);
+            ColorBrightness::new(r, g, b, brightness)
+        };
+        let sky = |temp: f64, brightness| {
+            let RGB8 { r, g, b } = temp_to_rgb(temp);
+            ColorBrightness::new(r, g, b, brightness)
+        };
+        let fog = |temp: f64, scale: f64| scale_rgb(temp_to_rgb(temp), scale);
+        let grey = |value| RGB8::new(value, value, value);
+
+        let keyframes = vec![
+            LightKeyframe {
+                hour: 0.0,
+                sun_color: sun(-max_elevation, 20),
+                amb_color: sky(12000.0, 10),
+                fog_color: fog(12000.0, 0.1),
+                fog_start: 256.0,
+                fog_end: 3072.0,
+                shadow_color: grey(30),
+            },
+            LightKeyframe {
+                hour: 5.0,
+                sun_color: sun(-10.0, 10),
+                amb_color: sky(9000.0, 10),
+                fog_color: fog(8000.0, 0.2),
+                fog_start: 256.0,
+                fog_end: 3072.0,
+                shadow_color: grey(40),
+            },
+            LightKeyframe {
+                hour: 7.0,
+                sun_color: sun(15.0, 250),
+                amb_color: sky(8000.0, 30),
+                fog_color: fog(3500.0, 0.7),
+                fog_start: 512.0,
+                fog_end: 6144.0,
+                shadow_color: grey(100),
+            },
+            LightKeyframe {
+                hour: 12.0,
+                sun_color: sun(max_elevation, 400),
+                amb_color: sky(10000.0, 50),
+                fog_color: fog(9000.0, 0.8),
+                fog_start: 1024.0,
+                fog_end: 16384.0,
+                shadow_color: grey(128),
+            },
+            LightKeyframe {
+                hour: Self::GOLDEN_HOUR,
+                sun_color: sun(8.0, 300),
+                amb_color: sky(7000.0, 35),
+                fog_color: fog(3000.0, 0.7),
+                fog_start: 512.0,
+                fog_end: 8192.0,
+                shadow_color: grey(100),
+            },
+            LightKeyframe {
+                hour: 19.0,
+                sun_color: sun(-5.0, 30),
+                amb_color: sky(6000.0, 15),
+                fog_color: fog(5000.0, 0.3),
+                fog_start: 256.0,
+                fog_end: 4096.0,
+                shadow_color: grey(50),
+            },
+        ];
+        Self { keyframes, sunrise, sunset, max_elevation, noon_yaw: 90.0 }
+    }
+
+    /// Cloudy all day. Grey sun and sky, close fog and faint shadows.
+    pub fn overcast() -> Self {
+        let mut cycle = Self::clear();
+        for keyframe in cycle.keyframes.iter_mut() {
+            let grey = |color: &ColorBrightness, scale: f64| {
+                let value = ((color.r as f64 + color.g as f64 + color.b as f64) / 3.0) as u8;
+                ColorBrightness::new(value, value, value, (color.a as f64 * scale) as i32)
+            };
+            // light from the sun goes to the sky
+            keyframe.amb_color = grey(&keyframe.amb_color, 1.0);
+            keyframe.amb_color.a += keyframe.sun_color.a / 4;
+            keyframe.sun_color = grey(&keyframe.sun_color, 0.3);
+            let RGB8 { r, g, b } = keyframe.fog_color;
+            let value = ((r as u32 + g as u32 + b as u32) / 3) as u8;
+            keyframe.fog_color = RGB8::new(value, value, value);
+            keyframe.fog_start *= 0.5;
+            keyframe.fog_end *= 0.4;
+            keyframe.shadow_color = scale_rgb(keyframe.shadow_color, 1.5);
+        }
+        cycle
+    }
+
+    /// Interpolated keyframe at `hour`, wrapping around midnight.
+    pub fn keyframe_at(&self, hour: f64) -> LightKeyframe {
+        let hour = hour.rem_euclid(24.0);
+        let (Some(first), Some(last)) = (self.keyframes.first(), self.keyframes.last()) else {
+            panic!("DayCycle needs at least one keyframe");
+        };
+        let prev = self.keyframes.iter().rev().find(|key| key.hour <= hour).unwrap_or(last);
+        let next = self.keyframes.iter().find(|key| key.hour > hour).unwrap_or(first);
+        let span = (next.hour - prev.hour).rem_euclid(24.0);
+        let t = if span == 0.0 { 0.0 } else { (hour - prev.hour).rem_euclid(24.0) / span };
+        LightKeyframe { hour, ..prev.lerp(next, t) }
+    }
+
+    /// Direction of the light from the sun at `hour`, or moon at night.
+    pub fn sun_dir(&self, hour: f64) -> Angles {
+        let hour = hour.rem_euclid(24.0);
+        let day = self.sunset - self.sunrise;
+        // 0 to 1 across the sky
+        let t = match (hour - self.sunrise).rem_euclid(24.0) {
+            since_sunrise if since_sunrise <= day => since_sunrise / day,
+            since_sunrise => (since_sunrise - day) / (24.0 - day),
+        };
+        let elevation = (self.max_elevation * (PI * t).sin()).max(Self::MIN_ELEVATION);
+        let yaw = (self.noon_yaw + 90.0 - 180.0 * t).rem_euclid(360.0);
+        Angles { pitch: elevation, yaw, roll: 0.0 }
+    }
+
+    /// All global lighting at `hour`.
+    pub fn at<S: Default>(&self, hour: f64) -> GlobalLightShadows<S> {
+        let keyframe = self.keyframe_at(hour);
+        let sun_dir = self.sun_dir(hour);
+        GlobalLightShadows {
+            light: GlobalLighting {
+                sun_color: keyframe.sun_color,
+                sun_color_hdr: None,
+                sun_dir: sun_dir.clone(),
+                sun_spread_angle: 5.0,
+                amb_color: keyframe.amb_color,
+                amb_color_hdr: None,
+                amb_dir: sun_dir,
+                dir_lights: Vec::new(),
+            },
+            shadows: ShadowControl { shadow_color: keyframe.shadow_color, ..Default::default() },
+            fog: Some(EnvFogController {
+                start: keyframe.fog_start,
+                end: keyframe.fog_end,
+                far_z: -1,
+                primary_color: keyframe.fog_color,
+                secondary_color: keyframe.fog_color,
+                ..Default::default()
+            }),
+            sun: None,
+        }
+    }
+}
+
+fn scale_rgb(color: RGB8, scale: f64) -> RGB8 {
+    let scale = |c: u8| (c as f64 * scale).round().min(255.0) as u8;
+    RGB8::new(scale(color.r), scale(color.g), scale(color.b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyframes() {
+        let cycle = DayCycle::clear();
+        let noon = cycle.keyframe_at(12.0);
+        assert_eq!(cycle.keyframes[3].sun_color, noon.sun_color);
+        let midnight = cycle.keyframe_at(24.0);
+        assert_eq!(cycle.keyframes[0].sun_color, midnight.sun_color);
+        assert!(noon.sun_color.a > midnight.sun_color.a);
+
+        // wraps around midnight, between 19 and 0
+        let late = cycle.keyframe_at(21.5);
+        assert_eq!(25, late.sun_color.a);
+        assert_eq!(3584.0, late.fog_end);
+
+        let overcast = DayCycle::overcast().keyframe_at(12.0);
+        assert_eq!(overcast.sun_color.r, overcast.sun_color.b);
+        assert!(overcast.sun_color.a < noon.sun_color.a);
+        assert!(overcast.fog_end < noon.fog_end);
+    }
+
+    #[test]
+    fn sun_dir() {
+        let cycle = DayCycle::clear();
+        let noon = cycle.sun_dir(12.0);
+        assert_eq!(Angles { pitch: 60.0, yaw: 90.0, roll: 0.0 }, noon);
+        // rises in the east, so light points west
+        let morning = cycle.sun_dir(7.0);
+        assert!(morning.pitch < noon.pitch);
+        assert!(morning.yaw > 90.0 && morning.yaw < 180.0);
+        assert_eq!(DayCycle::MIN_ELEVATION, cycle.sun_dir(6.0).pitch);
+        assert_eq!(noon, cycle.sun_dir(0.0));
+
+        let moonlit = LightingPreset::Moonlit.lighting::<String>();
+        let golden = LightingPreset::GoldenHour.lighting::<String>();
+        assert!(moonlit.light.sun_color.a < golden.light.sun_color.a);
+        assert!(golden.light.sun_dir.pitch < noon.pitch);
+        assert!(golden.light.sun_color.b < golden.light.sun_color.r);
+    }
+}
@@ -1,26 +1,34 @@
 //! Lighting, colorspaces, and time
 
 pub mod colorspace;
+pub mod cycle;
+pub mod photometric;
+pub mod sky;
 pub mod time;
+pub mod weather;
 
 use rgb::RGB;
 
 use crate::{
     map::{Angles, Entity},
-    source::ColorBrightness,
+    source::{ColorBrightness, LightDirectional, LightEnviroment, PointEntity},
+    vmf::ToLower,
 };
 
-// TODO: cloudyness
-// TODO: fog and skybox and env_sun
-
 // TODO: merge with lightenv? keep and split into valve stuff
 // TODO:DOCS:
 /// All information for global lighting
 #[derive(Clone, Debug, PartialEq)]
 pub struct GlobalLighting {
     pub sun_color: ColorBrightness,
+    /// `_lightHDR`, `None` for the same as LDR.
+    pub sun_color_hdr: Option<ColorBrightness>,
     pub sun_dir: Angles,
+    /// How soft the sun's shadows are, see [`LightEnviroment::sun_spread_angle`].
+    pub sun_spread_angle: f64,
     pub amb_color: ColorBrightness,
+    /// `_ambientHDR`, `None` for the same as LDR.
+    pub amb_color_hdr: Option<ColorBrightness>,
     pub amb_dir: Angles, // needed?
     pub dir_lights: Vec<(ColorBrightness, Angles)>,
 }
@@ -32,7 +40,7 @@ impl GlobalLighting {
     // shadow_control https://developer.valvesoftware.com/wiki/Intermediate_Lighting#light
     // color correction
     // env_tonemap_controller (with logic_auto?) // https://developer.valvesoftware.com/wiki/Env_tonemap_controller
-    // env_sun (sprite) NEED SKYBOX FIRST TO TARGET
+    // env_sun (sprite) using angles, see GlobalLightShadows
     // lightmap scale
     // csm (realtime shadows) https://developer.valvesoftware.com/wiki/Env_cascade_light
     // csm/shadow_control mutually exclusive?
@@ -42,27 +50,38 @@ impl GlobalLighting {
     // -textureshadows // alpha support
     // -staticproplighting // vertex lighting for props
     //     Warning.png Warning: This will disable info_lighting entities on static props that don't use bump maps!
-    pub fn to_entity(&self) -> Entity<String> {
-        // TODO: here
-        todo!()
-    }
-}
 
-// impl<'a> ToLower<'a, LightEnviroment> for GlobalLighting {
+    /// The `light_environment` for the sun and ambient light.
+    pub fn light_environment<S: Default>(&self) -> LightEnviroment<S> {
+        LightEnviroment {
+            point_entity: PointEntity { angles: self.sun_dir.clone(), ..Default::default() },
+            direct_color: self.sun_color.clone(),
+            amb_color: self.amb_color.clone(),
+            direct_color_hdr: self.sun_color_hdr.clone(),
+            amb_color_hdr: self.amb_color_hdr.clone(),
+            sun_spread_angle: self.sun_spread_angle,
+            ..Default::default()
+        }
+    }
 
-//     fn to_lower(&self) -> LightEnviroment {
-//         LightEnviroment {
-//             point_entity: PointEntity { angles: self.sun_dir.clone(), ..PointEntity::default() },
-//             direct_color: self.sun_color.clone(),
-//             amb_color: self.amb_color.clone(),
-//             ..LightEnviroment::default()
-//         }
-//     }
+    /// A `light_directional` for each of [`Self::dir_lights`].
+    pub fn light_directionals<S: Default>(&self) -> Vec<LightDirectional<S>> {
+        self.dir_lights
+            .iter()
+            .map(|(color, dir)| LightDirectional {
+                point_entity: PointEntity { angles: dir.clone(), ..Default::default() },
+                color: color.clone(),
+                ..Default::default()
+            })
+            .collect()
+    }
 
-//     fn into_lower(self) -> LightEnviroment {
-//         todo!()
-//     }
-// }
+    /// The `light_environment`. See [`GlobalLightShadows`](crate::source::GlobalLightShadows)
+    /// for all the entities.
+    pub fn to_entity(&self) -> Entity<String> {
+        self.light_environment::<String>().into_lower()
+    }
+}
 
 impl Default for GlobalLighting {
     // TODO:
@@ -70,8 +89,11 @@ impl Default for GlobalLighting {
     fn default() -> Self {
         Self {
             sun_color: ColorBrightness::new(255, 255, 255, 200),
+            sun_color_hdr: None,
             sun_dir: Angles::default(),
+            sun_spread_angle: 5.0,
             amb_color: ColorBrightness::new(255, 255, 255, 50),
+            amb_color_hdr: None,
             amb_dir: Angles::default(),
             dir_lights: Default::default(),
         }
@@ -0,0 +1,227 @@
+//! Photometric lights, converted to Source's LDR and HDR brightness.
+//!
+//! vrad reads `_light` as a color with a gamma of 2.2, scaled by the brightness, see
+//! [`ColorBrightness::to_linear`]. Point lights with the default quadratic falloff are
+//! normalized so the brightness is the intensity [`REFERENCE_DIST`] units away, the sun and
+//! ambient light are used as is. Compiling with `-hdr` uses `_lightHDR` instead, scaled by
+//! `_lightscaleHDR`.
+//!
+//! Real illuminances span a much wider range than Source can show, from ~0.25 lux under a full
+//! moon to ~100,000 lux in direct sunlight. An [`Exposure`] compresses them into brightnesses,
+//! less so for HDR as the auto exposure brightens dark areas.
+//!
+//! See also: <https://developer.valvesoftware.com/wiki/Light>
+
+use std::f64::consts::PI;
+
+use crate::{
+    light::{colorspace::temp_to_light, GlobalLighting},
+    map::Angles,
+    source::{ColorBrightness, Light},
+};
+
+/// Meters per hammer unit, 1 inch.
+pub const METERS_PER_UNIT: f64 = 0.0254;
+/// Distance in units vrad normalizes point light brightness to.
+pub const REFERENCE_DIST: f64 = 100.0;
+
+/// How bright a light is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Intensity {
+    /// Luminous intensity of a point or spot light, in candela.
+    Candela(f64),
+    /// Luminous flux of an omnidirectional light, in lumens.
+    Lumens(f64),
+    /// Illuminance of a surface facing the light, in lux. For the sun and sky, or a point light
+    /// [`REFERENCE_DIST`] units away.
+    Lux(f64),
+    /// Brightness divided by 255, the same in LDR and HDR.
+    Normalized(f64),
+}
+
+impl Intensity {
+    /// Illuminance in lux, [`REFERENCE_DIST`] units away for point lights.
+    /// `None` for [`Intensity::Normalized`].
+    pub fn lux(&self) -> Option<f64> {
+        let candela_to_lux = |candela: f64| candela / (REFERENCE_DIST * METERS_PER_UNIT).powi(2);
+        match *self {
+            Intensity::Candela(candela) => Some(candela_to_lux(candela)),
+            Intensity::Lumens(lumens) => Some(candela_to_lux(lumens / (4.0 * PI))),
+            Intensity::Lux(lux) => Some(lux),
+            Intensity::Normalized(_) => None,
+        }
+    }
+}
+
+/// A light with a color temperature and photometric intensity.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PhotometricLight {
+    /// Color temperature in Kelvin.
+    pub cct: f64,
+    /// Tint, positive is greenish and negative is magentaish. See [`cct_duv_to_xy`].
+    ///
+    /// [`cct_duv_to_xy`]: crate::light::colorspace::cct_duv_to_xy
+    pub duv: f64,
+    pub intensity: Intensity,
+}
+
+impl PhotometricLight {
+    /// Direct sunlight around noon.
+    pub const SUN: Self = Self::new(5500.0, Intensity::Lux(100_000.0));
+    /// Skylight on a clear day, for ambient light.
+    pub const CLEAR_SKY: Self = Self::new(10000.0, Intensity::Lux(20_000.0));
+    /// Skylight on an overcast day, for ambient light.
+    pub const OVERCAST_SKY: Self = Self::new(6500.0, Intensity::Lux(5_000.0));
+    /// A full moon.
+    pub const MOON: Self = Self::new(4100.0, Intensity::Lux(0.25));
+    /// A 60W incandescent bulb.
+    pub const INCANDESCENT: Self = Self::new(2700.0, Intensity::Lumens(800.0));
+    /// A 4 foot fluorescent tube, slightly green.
+    pub const FLUORESCENT: Self =
+        Self { cct: 4000.0, duv: 0.003, intensity: Intensity::Lumens(2600.0) };
+    /// A high pressure sodium street light.
+    pub const SODIUM: Self = Self::new(2000.0, Intensity::Lumens(15_000.0));
+
+    /// A light on the Planckian locus, without tint.
+    pub const fn new(cct: f64, intensity: Intensity) -> Self {
+        Self { cct, duv: 0.0, intensity }
+    }
+}
+
+/// Maps illuminance to Source brightness, `255 * (lux / white_lux) ^ contrast`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Exposure {
+    /// Illuminance in lux with a brightness of 255.
+    pub white_lux: f64,
+    /// Exponent compressing the range of illuminances. 1 is linear, like real life.
+    pub contrast: f64,
+}
+
+impl Exposure {
+    /// Without auto exposure. The sun is ~400 and a 60W bulb is ~100.
+    pub const LDR: Self = Self { white_lux: 5_000.0, contrast: 0.15 };
+    /// With auto exposure. The sun is ~400 and a 60W bulb is ~64.
+    pub const HDR: Self = Self { white_lux: 10_000.0, contrast: 0.2 };
+
+    /// Brightness of `intensity`, the `a` in [`ColorBrightness`].
+    pub fn brightness(&self, intensity: &Intensity) -> f64 {
+        if let Intensity::Normalized(n) = intensity {
+            return 255.0 * n;
+        }
+        let lux = intensity.lux().unwrap_or_default().max(0.0);
+        255.0 * (lux / self.white_lux).powf(self.contrast)
+    }
+}
+
+/// `_light` and `_lightHDR` values of a light. `_lightscaleHDR` should be 1.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceLight {
+    pub light: ColorBrightness,
+    pub light_hdr: ColorBrightness,
+}
+
+/// Converts [`PhotometricLight`]s to Source values for LDR and HDR compiles,
+/// so lights stay balanced with each other in both.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LightModel {
+    pub ldr: Exposure,
+    pub hdr: Exposure,
+}
+
+impl Default for LightModel {
+    fn default() -> Self {
+        Self { ldr: Exposure::LDR, hdr: Exposure::HDR }
+    }
+}
+
+impl LightModel {
+    /// `_light` and `_lightHDR` of `light`, with the same color.
+    pub fn source(&self, light: &PhotometricLight) -> SourceLight {
+        let ldr = temp_to_light(light.cct, light.duv, self.ldr.brightness(&light.intensity));
+        let hdr = self.hdr.brightness(&light.intensity).round() as i32;
+        SourceLight { light_hdr: ColorBrightness { a: hdr, ..ldr.clone() }, light: ldr }
+    }
+
+    /// A `light` entity, wrap it in a [`LightSpot`](crate::source::LightSpot) for a spotlight.
+    pub fn light<S: Default>(&self, light: &PhotometricLight) -> Light<S> {
+        let SourceLight { light, light_hdr } = self.source(light);
+        Light { color: light, color_hdr: Some(light_hdr), hdr_scale: 1.0, ..Default::default() }
+    }
+
+    /// Global lighting from the sun and the sky's ambient light.
+    pub fn global_lighting(
+        &self, sun: &PhotometricLight, sky: &PhotometricLight, sun_dir: Angles,
+    ) -> GlobalLighting {
+        let (sun, sky) = (self.source(sun), self.source(sky));
+        GlobalLighting {
+            sun_color: sun.light,
+            sun_color_hdr: Some(sun.light_hdr),
+            amb_color: sky.light,
+            amb_color_hdr: Some(sky.light_hdr),
+            amb_dir: sun_dir.clone(),
+            sun_dir,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::abs_diff_eq;
+
+    use super::*;
+    use crate::{map::Entity, vmf::ToLower, StrType};
+
+    #[test]
+    fn balance() {
+        // 4 pi candela is 16 pi^2 lumens, 100 units is 2.54 meters
+        let [candela, lumens] =
+            [Intensity::Candela(4.0 * PI), Intensity::Lumens(16.0 * PI * PI)].map(|i| i.lux());
+        assert!(abs_diff_eq!(candela.unwrap(), lumens.unwrap(), epsilon = 1e-9));
+        assert!(abs_diff_eq!(4.0 * PI / 6.4516, candela.unwrap(), epsilon = 1e-9));
+
+        for exposure in [Exposure::LDR, Exposure::HDR] {
+            assert_eq!(255.0, exposure.brightness(&Intensity::Normalized(1.0)));
+            assert_eq!(0.0, exposure.brightness(&Intensity::Lux(0.0)));
+            assert_eq!(255.0, exposure.brightness(&Intensity::Lux(exposure.white_lux)));
+            let sun = exposure.brightness(&PhotometricLight::SUN.intensity);
+            assert!(abs_diff_eq!(400.0, sun, epsilon = 10.0), "{sun}");
+        }
+
+        let model = LightModel::default();
+        let [sun, bulb, tube, moon] = [
+            PhotometricLight::SUN,
+            PhotometricLight::INCANDESCENT,
+            PhotometricLight::FLUORESCENT,
+            PhotometricLight::MOON,
+        ]
+        .map(|light| model.source(&light));
+        assert!(abs_diff_eq!(100, bulb.light.a, epsilon = 2), "{}", bulb.light);
+        assert!(abs_diff_eq!(64, bulb.light_hdr.a, epsilon = 2), "{}", bulb.light_hdr);
+        assert!(sun.light_hdr.a / bulb.light_hdr.a > sun.light.a / bulb.light.a);
+        assert!(moon.light.a > 0 && moon.light.a < bulb.light.a);
+        assert!(tube.light.a > bulb.light.a && tube.light.b > bulb.light.b);
+        for light in [&sun, &bulb, &tube, &moon] {
+            let SourceLight { light, light_hdr } = light;
+            assert_eq!((light.r, light.g, light.b), (light_hdr.r, light_hdr.g, light_hdr.b));
+        }
+        // warm bulb
+        assert_eq!(255, bulb.light.r);
+        assert!(bulb.light.b < 200);
+
+        let entity: Entity<StrType> = model.light(&PhotometricLight::INCANDESCENT).into_lower();
+        assert_eq!(Some(&bulb.light.to_string().into()), entity.get("_light"));
+        assert_eq!(Some(&bulb.light_hdr.to_string().into()), entity.get("_lightHDR"));
+        assert_eq!(Some(&"1".into()), entity.get("_lightscaleHDR"));
+
+        let lighting = model.global_lighting(
+            &PhotometricLight::SUN,
+            &PhotometricLight::CLEAR_SKY,
+            Angles { pitch: 45.0, yaw: 0.0, roll: 0.0 },
+        );
+        let entity: Entity<StrType> = lighting.light_environment().into_lower();
+        assert_eq!(Some(&sun.light.to_string().into()), entity.get("_light"));
+        assert_eq!(Some(&sun.light_hdr.to_string().into()), entity.get("_lightHDR"));
+        assert!(lighting.amb_color.a < lighting.sun_color.a);
+    }
+}
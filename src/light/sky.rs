@@ -0,0 +1,229 @@
+//! Skybox textures per game, and picking one that matches the lighting.
+//!
+//! See also: <https://developer.valvesoftware.com/wiki/Sky_List>
+
+use rgb::RGB8;
+
+use crate::{
+    light::{cycle::DayCycle, GlobalLighting},
+    map::Angles,
+};
+
+/// Rough time of day painted in a sky.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TimeOfDay {
+    Dawn,
+    Day,
+    Dusk,
+    Night,
+}
+
+impl TimeOfDay {
+    /// 0 for the same, dawn and dusk are close.
+    fn distance(self, other: Self) -> f64 {
+        match (self, other) {
+            (a, b) if a == b => 0.0,
+            (Self::Dawn, Self::Dusk) | (Self::Dusk, Self::Dawn) => 0.5,
+            _ => 2.0,
+        }
+    }
+}
+
+/// A 2D skybox, set with [`MapOptions::sky_name`](crate::map::MapOptions::sky_name).
+///
+/// The sun directions are approximate, eyeballed from the sky textures.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sky {
+    /// Ex: `sky_day01_01`
+    pub name: &'static str,
+    pub time: TimeOfDay,
+    /// 0 for clear to 1 for completely overcast.
+    pub cloudiness: f64,
+    /// Direction of light from the painted sun or moon, like [`GlobalLighting::sun_dir`].
+    /// `None` if there is no visible sun.
+    pub sun_dir: Option<Angles>,
+    /// Dominant color of the sun and sky near it.
+    pub sun_color: RGB8,
+}
+
+/// Shorthand for the catalogues.
+const fn sky(
+    name: &'static str, time: TimeOfDay, cloudiness: f64, sun: Option<(f64, f64)>,
+    sun_color: (u8, u8, u8),
+) -> Sky {
+    let sun_dir = match sun {
+        Some((pitch, yaw)) => Some(Angles { pitch, yaw, roll: 0.0 }),
+        None => None,
+    };
+    let (r, g, b) = sun_color;
+    Sky { name, time, cloudiness, sun_dir, sun_color: RGB8 { r, g, b } }
+}
+
+/// Left 4 Dead 2 skies, including ones from Left 4 Dead.
+pub const L4D2_SKIES: [Sky; 11] = {
+    use TimeOfDay::*;
+    [
+        sky("sky_l4d_rural02_hdr", Dusk, 0.5, Some((10.0, 30.0)), (230, 160, 110)),
+        sky("sky_l4d_predawn02_hdr", Dawn, 0.4, None, (120, 130, 170)),
+        sky("sky_l4d_night02_hdr", Night, 0.3, None, (60, 70, 100)),
+        sky("sky_l4d_urban01_hdr", Night, 0.6, None, (90, 80, 80)),
+        sky("sky_l4d_c1_1_hdr", Day, 0.3, Some((35.0, 210.0)), (240, 220, 190)),
+        sky("sky_l4d_c1_2_hdr", Dusk, 0.4, Some((15.0, 150.0)), (240, 180, 120)),
+        sky("sky_l4d_c2m1_hdr", Day, 0.2, Some((50.0, 120.0)), (250, 240, 220)),
+        sky("sky_l4d_c4m1_hdr", Day, 0.9, None, (170, 175, 180)),
+        sky("sky_l4d_c4m4_hdr", Night, 1.0, None, (70, 75, 85)),
+        sky("sky_l4d_c5_1_hdr", Day, 0.3, Some((45.0, 60.0)), (250, 235, 210)),
+        sky("sky_l4d_c6m1_hdr", Day, 0.2, Some((40.0, 300.0)), (250, 230, 200)),
+    ]
+};
+
+/// Team Fortress 2 skies.
+pub const TF2_SKIES: [Sky; 14] = {
+    use TimeOfDay::*;
+    [
+        sky("sky_day01_01", Day, 0.3, Some((50.0, 45.0)), (250, 240, 220)),
+        sky("sky_day01_05", Day, 0.4, Some((45.0, 135.0)), (245, 235, 215)),
+        sky("sky_day02_02", Day, 0.5, Some((40.0, 90.0)), (240, 230, 210)),
+        sky("sky_day03_01", Day, 0.2, Some((55.0, 225.0)), (255, 245, 225)),
+        sky("sky_goldrush_01", Day, 0.1, Some((50.0, 315.0)), (255, 240, 210)),
+        sky("sky_badlands_01", Day, 0.1, Some((60.0, 20.0)), (255, 235, 200)),
+        sky("sky_harvest_01", Day, 0.2, Some((45.0, 180.0)), (250, 230, 190)),
+        sky("sky_stormfront_01", Day, 0.9, None, (160, 165, 170)),
+        sky("sky_alpinestorm_01", Day, 1.0, None, (200, 205, 215)),
+        sky("sky_morningsnow_01", Dawn, 0.4, Some((10.0, 180.0)), (240, 200, 170)),
+        sky("sky_nightfall_01", Dusk, 0.3, Some((8.0, 0.0)), (240, 150, 90)),
+        sky("sky_night_01", Night, 0.3, None, (70, 80, 120)),
+        sky("sky_harvest_night_01", Night, 0.2, Some((30.0, 90.0)), (150, 170, 210)),
+        sky("sky_halloween_night_01", Night, 0.5, Some((40.0, 270.0)), (120, 140, 110)),
+    ]
+};
+
+impl Sky {
+    /// Point the sun in `lighting` towards the painted sun, keeping its pitch.
+    pub const fn align(&self, lighting: &mut GlobalLighting) {
+        if let Some(sun_dir) = &self.sun_dir {
+            lighting.sun_dir.yaw = sun_dir.yaw;
+        }
+    }
+
+    /// Lower is a better match.
+    fn score(&self, time: TimeOfDay, cloudiness: f64, sun_color: RGB8) -> f64 {
+        let color_dist = [
+            (self.sun_color.r, sun_color.r),
+            (self.sun_color.g, sun_color.g),
+            (self.sun_color.b, sun_color.b),
+        ]
+        .map(|(a, b)| (a as f64 - b as f64).powi(2))
+        .iter()
+        .sum::<f64>()
+        .sqrt();
+        // max distance is ~441
+        self.time.distance(time) + (self.cloudiness - cloudiness).abs() + color_dist / 882.0
+    }
+}
+
+/// Guess the time of day from the sun's brightness and elevation.
+/// Dawn and dusk can't be told apart, dusk is used.
+pub fn time_of_day(lighting: &GlobalLighting) -> TimeOfDay {
+    if lighting.sun_color.a < 60 {
+        TimeOfDay::Night
+    } else if lighting.sun_dir.pitch < 20.0 {
+        TimeOfDay::Dusk
+    } else {
+        TimeOfDay::Day
+    }
+}
+
+/// Guess cloudiness from how much of the light is ambient, 0 to 1.
+pub fn cloudiness(lighting: &GlobalLighting) -> f64 {
+    let (sun, amb) = (lighting.sun_color.a.max(0) as f64, lighting.amb_color.a.max(0) as f64);
+    let ratio = if sun + amb == 0.0 { 1.0 } else { amb / (sun + amb) };
+    ((ratio - 0.1) / 0.5).clamp(0.0, 1.0)
+}
+
+/// The sky from `skies` that best matches `lighting`.
+///
+/// Panics if `skies` is empty.
+pub fn best_sky<'s>(skies: &'s [Sky], lighting: &GlobalLighting) -> &'s Sky {
+    let time = time_of_day(lighting);
+    best_sky_for(skies, time, cloudiness(lighting), lighting)
+}
+
+/// Like [`best_sky`], with a known cloudiness from 0 to 1 instead of guessing it.
+///
+/// Panics if `skies` is empty.
+pub fn best_sky_clouds<'s>(
+    skies: &'s [Sky], lighting: &GlobalLighting, cloudiness: f64,
+) -> &'s Sky {
+    best_sky_for(skies, time_of_day(lighting), cloudiness, lighting)
+}
+
+/// The sky from `skies` that best matches `cycle` at `hour`.
+///
+/// Panics if `skies` is empty.
+pub fn best_sky_at<'s>(skies: &'s [Sky], cycle: &DayCycle, hour: f64) -> &'s Sky {
+    let hour = hour.rem_euclid(24.0);
+    let time = if (cycle.sunrise - 1.0..cycle.sunrise + 2.0).contains(&hour) {
+        TimeOfDay::Dawn
+    } else if (cycle.sunset - 2.0..cycle.sunset + 1.0).contains(&hour) {
+        TimeOfDay::Dusk
+    } else if (cycle.sunrise..cycle.sunset).contains(&hour) {
+        TimeOfDay::Day
+    } else {
+        TimeOfDay::Night
+    };
+    let lighting = cycle.at::<String>(hour).light;
+    best_sky_for(skies, time, cloudiness(&lighting), &lighting)
+}
+
+fn best_sky_for<'s>(
+    skies: &'s [Sky], time: TimeOfDay, cloudiness: f64, lighting: &GlobalLighting,
+) -> &'s Sky {
+    let sun_color = {
+        let color = &lighting.sun_color;
+        RGB8::new(color.r, color.g, color.b)
+    };
+    skies
+        .iter()
+        .min_by(|a, b| {
+            let [a, b] = [a, b].map(|sky| sky.score(time, cloudiness, sun_color));
+            a.total_cmp(&b)
+        })
+        .expect("no skies")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light::cycle::LightingPreset;
+
+    #[test]
+    fn pick() {
+        let noon = LightingPreset::Noon.lighting::<String>().light;
+        let night = LightingPreset::Moonlit.lighting::<String>().light;
+        let overcast = LightingPreset::Overcast.lighting::<String>().light;
+        assert_eq!(TimeOfDay::Day, time_of_day(&noon));
+        assert_eq!(TimeOfDay::Night, time_of_day(&night));
+        assert!(cloudiness(&overcast) > 0.7);
+        assert!(cloudiness(&noon) < 0.3);
+
+        for skies in [&L4D2_SKIES[..], &TF2_SKIES[..]] {
+            assert_eq!(TimeOfDay::Day, best_sky(skies, &noon).time);
+            assert_eq!(TimeOfDay::Night, best_sky(skies, &night).time);
+            assert!(best_sky(skies, &overcast).cloudiness > 0.7);
+            assert_eq!(TimeOfDay::Dusk, best_sky_at(skies, &DayCycle::clear(), 17.5).time);
+        }
+        assert_eq!("sky_l4d_predawn02_hdr", best_sky_at(&L4D2_SKIES, &DayCycle::clear(), 6.0).name);
+
+        let mut lighting = noon.clone();
+        let sky = best_sky(&TF2_SKIES, &lighting);
+        sky.align(&mut lighting);
+        assert_eq!(sky.sun_dir.as_ref().unwrap().yaw, lighting.sun_dir.yaw);
+        assert_eq!(noon.sun_dir.pitch, lighting.sun_dir.pitch);
+
+        let mut map = crate::map::Map::default();
+        map.set_lighting_and_sky(&L4D2_SKIES, LightingPreset::Moonlit.lighting());
+        let sky = L4D2_SKIES.iter().find(|sky| sky.name == map.options.sky_name).unwrap();
+        assert_eq!(TimeOfDay::Night, sky.time);
+    }
+}
@@ -3,8 +3,13 @@ use rgb::RGB;
 use spa::{SolarPos, SpaError};
 
 use crate::{
-    // light::{pitch_to_rgb, Angles, GlobalLighting},
-    source::{ColorBrightness}, light::{GlobalLighting, pitch_to_rgb}, map::Angles,
+    light::{
+        colorspace::temp_to_rgb,
+        photometric::{Exposure, Intensity, PhotometricLight},
+        pitch_to_rgb, GlobalLighting,
+    },
+    map::Angles,
+    source::ColorBrightness,
 };
 
 // lat
@@ -30,15 +35,25 @@ use crate::{
 // dec 24.5
 
 impl Angles {
+    /// Direction of light from the sun, like [`GlobalLighting::sun_dir`].
+    pub(crate) fn from_solar_pos(pos: SolarPos) -> Self {
+        Self::from_horizontal(90.0 - pos.zenith_angle, pos.azimuth)
+    }
+
+    /// Direction of light from the moon, like [`GlobalLighting::sun_dir`].
+    pub(crate) fn from_lunar_pos(pos: &MoonPos) -> Self {
+        Self::from_horizontal(pos.elevation, pos.azimuth)
+    }
+
     // Assumes +Y is north.
     // seems good, checked in hammer and irl (scary)
-    pub(crate) fn from_solar_pos(pos: SolarPos) -> Self {
-        let pitch = pos.zenith_angle - 90.0;
-        // angle right from north (azimuth) to angle left from +X/east (yaw)
+    /// Light coming from `elevation` degrees above the horizon and `azimuth` degrees right
+    /// from north. Pitch is the elevation, positive is pointing down.
+    fn from_horizontal(elevation: f64, azimuth: f64) -> Self {
+        // angle right from north (azimuth) to angle left from +X/east (yaw), facing away
         // rem_euclid() means slam into 0..360 range
-        let yaw = (270.0 - pos.azimuth).rem_euclid(360.0);
-        let roll = 0.0;
-        Angles { pitch, yaw, roll }
+        let yaw = (270.0 - azimuth).rem_euclid(360.0);
+        Angles { pitch: elevation, yaw, roll: 0.0 }
     }
 }
 
@@ -89,35 +104,224 @@ pub(crate) fn lon_to_offset(longitude_east: f64) -> Option<FixedOffset> {
 // TODO: SolarPos to
 /// Calculate the sun position from world position and a local date and time.
 pub fn calc_solar_position_local(
-    lat: f64,
-    lon: f64,
-    datetime: NaiveDateTime,
+    lat: f64, lon: f64, datetime: NaiveDateTime,
 ) -> Result<SolarPos, SpaError> {
     let utc = DateTime::from_longitude(lon, datetime).ok_or(SpaError::BadParam)?;
     spa::calc_solar_position(utc, lat, lon)
 }
+
+/// Position and phase of the moon, seen from a place on earth.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MoonPos {
+    /// Degrees above the horizon, negative if below it. Corrected for parallax.
+    pub elevation: f64,
+    /// Degrees right from north, like [`SolarPos::azimuth`].
+    pub azimuth: f64,
+    /// Distance from the center of the earth in km.
+    pub distance: f64,
+    /// Illuminated fraction of the disk, 0 at new moon to 1 at full moon.
+    pub fraction: f64,
+    /// 0 at new moon, 0.25 at first quarter, 0.5 at full moon and 0.75 at last quarter.
+    pub phase: f64,
+}
+
+/// Ecliptic coordinates in degrees.
+struct Ecliptic {
+    lon: f64,
+    lat: f64,
+}
+
+/// Equatorial coordinates in radians.
+struct Equatorial {
+    right_ascension: f64,
+    declination: f64,
+}
+
+/// Obliquity of the ecliptic at J2000, in degrees.
+const OBLIQUITY: f64 = 23.4397;
+/// Equatorial radius of the earth in km.
+const EARTH_RADIUS: f64 = 6378.14;
+/// Mean distance to the sun in km.
+const SUN_DISTANCE: f64 = 149_598_000.0;
+
+impl Ecliptic {
+    fn to_equatorial(&self) -> Equatorial {
+        let (lon, lat, e) = (self.lon.to_radians(), self.lat.to_radians(), OBLIQUITY.to_radians());
+        Equatorial {
+            right_ascension: (lon.sin() * e.cos() - lat.tan() * e.sin()).atan2(lon.cos()),
+            declination: (lat.sin() * e.cos() + lat.cos() * e.sin() * lon.sin()).asin(),
+        }
+    }
+}
+
+/// Days since J2000, noon on the 1st of January 2000.
+fn days_since_j2000(utc: DateTime<Utc>) -> f64 {
+    // unix epoch is J2000 - 10957.5 days
+    utc.timestamp_millis() as f64 / 86_400_000.0 - 10957.5
+}
+
+/// Low precision position of the sun, good to ~0.01 degrees.
+fn sun_ecliptic(days: f64) -> Ecliptic {
+    let anomaly = (357.5291 + 0.98560028 * days).to_radians();
+    let center =
+        1.9148 * anomaly.sin() + 0.02 * (2.0 * anomaly).sin() + 0.0003 * (3.0 * anomaly).sin();
+    let lon = anomaly.to_degrees() + center + 102.9372 + 180.0;
+    Ecliptic { lon, lat: 0.0 }
+}
+
+/// Low precision position of the moon and its distance in km, good to ~0.3 degrees.
+/// Only the largest perturbations are used.
+fn moon_ecliptic(days: f64) -> (Ecliptic, f64) {
+    let mean_lon = 218.316 + 13.176396 * days;
+    let anomaly = (134.963 + 13.064993 * days).to_radians();
+    let node_dist = (93.272 + 13.229350 * days).to_radians();
+    // twice the mean elongation, the angle between the sun and moon
+    let elongation2 = 2.0 * (mean_lon - 280.460 - 0.9856474 * days).to_radians();
+    let sun_anomaly = (357.5291 + 0.98560028 * days).to_radians();
+
+    let lon = mean_lon + 6.289 * anomaly.sin()
+        - 1.274 * (anomaly - elongation2).sin() // evection
+        + 0.658 * elongation2.sin() // variation
+        - 0.186 * sun_anomaly.sin() // yearly equation
+        - 0.059 * (2.0 * anomaly - elongation2).sin()
+        - 0.057 * (anomaly - elongation2 + sun_anomaly).sin();
+    let lat = 5.128 * node_dist.sin() - 0.173 * (node_dist - elongation2).sin();
+    let distance = 385_001.0
+        - 20_905.0 * anomaly.cos()
+        - 3_699.0 * (elongation2 - anomaly).cos()
+        - 2_956.0 * elongation2.cos();
+    (Ecliptic { lon, lat }, distance)
+}
+
+/// Calculate the moon position and phase from world position and a UTC date and time.
+///
+/// Uses a simplified lunar theory, see <https://aa.quae.nl/en/reken/hemelpositie.html>.
+pub fn calc_lunar_position(utc: DateTime<Utc>, lat: f64, lon: f64) -> MoonPos {
+    let days = days_since_j2000(utc);
+    let (moon, distance) = moon_ecliptic(days);
+    let moon = moon.to_equatorial();
+    let sun = sun_ecliptic(days).to_equatorial();
+
+    // horizontal coordinates from the local sidereal time
+    let lat_rad = lat.to_radians();
+    let sidereal = (280.16 + 360.9856235 * days + lon).to_radians();
+    let hour_angle = sidereal - moon.right_ascension;
+    let (dec_sin, dec_cos) = moon.declination.sin_cos();
+    let elevation = (lat_rad.sin() * dec_sin + lat_rad.cos() * dec_cos * hour_angle.cos()).asin();
+    // measured from south, to the west
+    let azimuth = hour_angle
+        .sin()
+        .atan2(hour_angle.cos() * lat_rad.sin() - moon.declination.tan() * lat_rad.cos());
+    // the moon is close enough to look lower from the surface than from the center
+    let parallax = (EARTH_RADIUS / distance).asin() * elevation.cos();
+
+    // angle between the sun and moon, seen from the earth and then from the moon
+    let elongation = (sun.declination.sin() * dec_sin
+        + sun.declination.cos() * dec_cos * (sun.right_ascension - moon.right_ascension).cos())
+    .acos();
+    let phase_angle =
+        (SUN_DISTANCE * elongation.sin()).atan2(distance - SUN_DISTANCE * elongation.cos());
+    // positive when waxing, the sun is east of the moon
+    let waxing = sun.declination.cos() * (sun.right_ascension - moon.right_ascension).sin();
+    let phase = 0.5 + 0.5 * phase_angle * waxing.signum() / std::f64::consts::PI;
+
+    MoonPos {
+        elevation: (elevation - parallax).to_degrees(),
+        azimuth: (azimuth.to_degrees() + 180.0).rem_euclid(360.0),
+        distance,
+        fraction: (1.0 + phase_angle.cos()) / 2.0,
+        phase: phase.rem_euclid(1.0),
+    }
+}
+
+/// Calculate the moon position and phase from world position and a local date and time.
+pub fn calc_lunar_position_local(
+    lat: f64, lon: f64, datetime: NaiveDateTime,
+) -> Result<MoonPos, SpaError> {
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return Err(SpaError::BadParam);
+    }
+    let utc = DateTime::from_longitude(lon, datetime).ok_or(SpaError::BadParam)?;
+    Ok(calc_lunar_position(utc, lat, lon))
+}
+
+/// Illuminance of the night sky from stars and airglow, in lux.
+const STARLIGHT_LUX: f64 = 0.002;
+/// Color temperature of the night sky, for ambient light.
+const NIGHT_SKY_TEMP: f64 = 12000.0;
+/// The lowest the moon is lit from, so the ground is still lit.
+const MIN_MOON_ELEVATION: f64 = 5.0;
+
 // TODO:LOC: TODO: make a lot better
-/// Get the mao lighting for a location and time
+/// Get the map lighting for a location and time.
+///
+/// When the sun is below the horizon, the map is dimly lit by the moon depending on its phase,
+/// blue shifted like night looks to dark adapted eyes, and by twilight and starlight.
 pub fn loc_time_to_sun(
-    lat: f64,
-    lon: f64,
-    datetime: NaiveDateTime,
+    lat: f64, lon: f64, datetime: NaiveDateTime,
 ) -> Result<GlobalLighting, SpaError> {
     let solar_pos = calc_solar_position_local(lat, lon, datetime)?;
-    let mut sun_dir = Angles::from_solar_pos(solar_pos);
-    dbg!(sun_dir.pitch);
-    let RGB { r, g, b } = pitch_to_rgb(-sun_dir.pitch);
-    sun_dir.pitch = -sun_dir.pitch.abs();
+    let sun_dir = Angles::from_solar_pos(solar_pos);
+    if sun_dir.pitch < 0.0 {
+        let moon = calc_lunar_position_local(lat, lon, datetime)?;
+        return Ok(night_lighting(sun_dir.pitch, &moon));
+    }
+    let RGB { r, g, b } = pitch_to_rgb(sun_dir.pitch);
 
     Ok(GlobalLighting {
         sun_color: ColorBrightness::new(r, g, b, 255), // TODO: brightness
+        sun_color_hdr: None,
         sun_dir: sun_dir.clone(),
+        sun_spread_angle: 5.0,
         amb_color: ColorBrightness::new(171, 206, 220, 50), // default l4d2
+        amb_color_hdr: None,
         amb_dir: sun_dir,
         dir_lights: Vec::new(),
     })
 }
 
+/// Moonlight with the sun `sun_elevation` degrees below the horizon.
+fn night_lighting(sun_elevation: f64, moon: &MoonPos) -> GlobalLighting {
+    let moon_lux = match PhotometricLight::MOON.intensity {
+        Intensity::Lux(lux) if moon.elevation > 0.0 => lux * moon.fraction,
+        _ => 0.0,
+    };
+    // ~400 lux at sunset, ~1 at the end of civil twilight
+    let twilight_lux = 10f64.powf(2.6 + 0.45 * sun_elevation);
+    let sky_lux = STARLIGHT_LUX + twilight_lux + moon_lux * 0.2;
+
+    let light = |rgb: RGB<u8>, lux: f64| {
+        let [a, a_hdr] = [Exposure::LDR, Exposure::HDR]
+            .map(|exposure| exposure.brightness(&Intensity::Lux(lux)).round() as i32);
+        (
+            ColorBrightness::new(rgb.r, rgb.g, rgb.b, a),
+            ColorBrightness::new(rgb.r, rgb.g, rgb.b, a_hdr),
+        )
+    };
+    // pitch_to_rgb() gets bluer as the night gets darker
+    let (sun_color, sun_color_hdr) = light(pitch_to_rgb(sun_elevation), moon_lux);
+    let (amb_color, amb_color_hdr) = light(temp_to_rgb(NIGHT_SKY_TEMP), sky_lux);
+
+    let mut sun_dir = Angles::from_lunar_pos(moon);
+    sun_dir.pitch = if moon.elevation > 0.0 {
+        sun_dir.pitch.max(MIN_MOON_ELEVATION)
+    } else {
+        // no direct light, shadows straight down
+        90.0
+    };
+
+    GlobalLighting {
+        sun_color,
+        sun_color_hdr: Some(sun_color_hdr),
+        sun_dir: sun_dir.clone(),
+        sun_spread_angle: 5.0,
+        amb_color,
+        amb_color_hdr: Some(amb_color_hdr),
+        amb_dir: sun_dir,
+        dir_lights: Vec::new(),
+    }
+}
+
 // Property::new("origin", "0 0 0"),
 // Property::new("SunSpreadAngle", "0"),
 // Property::new("pitch", "-14"),
@@ -147,8 +351,71 @@ mod tests {
         let dir = &lighting.sun_dir;
         dbg!(dir);
         // verified in hammer/l4d2 and irl (scary)
-        assert_relative_eq!(-46.0, dir.pitch, epsilon = 3.0);
+        assert_relative_eq!(46.0, dir.pitch, epsilon = 3.0);
         assert_relative_eq!(22.0, dir.yaw, epsilon = 3.0);
         assert_relative_eq!(0.0, dir.roll);
+        assert_eq!(None, lighting.sun_color_hdr);
+    }
+
+    #[test]
+    fn moon() {
+        let utc = |month, day, hour, min| {
+            NaiveDate::from_ymd_opt(2023, month, day)
+                .unwrap()
+                .and_hms_opt(hour, min, 0)
+                .unwrap()
+                .and_utc()
+        };
+        let new = calc_lunar_position(utc(4, 20, 4, 12), 0.0, 0.0);
+        let first_quarter = calc_lunar_position(utc(4, 27, 21, 20), 0.0, 0.0);
+        let full = calc_lunar_position(utc(5, 5, 17, 34), 0.0, 0.0);
+        assert!(new.fraction < 0.01, "{new:?}");
+        assert_relative_eq!(0.5, first_quarter.fraction, epsilon = 0.02);
+        assert_relative_eq!(0.25, first_quarter.phase, epsilon = 0.01);
+        assert!(full.fraction > 0.99, "{full:?}");
+        assert_relative_eq!(0.5, full.phase, epsilon = 0.01);
+
+        // total solar eclipse in Exmouth, Australia
+        let (lat, lon) = (-21.93, 114.13);
+        let moon = calc_lunar_position(utc(4, 20, 3, 30), lat, lon);
+        let sun = spa::calc_solar_position(utc(4, 20, 3, 30), lat, lon).unwrap();
+        let [sun, moon] =
+            [Angles::from_solar_pos(sun), Angles::from_lunar_pos(&moon)].map(|a| a.forward());
+        let cos = sun.x * moon.x + sun.y * moon.y + sun.z * moon.z;
+        assert!(cos.acos().to_degrees() < 1.0, "{sun} {moon}");
+
+        let local = NaiveDate::from_ymd_opt(2023, 4, 20).unwrap().and_hms_opt(12, 0, 0).unwrap();
+        assert!(matches!(calc_lunar_position_local(91.0, 0.0, local), Err(SpaError::BadParam)));
+    }
+
+    #[test]
+    fn night() {
+        // Las Vegas
+        let (lat, lon) = (36.188110, -115.176468);
+        let at = |month, day, hour| {
+            let datetime = NaiveDate::from_ymd_opt(2023, month, day)
+                .unwrap()
+                .and_hms_opt(hour, 0, 0)
+                .unwrap();
+            loc_time_to_sun(lat, lon, datetime).unwrap()
+        };
+        let day = at(5, 5, 12);
+        let full = at(5, 5, 23);
+        let new = at(4, 20, 23);
+
+        // full moon rises at sunset
+        assert!(full.sun_dir.pitch > 10.0, "{:?}", full.sun_dir);
+        assert!(full.sun_color.a > 0 && full.sun_color.a < day.sun_color.a / 2);
+        assert!(full.sun_color.b > full.sun_color.r);
+        assert!(full.amb_color.b > full.amb_color.r);
+        assert!(full.amb_color.a < day.amb_color.a);
+        // new moon sets with the sun, only starlight
+        assert_eq!(0, new.sun_color.a);
+        assert_eq!(90.0, new.sun_dir.pitch);
+        assert!(new.amb_color.a > 0 && new.amb_color.a < full.amb_color.a);
+        for lighting in [&full, &new] {
+            let hdr = lighting.sun_color_hdr.as_ref().unwrap();
+            assert!(hdr.a <= lighting.sun_color.a);
+        }
     }
 }
@@ -0,0 +1,358 @@
+//! Weather presets, configuring lighting, shadows, fog, sky, wind and precipitation together.
+//!
+//! Values are picked randomly within [`WeatherRanges`], the same seed always gives the same
+//! weather.
+
+use std::ops::Range;
+
+use rand_chacha::rand_core::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rgb::RGB8;
+
+use crate::{
+    generation2::{shape::cube, Bounds, SolidOptions},
+    light::{
+        colorspace::{temp_to_light, temp_to_rgb},
+        sky::{best_sky_clouds, Sky},
+        GlobalLighting,
+    },
+    map::{Angles, Material},
+    source::{
+        ColorBrightness, EnvFogController, EnvSun, EnvWind, FuncPrecipitation, GlobalLightShadows,
+        PrecipitationType, ShadowControl,
+    },
+};
+
+/// Common kinds of weather, see [`WeatherKind::ranges`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum WeatherKind {
+    /// Bright sun, light haze and a breeze.
+    #[default]
+    Clear,
+    /// No visible sun, soft shadows and grey fog.
+    Overcast,
+    /// Overcast with rain and wind.
+    Rain,
+    /// Dense fog close to the player, little wind.
+    Fog,
+    /// Dark, heavy rain and strong gusting wind.
+    Storm,
+}
+
+impl WeatherKind {
+    pub const ALL: [Self; 5] = [Self::Clear, Self::Overcast, Self::Rain, Self::Fog, Self::Storm];
+
+    /// Ranges for this kind of weather. Brightnesses are for LDR `_light` values.
+    pub const fn ranges(self) -> WeatherRanges {
+        match self {
+            WeatherKind::Clear => WeatherRanges {
+                cloudiness: 0.0..0.3,
+                sun_temp: 5000.0..6000.0,
+                sun_brightness: 350.0..450.0,
+                sun_pitch: 35.0..65.0,
+                sun_spread_angle: 3.0..8.0,
+                amb_temp: 9000.0..12000.0,
+                amb_brightness: 60.0..100.0,
+                shadow_brightness: 100.0..140.0,
+                fog_start: 1500.0..3000.0,
+                fog_end: 8000.0..12000.0,
+                fog_density: 0.2..0.4,
+                fog_brightness: 0.75..0.85,
+                wind_speed: Some(10.0..30.0),
+                precipitation: None,
+                precipitation_type: PrecipitationType::Rain,
+                sun_sprite: true,
+            },
+            WeatherKind::Overcast => WeatherRanges {
+                cloudiness: 0.7..0.9,
+                sun_temp: 6000.0..7000.0,
+                sun_brightness: 100.0..180.0,
+                sun_pitch: 40.0..70.0,
+                sun_spread_angle: 90.0..135.0,
+                amb_temp: 6500.0..7500.0,
+                amb_brightness: 120.0..180.0,
+                shadow_brightness: 170.0..210.0,
+                fog_start: 500.0..1500.0,
+                fog_end: 5000.0..8000.0,
+                fog_density: 0.4..0.6,
+                fog_brightness: 0.6..0.75,
+                wind_speed: Some(20.0..50.0),
+                precipitation: None,
+                precipitation_type: PrecipitationType::Rain,
+                sun_sprite: false,
+            },
+            WeatherKind::Rain => WeatherRanges {
+                cloudiness: 0.8..1.0,
+                sun_temp: 6500.0..7500.0,
+                sun_brightness: 60.0..120.0,
+                sun_pitch: 40.0..70.0,
+                sun_spread_angle: 135.0..180.0,
+                amb_temp: 7000.0..8000.0,
+                amb_brightness: 100.0..150.0,
+                shadow_brightness: 190.0..230.0,
+                fog_start: 256.0..768.0,
+                fog_end: 3000.0..5000.0,
+                fog_density: 0.6..0.8,
+                fog_brightness: 0.45..0.6,
+                wind_speed: Some(40.0..80.0),
+                precipitation: Some(20.0..50.0),
+                precipitation_type: PrecipitationType::Rain,
+                sun_sprite: false,
+            },
+            WeatherKind::Fog => WeatherRanges {
+                cloudiness: 0.8..1.0,
+                sun_temp: 5500.0..6500.0,
+                sun_brightness: 80.0..150.0,
+                sun_pitch: 30.0..60.0,
+                sun_spread_angle: 135.0..180.0,
+                amb_temp: 6500.0..7500.0,
+                amb_brightness: 130.0..180.0,
+                shadow_brightness: 200.0..240.0,
+                fog_start: 0.0..256.0,
+                fog_end: 1000.0..2000.0,
+                fog_density: 0.85..1.0,
+                fog_brightness: 0.7..0.8,
+                wind_speed: None,
+                precipitation: None,
+                precipitation_type: PrecipitationType::Rain,
+                sun_sprite: false,
+            },
+            WeatherKind::Storm => WeatherRanges {
+                cloudiness: 0.9..1.0,
+                sun_temp: 7000.0..8500.0,
+                sun_brightness: 60.0..100.0,
+                sun_pitch: 40.0..70.0,
+                sun_spread_angle: 180.0..180.0,
+                amb_temp: 7500.0..9000.0,
+                amb_brightness: 60.0..100.0,
+                shadow_brightness: 210.0..245.0,
+                fog_start: 128.0..512.0,
+                fog_end: 2000.0..3500.0,
+                fog_density: 0.7..0.9,
+                fog_brightness: 0.3..0.45,
+                wind_speed: Some(80.0..150.0),
+                precipitation: Some(60.0..100.0),
+                precipitation_type: PrecipitationType::Rain,
+                sun_sprite: false,
+            },
+        }
+    }
+
+    /// Shorthand for [`WeatherRanges::generate`] with [`Self::ranges`].
+    pub fn generate<'a, S: Default + From<&'static str>>(
+        self, seed: u64, skies: &[Sky], bounds: &Bounds,
+    ) -> Weather<'a, S> {
+        self.ranges().generate(seed, skies, bounds)
+    }
+}
+
+/// Ranges a [`Weather`] is randomly picked within.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeatherRanges {
+    /// 0 for clear to 1 for completely overcast, for picking the sky.
+    pub cloudiness: Range<f64>,
+    /// Color temperature of the sun in Kelvin.
+    pub sun_temp: Range<f64>,
+    /// Brightness of the sun.
+    pub sun_brightness: Range<f64>,
+    /// Degrees above the horizon. The yaw is random.
+    pub sun_pitch: Range<f64>,
+    /// See [`LightEnviroment::sun_spread_angle`](crate::source::LightEnviroment::sun_spread_angle).
+    pub sun_spread_angle: Range<f64>,
+    /// Color temperature of the sky in Kelvin.
+    pub amb_temp: Range<f64>,
+    /// Brightness of the sky.
+    pub amb_brightness: Range<f64>,
+    /// Grey of dynamic shadows, 0 (black) to 255 (none).
+    pub shadow_brightness: Range<f64>,
+    /// Distance fog starts.
+    pub fog_start: Range<f64>,
+    /// Distance fog is fully dense.
+    pub fog_end: Range<f64>,
+    /// Density of the fog at `fog_end`, 0 to 1.
+    pub fog_density: Range<f64>,
+    /// Fog color as a fraction of the sky's color.
+    pub fog_brightness: Range<f64>,
+    /// Wind speed of an `env_wind`, gusts are faster. `None` for no wind.
+    pub wind_speed: Option<Range<f64>>,
+    /// Density of a `func_precipitation`, 0 to 100. `None` for no precipitation.
+    pub precipitation: Option<Range<f64>>,
+    pub precipitation_type: PrecipitationType,
+    /// Add an `env_sun` sprite.
+    pub sun_sprite: bool,
+}
+
+impl WeatherRanges {
+    /// Pick a weather within the ranges. The sky is picked from `skies` to match the lighting,
+    /// precipitation falls within `bounds`.
+    ///
+    /// Panics if `skies` is empty.
+    pub fn generate<'a, S: Default + From<&'static str>>(
+        &self, seed: u64, skies: &[Sky], bounds: &Bounds,
+    ) -> Weather<'a, S> {
+        let mut rand = ChaCha8Rng::seed_from_u64(seed);
+        let mut pick = |range: &Range<f64>| {
+            // 53 random bits, all a f64 can hold
+            let t = (rand.next_u64() >> 11) as f64 / (1_u64 << 53) as f64;
+            range.start + (range.end - range.start) * t
+        };
+        let grey = |value: f64| {
+            let value = value.round().clamp(0.0, 255.0) as u8;
+            RGB8::new(value, value, value)
+        };
+
+        let sun_dir = Angles { pitch: pick(&self.sun_pitch), yaw: pick(&(0.0..360.0)), roll: 0.0 };
+        let sun_color = temp_to_light(pick(&self.sun_temp), 0.0, pick(&self.sun_brightness));
+        let amb_temp = pick(&self.amb_temp);
+        let mut light = GlobalLighting {
+            sun_color,
+            sun_dir: sun_dir.clone(),
+            sun_spread_angle: pick(&self.sun_spread_angle),
+            amb_color: temp_to_light(amb_temp, 0.0, pick(&self.amb_brightness)),
+            amb_dir: sun_dir,
+            ..Default::default()
+        };
+        let sky = best_sky_clouds(skies, &light, pick(&self.cloudiness));
+        sky.align(&mut light);
+
+        let shadows = ShadowControl {
+            shadow_color: grey(pick(&self.shadow_brightness)),
+            ..Default::default()
+        };
+
+        let fog_color = {
+            let scale = pick(&self.fog_brightness);
+            let RGB8 { r, g, b } = temp_to_rgb(amb_temp);
+            let [r, g, b] = [r, g, b].map(|c| (c as f64 * scale).round() as u8);
+            RGB8::new(r, g, b)
+        };
+        let fog = EnvFogController {
+            start: pick(&self.fog_start),
+            end: pick(&self.fog_end),
+            max_density: pick(&self.fog_density),
+            far_z: -1,
+            primary_color: fog_color,
+            secondary_color: fog_color,
+            ..Default::default()
+        };
+
+        let sun = self.sun_sprite.then(|| {
+            let ColorBrightness { r, g, b, .. } = &light.sun_color;
+            EnvSun { color: RGB8::new(*r, *g, *b), ..Default::default() }
+        });
+
+        let wind = self.wind_speed.as_ref().map(|range| {
+            let speed = pick(range);
+            let mut wind = EnvWind {
+                min_wind: (speed * 0.5).round() as i32,
+                max_wind: speed.round() as i32,
+                min_gust: (speed * 1.5).round() as i32,
+                max_gust: (speed * 2.5).round() as i32,
+                ..Default::default()
+            };
+            wind.point_entity.angles.yaw = pick(&(0.0..360.0)).round();
+            wind
+        });
+
+        let precipitation = self.precipitation.as_ref().map(|range| {
+            let material = Material::new("TOOLS/TOOLSTRIGGER".into());
+            let solid = cube(bounds, &[&material; 6], &SolidOptions::default());
+            FuncPrecipitation {
+                density: pick(range).round().clamp(0.0, 100.0) as u8,
+                ..FuncPrecipitation::new(vec![solid], self.precipitation_type)
+            }
+        });
+
+        Weather {
+            lighting: GlobalLightShadows { light, shadows, fog: Some(fog), sun },
+            sky_name: sky.name,
+            wind,
+            precipitation,
+        }
+    }
+}
+
+/// Lighting, sky and atmosphere entities for a weather. Set with
+/// [`Map::set_weather`](crate::map::Map::set_weather).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Weather<'a, S> {
+    /// Includes the `light_environment`, `shadow_control`, `env_fog_controller` and `env_sun`.
+    pub lighting: GlobalLightShadows<S>,
+    /// See [`MapOptions::sky_name`](crate::map::MapOptions::sky_name).
+    pub sky_name: &'static str,
+    pub wind: Option<EnvWind<S>>,
+    pub precipitation: Option<FuncPrecipitation<'a>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        light::sky::{cloudiness, L4D2_SKIES, TF2_SKIES},
+        map::Map,
+        prelude::Vector3,
+        StrType,
+    };
+
+    #[test]
+    fn generate() {
+        let bounds =
+            Bounds::new(Vector3::new(-512.0, -512.0, 0.0), Vector3::new(512.0, 512.0, 512.0));
+        let weather =
+            |kind: WeatherKind, seed| kind.generate::<StrType>(seed, &L4D2_SKIES, &bounds);
+
+        for kind in WeatherKind::ALL {
+            let ranges = kind.ranges();
+            assert_eq!(weather(kind, 1), weather(kind, 1));
+            assert_ne!(weather(kind, 1), weather(kind, 2));
+
+            for seed in 0..8 {
+                let weather = weather(kind, seed);
+                let light = &weather.lighting.light;
+                let fog = weather.lighting.fog.as_ref().unwrap();
+                assert!(ranges.sun_pitch.contains(&light.sun_dir.pitch));
+                assert!((ranges.sun_brightness.start as i32..=ranges.sun_brightness.end as i32)
+                    .contains(&light.sun_color.a));
+                assert!(fog.start < fog.end && ranges.fog_density.contains(&fog.max_density));
+                assert_eq!(ranges.sun_sprite, weather.lighting.sun.is_some());
+                assert_eq!(ranges.wind_speed.is_some(), weather.wind.is_some());
+                assert_eq!(ranges.precipitation.is_some(), weather.precipitation.is_some());
+                if let Some(precipitation) = &weather.precipitation {
+                    assert_eq!(1, precipitation.solids.len());
+                    assert!(
+                        precipitation.density >= ranges.precipitation.as_ref().unwrap().start as u8
+                    );
+                }
+
+                let sky = L4D2_SKIES.iter().find(|sky| sky.name == weather.sky_name).unwrap();
+                if kind == WeatherKind::Clear {
+                    assert!(sky.cloudiness < 0.5, "{}", sky.name);
+                } else {
+                    assert!(cloudiness(light) > 0.5 && sky.cloudiness > 0.5, "{}", sky.name);
+                }
+            }
+        }
+        let storm = weather(WeatherKind::Storm, 0);
+        let clear = weather(WeatherKind::Clear, 0);
+        assert!(storm.lighting.shadows.shadow_color.r > clear.lighting.shadows.shadow_color.r);
+        assert!(storm.wind.unwrap().max_gust > clear.wind.unwrap().max_gust);
+        let tf2 = WeatherKind::Overcast.generate::<StrType>(0, &TF2_SKIES, &bounds);
+        assert!(TF2_SKIES.iter().any(|sky| sky.name == tf2.sky_name));
+
+        let mut map = Map::default();
+        map.defaults_l4d2();
+        map.set_weather(weather(WeatherKind::Rain, 3));
+        map.set_weather(weather(WeatherKind::Storm, 4));
+        let count = |classname| {
+            map.entities
+                .iter()
+                .filter(|entity| entity.classname() == Some(classname))
+                .count()
+        };
+        assert_eq!(1, count("light_environment"));
+        assert_eq!(1, count("env_fog_controller"));
+        assert_eq!(1, count("env_wind"));
+        assert_eq!(1, map.brush_entities.len());
+        assert_eq!(weather(WeatherKind::Storm, 4).sky_name, map.options.sky_name);
+    }
+}
@@ -1,5 +1,11 @@
+use std::str::FromStr;
+
 use vmf_parser_nom::ast::Property;
 
+use crate::map::{Angles, Solid, Vector3};
+use crate::source::ColorBrightness;
+use crate::StrType;
+
 /// An entity
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Entity<S> {
@@ -7,10 +13,114 @@ pub struct Entity<S> {
     // pub solid: Option<Solid<'a>>,
     // pub origin: Option<Vector3<f32>>,
     pub props: Vec<Property<S, S>>,
+    /// Outputs, in the `connections` block. Ex: `"OnTrigger" "door,Open,,0,-1"`
+    pub connections: Vec<Property<S, S>>,
 }
 
 impl<S> Entity<S> {
-    pub fn new(props: Vec<Property<S, S>>) -> Self {
-        Self { props }
+    pub const fn new(props: Vec<Property<S, S>>) -> Self {
+        Self { props, connections: Vec::new() }
+    }
+}
+
+/// Keys are case insensitive, like in Source.
+impl<S: AsRef<str>> Entity<S> {
+    /// The value of `key`, if any.
+    pub fn get(&self, key: &str) -> Option<&S> {
+        self.props
+            .iter()
+            .find(|prop| prop.key.as_ref().eq_ignore_ascii_case(key))
+            .map(|prop| &prop.value)
+    }
+
+    /// Set `key` to `value`, replacing the existing value if any.
+    pub fn set(&mut self, key: &str, value: impl Into<S>) -> &mut Self
+    where
+        S: From<String>,
+    {
+        let value = value.into();
+        match self.props.iter_mut().find(|prop| prop.key.as_ref().eq_ignore_ascii_case(key)) {
+            Some(prop) => prop.value = value,
+            None => self.props.push(Property::new(key.to_string(), value)),
+        }
+        self
+    }
+
+    /// Remove `key`, returning its value if any.
+    pub fn remove(&mut self, key: &str) -> Option<S> {
+        let index =
+            self.props.iter().position(|prop| prop.key.as_ref().eq_ignore_ascii_case(key))?;
+        Some(self.props.remove(index).value)
+    }
+
+    /// Ex: `light_environment`
+    pub fn classname(&self) -> Option<&str> {
+        self.get("classname").map(AsRef::as_ref)
+    }
+
+    /// Parse `origin`, `"x y z"`.
+    pub fn origin(&self) -> Option<Vector3<f32>> {
+        self.get("origin")
+            .and_then(|value| parse_numbers(value.as_ref()))
+            .map(Vector3::from)
+    }
+
+    /// Parse `angles`, `"pitch yaw roll"`.
+    pub fn angles(&self) -> Option<Angles> {
+        let [pitch, yaw, roll] = parse_numbers(self.get("angles")?.as_ref())?;
+        Some(Angles { pitch, yaw, roll })
+    }
+
+    /// Parse the color and brightness of a light, `_light`.
+    pub fn color(&self) -> Option<ColorBrightness> {
+        self.get("_light")?.as_ref().parse().ok()
+    }
+}
+
+/// Parse exactly `N` whitespace separated values.
+pub(crate) fn parse_numbers<T: FromStr, const N: usize>(value: &str) -> Option<[T; N]> {
+    let values: Vec<T> = value.split_whitespace().map(|n| n.parse().ok()).collect::<Option<_>>()?;
+    values.try_into().ok()
+}
+
+/// An entity made of brushes. Ex: `func_detail`, `trigger_multiple`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BrushEntity<'a> {
+    pub entity: Entity<StrType<'a>>,
+    pub solids: Vec<Solid<'a>>,
+}
+
+impl<'a> BrushEntity<'a> {
+    pub const fn new(entity: Entity<StrType<'a>>, solids: Vec<Solid<'a>>) -> Self {
+        Self { entity, solids }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accessors() {
+        let mut entity: Entity<StrType> = Entity::new(vec![
+            Property::new("classname", "light"),
+            Property::new("origin", "0 -16.5 64"),
+            Property::new("_light", "255 128 0 300"),
+        ]);
+        assert_eq!(Some("light"), entity.classname());
+        assert_eq!(Some(Vector3::new(0.0, -16.5, 64.0)), entity.origin());
+        assert_eq!(Some(ColorBrightness::new(255, 128, 0, 300)), entity.color());
+        assert_eq!(None, entity.angles());
+
+        entity.set("Angles", "-45 90 0").set("_LIGHT", "255 255 255");
+        assert_eq!(Some(Angles { pitch: -45.0, yaw: 90.0, roll: 0.0 }), entity.angles());
+        assert_eq!(Some(ColorBrightness::new(255, 255, 255, 255)), entity.color());
+        assert_eq!(4, entity.props.len());
+
+        assert_eq!(Some("0 -16.5 64".into()), entity.remove("ORIGIN"));
+        assert_eq!(None, entity.origin());
+        assert_eq!(None, entity.remove("origin"));
+        entity.set("angles", "1 2");
+        assert_eq!(None, entity.angles());
     }
 }
@@ -11,8 +11,12 @@ pub use texture::*;
 pub use vector::*;
 
 use crate::generation::Bounds;
+use crate::light::sky::{best_sky, Sky};
+use crate::light::weather::Weather;
+use crate::light::GlobalLighting;
+use crate::source::{ColorBrightness, GlobalLightShadows};
+use crate::vmf::ToLower;
 use crate::StrType;
-use vmf_parser_nom::ast::Property;
 
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct MapOptions {
@@ -43,28 +47,22 @@ pub struct Map<'a> {
     pub options: MapOptions,
     pub solids: Vec<Solid<'a>>,
     pub entities: Vec<Entity<StrType<'a>>>,
+    pub brush_entities: Vec<BrushEntity<'a>>,
 }
 
 impl<'a> Map<'a> {
     pub fn defaults_l4d2(&mut self) -> &mut Self {
         self.options.sky_name = "sky_l4d_rural02_hdr".to_string();
-        self.entities.push(Entity {
-            // c1m1_hotel
-            props: vec![
-                Property::new("origin", "0 0 0"),
-                Property::new("SunSpreadAngle", "0"),
-                Property::new("pitch", "-14"),
-                Property::new("angles", "0 30 0"),
-                Property::new("_lightscaleHDR", "1"),
-                Property::new("_lightHDR", "-1 -1 -1 1"),
-                Property::new("_light", "228 215 192 400"),
-                Property::new("_AmbientScaleHDR", "1"),
-                Property::new("_ambientHDR", "-1 -1 -1 1"),
-                Property::new("_ambient", "171 206 220 50"),
-                Property::new("classname", "light_environment"),
-            ],
-        });
-        self
+        // c1m1_hotel
+        self.set_global_lighting(GlobalLightShadows {
+            light: GlobalLighting {
+                sun_color: ColorBrightness::new(228, 215, 192, 400),
+                sun_dir: Angles { pitch: 14.0, yaw: 30.0, roll: 0.0 },
+                amb_color: ColorBrightness::new(171, 206, 220, 50),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
     }
     pub fn defaults_tf2(&mut self) -> &mut Self {
         self.options.sky_name = "sky_day01_01".to_string();
@@ -76,6 +74,53 @@ impl<'a> Map<'a> {
     pub fn add_solid(&mut self, solid: Solid<'a>) {
         self.solids.push(solid);
     }
+
+    /// Replace all global lighting entities, see [`GlobalLightShadows::CLASSNAMES`].
+    pub fn set_global_lighting(&mut self, lighting: GlobalLightShadows<StrType<'a>>) -> &mut Self {
+        self.entities.retain(|entity| {
+            let classname = entity.classname().unwrap_or_default();
+            !GlobalLightShadows::<StrType>::CLASSNAMES
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(classname))
+        });
+        self.entities.extend(lighting.into_lower());
+        self
+    }
+
+    /// Set the sky from `skies` that best matches `lighting`, aligned to its painted sun,
+    /// then [`Self::set_global_lighting`]. See [`sky`](crate::light::sky) for catalogues.
+    pub fn set_lighting_and_sky(
+        &mut self, skies: &[Sky], mut lighting: GlobalLightShadows<StrType<'a>>,
+    ) -> &mut Self {
+        let sky = best_sky(skies, &lighting.light);
+        sky.align(&mut lighting.light);
+        self.options.sky_name = sky.name.to_string();
+        self.set_global_lighting(lighting)
+    }
+
+    /// Set the sky, [`Self::set_global_lighting`], and replace any `env_wind` and
+    /// `func_precipitation` with the ones in `weather`.
+    pub fn set_weather(&mut self, weather: Weather<'a, StrType<'a>>) -> &mut Self {
+        let is = |entity: &Entity<StrType>, classname: &str| {
+            entity.classname().is_some_and(|name| name.eq_ignore_ascii_case(classname))
+        };
+        self.entities.retain(|entity| !is(entity, "env_wind"));
+        self.brush_entities.retain(|brush| !is(&brush.entity, "func_precipitation"));
+
+        self.options.sky_name = weather.sky_name.to_string();
+        self.set_global_lighting(weather.lighting);
+        self.entities.extend(weather.wind.map(ToLower::into_lower));
+        self.brush_entities.extend(weather.precipitation.map(ToLower::into_lower));
+        self
+    }
+
+    /// Sew together the edges of all displacements. See [`disp::sew()`].
+    ///
+    /// [`disp::sew()`]: crate::generation2::disp::sew
+    pub fn sew_displacements(&mut self) -> &mut Self {
+        crate::generation2::disp::sew(&mut self.solids);
+        self
+    }
 }
 
 // entity
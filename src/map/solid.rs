@@ -32,6 +32,84 @@ impl<'a> Solid<'a> {
         }
         self
     }
+
+    /// The corners of the solid and the indexes of the [`Side`]s they touch.
+    /// Calculated from plane intersections, like Hammer and vbsp do.
+    fn vertices_and_sides(&self) -> Vec<(Vector3<f32>, Vec<usize>)> {
+        const EPSILON: f32 = 1e-2;
+        // normal and distance from origin
+        let planes: Vec<_> = self
+            .sides
+            .iter()
+            .map(|side| {
+                let normal = side.plane.normal();
+                let dist = normal.dot(&side.plane.bottom_left);
+                (normal, dist)
+            })
+            .collect();
+        let on_plane = |point: &Vector3<f32>, (normal, dist): &(Vector3<f32>, f32)| {
+            (normal.dot(point) - dist).abs() < EPSILON
+        };
+
+        let mut vertices: Vec<(Vector3<f32>, Vec<usize>)> = Vec::new();
+        let len = planes.len();
+        for i in 0..len {
+            for j in i + 1..len {
+                for k in j + 1..len {
+                    let Some(point) = intersect_planes([&planes[i], &planes[j], &planes[k]]) else {
+                        continue;
+                    };
+                    // outside the solid or already found
+                    if planes.iter().any(|(normal, dist)| normal.dot(&point) - dist > EPSILON)
+                        || vertices.iter().any(|(vertex, _)| vertex.dist(&point) < EPSILON)
+                    {
+                        continue;
+                    }
+                    let sides = (0..len).filter(|&n| on_plane(&point, &planes[n])).collect();
+                    vertices.push((point, sides));
+                }
+            }
+        }
+        vertices
+    }
+
+    /// The corners of the solid, calculated from plane intersections.
+    pub fn vertices(&self) -> Vec<Vector3<f32>> {
+        self.vertices_and_sides().into_iter().map(|(vertex, _)| vertex).collect()
+    }
+
+    /// The edges of the solid as pairs of points, calculated from plane intersections.
+    /// Ex: for making a wireframe with [`wireframe()`].
+    ///
+    /// [`wireframe()`]: crate::generation2::shape::wireframe
+    pub fn edges(&self) -> Vec<(Vector3<f32>, Vector3<f32>)> {
+        let vertices = self.vertices_and_sides();
+        let mut edges = Vec::new();
+        for (i, (vertex1, sides1)) in vertices.iter().enumerate() {
+            for (vertex2, sides2) in vertices[i + 1..].iter() {
+                // an edge is where 2 sides meet
+                let shared = sides1.iter().filter(|side| sides2.contains(side)).count();
+                if shared >= 2 {
+                    edges.push((vertex1.clone(), vertex2.clone()));
+                }
+            }
+        }
+        edges
+    }
+}
+
+/// The point where 3 planes (normal and distance from origin) meet.
+/// Returns `None` if any are parallel.
+/// See <https://en.wikipedia.org/wiki/Plane_(mathematics)#Line_of_intersection_between_two_planes>
+fn intersect_planes(planes: [&(Vector3<f32>, f32); 3]) -> Option<Vector3<f32>> {
+    let [(n1, d1), (n2, d2), (n3, d3)] = planes;
+    let n2_x_n3 = n2.cross(n3);
+    let det = n1.dot(&n2_x_n3);
+    if det.abs() < 1e-6 {
+        return None;
+    }
+    let point = n2_x_n3 * *d1 + &(n3.cross(n1) * *d2) + &(n1.cross(n2) * *d3);
+    Some(point / det)
 }
 
 /// A side of a [`Solid`].
@@ -144,8 +222,8 @@ pub struct DispInfo {
     /// map. A false flag removes the vertex from the compiled map. Note that -1 is all bits set to
     /// true.
     ///
-    /// Pretty sure this can just be ignored. For sewing displacements of differing power. Its
-    /// either a miniscule optimization to save like 5 triangles or a safety net / UX feature maybe
+    /// For sewing displacements of differing power, set by
+    /// [`disp::sew()`](crate::generation2::disp::sew).
     ///
     /// All zeros means to remove all verts; vbsp will only remove some near edge and not even all
     /// of them (huh). Also last i32 always seems to be -1 / has no effect.
@@ -312,6 +390,18 @@ mod tests {
     use crate::{generation::Bounds, map::Map};
     use approx::abs_diff_eq;
 
+    #[test]
+    fn vertices_edges() {
+        let bounds = Bounds::new(Vector3::new(-64.0, -64.0, 0.0), Vector3::new(64.0, 64.0, 128.0));
+        let cube = Map::cube_dev(bounds);
+        let vertices = cube.vertices();
+        assert_eq!(8, vertices.len());
+        assert!(vertices.contains(&Vector3::new(64.0, -64.0, 128.0)));
+        let edges = cube.edges();
+        assert_eq!(12, edges.len());
+        assert!(edges.iter().all(|(a, b)| a.dist(b) == 128.0));
+    }
+
     #[test]
     fn normal() {
         let truth = [
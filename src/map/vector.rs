@@ -23,6 +23,19 @@ pub struct Angles {
     pub roll: f64,
 }
 
+impl Angles {
+    /// Unit vector in the direction of these angles, ignoring roll.
+    pub fn forward(&self) -> Vector3<f64> {
+        let (pitch, yaw) = (self.pitch.to_radians(), self.yaw.to_radians());
+        Vector3::new(pitch.cos() * yaw.cos(), pitch.cos() * yaw.sin(), -pitch.sin())
+    }
+
+    /// Facing the opposite direction. Ex: from the sun's light to the sun.
+    pub fn reversed(&self) -> Self {
+        Self { pitch: -self.pitch, yaw: (self.yaw + 180.0).rem_euclid(360.0), roll: self.roll }
+    }
+}
+
 impl Display for Angles {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{} {} {}", self.pitch, self.yaw, self.roll)
@@ -7,21 +7,35 @@ use source_map_gen::generation::Bounds;
 use source_map_gen::map::{Entity, Map};
 use source_map_gen::prelude::*;
 use source_map_gen::vmf::ToLower;
+use std::error::Error;
 use std::fs::{self, OpenOptions};
-use std::io::Write;
+use std::io::{self, Write};
+use std::str::FromStr;
 use vmf_parser_nom::ast::Property;
 use vpk::utils::PathExt;
 
 const DEV_FLOOR: &str = "dev/dev_measuregeneric01b";
 const HINT: &str = "tools/toolshint";
 
+const VPK_USAGE: &str = "usage:
+    vpk list <vpk>
+    vpk extract <vpk> <dest_dir> [path...]
+    vpk cat <vpk> <path>
+    vpk archive <src_dir> [dest_vpk] [--v1] [--chunked | --chunk-size <bytes>] [--preload <bytes>]";
+
 pub fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "vpk") {
+        if let Err(err) = vpk_command(&args[1..]) {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     // let home: PathBuf = std::env::var("HOME").unwrap().into();
     // let game_info = TF2_TF.join("gameinfo.txt");
 
-    const VPK_CMD: &str =
-        "/home/redram/.local/share/Steam/steamapps/common/Team Fortress 2/bin/vpk_linux32";
-
     let game_root: &str = "/home/redram/.local/share/Steam/steamapps/common/Left 4 Dead 2";
     let game_info =
         "/home/redram/.local/share/Steam/steamapps/common/Left 4 Dead 2/left4dead2/gameinfo.txt";
@@ -32,7 +46,7 @@ pub fn main() {
     let mut tty = OpenOptions::new().write(true).open("/dev/tty").unwrap();
     writeln!(tty, "{:#?}", search_paths).unwrap();
 
-    let content = get_content(VPK_CMD, game_root, game_info, filter_no_addons).unwrap();
+    let content = get_content(game_root, game_info, filter_no_addons).unwrap();
 
     let bad_mdls = [
         "vgui",
@@ -144,6 +158,7 @@ pub fn main() {
         //     // must be 1 unit above or leak somehow O_o
         //     Property::new("origin", "0 0 1"),
         // ],
+        connections: vec![],
     });
 
     let len = vmts.len();
@@ -227,6 +242,55 @@ pub fn main() {
     // TODO: merge vpk, vmt, vmt
 }
 
+/// `vpk` subcommands, see [`VPK_USAGE`].
+fn vpk_command(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args[..] {
+        ["list", src_vpk] => {
+            for path in vpk::list(src_vpk)? {
+                println!("{path}");
+            }
+        }
+        ["extract", src_vpk, dest_dir] => {
+            let options = vpk::Options { threads: 0, show_progress: true, ..Default::default() };
+            vpk::extract_all(src_vpk, dest_dir, options)?;
+        }
+        ["extract", src_vpk, dest_dir, ref paths @ ..] => {
+            for path in paths {
+                eprintln!("extracted `{}`", vpk::extract_one(src_vpk, dest_dir, path)?.display());
+            }
+        }
+        ["cat", src_vpk, path] => {
+            io::stdout().write_all(&vpk::VpkArchive::open(src_vpk)?.read(path)?)?;
+        }
+        ["archive", src_dir, ref rest @ ..] => {
+            let mut options = vpk::WriteOptions::default();
+            let mut dest_vpk = None;
+            let mut rest = rest.iter();
+            while let Some(&arg) = rest.next() {
+                match arg {
+                    "--v1" => options.version = 1,
+                    "--chunked" => options.chunk_size = Some(vpk::WriteOptions::VALVE_CHUNK_SIZE),
+                    "--chunk-size" => options.chunk_size = Some(parse_value(rest.next())?),
+                    "--preload" => options.preload_size = parse_value(rest.next())?,
+                    _ if dest_vpk.is_none() && !arg.starts_with("--") => dest_vpk = Some(arg),
+                    _ => return Err(VPK_USAGE.into()),
+                }
+            }
+            for path in vpk::archive(src_dir, dest_vpk, options)? {
+                eprintln!("wrote `{}`", path.display());
+            }
+        }
+        _ => return Err(VPK_USAGE.into()),
+    }
+    Ok(())
+}
+
+/// Parse the value after a flag, the usage if it's missing or invalid.
+fn parse_value<T: FromStr>(value: Option<&&str>) -> Result<T, &'static str> {
+    value.and_then(|value| value.parse().ok()).ok_or(VPK_USAGE)
+}
+
 fn xy_to_bounds(
     col: i32, row: i32, col_size: i32, row_size: i32, top: f32, bottom: f32,
 ) -> Bounds<f32> {
@@ -1,8 +1,10 @@
 use crate::pallet::vpk;
-use vpk::utils::*;
-use vpk::{VpkError, VpkResult};
+use source_map_gen::source::{KeyValues, PLATFORM_CONDITIONS};
 use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
+use vpk::utils::*;
+use vpk::{VpkArchive, VpkError, VpkResult};
 use walkdir::WalkDir;
 
 // use std::io::Result as IoResult;
@@ -24,54 +26,172 @@ use walkdir::WalkDir;
 // |gameinfo_path|. = you know
 // multichunk vpks = .vpk no _dir, or _###
 
-// FIXME: be more inclusive, tf2 mega fails
-// TODO: dont convert path case
-/// Parse `gameinfo.txt` and return the search paths. Simply returns the trimmed
-/// text after "Game" in "SearchPaths{}".
-///
-/// Interprets `|gameinfo_path|` to the parent directory of `game_info` as an absolute path.
-///
-/// See <https://developer.valvesoftware.com/wiki/Gameinfo.txt>
-///
-/// # Notes
-///
-/// Will convert all paths to lowercase.
-///
-/// # Errors
+/// What a search path points to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SearchPathKind {
+    /// A loose directory.
+    Dir,
+    /// A vpk, the `_dir.vpk` of multi-part vpks.
+    Vpk,
+}
+
+/// A path from `SearchPaths` in `gameinfo.txt`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchPath {
+    /// Path IDs, lowercase. Ex: `["game", "mod"]` from `game+mod`.
+    pub ids: Vec<String>,
+    pub path: PathBuf,
+    pub kind: SearchPathKind,
+}
+
+impl SearchPath {
+    /// Has the path ID `id`, case insensitive. Ex: `game`, `mod`, `platform`, `gamebin`.
+    pub fn has_id(&self, id: &str) -> bool {
+        self.ids.iter().any(|i| i.eq_ignore_ascii_case(id))
+    }
+}
+
+/// The search paths of a game, highest priority first like in `gameinfo.txt`.
 ///
-/// Returns `None` if `game_root` isn't a directory or doesn't exist.
-/// Returns `None` if `game_info` doesn't exist or couldn't be parsed.
-pub fn get_search_paths(
-    game_root: impl AsRef<Path>, game_info: impl AsRef<Path>,
-) -> Option<Vec<PathBuf>> {
-    fn search_paths(game_root: &Path, game_info: &Path) -> Option<Vec<PathBuf>> {
-        // TODO: fix vmf_parser to allow quoted block args, and non str props and use that
-        let game_info = game_info.canonicalize().ok()?;
-        let string = fs::read_to_string(&game_info).ok()?;
-        let game_info_path = game_info.parent()?;
+/// See <https://developer.valvesoftware.com/wiki/Gameinfo.txt#Search_paths>
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchPaths {
+    pub paths: Vec<SearchPath>,
+}
 
-        // (horribly) get str between /SearchPaths.*{/ and /}/
-        let after_open_brace = string.split_once("SearchPaths")?.1.split_once('{')?.1;
-        let between_braces = after_open_brace.split_once('}')?.0;
+impl SearchPaths {
+    /// Load `game_info` and resolve its search paths, see [`Self::from_keyvalues`].
+    /// `game_root` is the directory with the game's executable.
+    ///
+    /// # Errors
+    ///
+    /// * `game_info` must exist and be valid KeyValues with a `SearchPaths` block.
+    pub fn load(game_root: impl AsRef<Path>, game_info: impl AsRef<Path>) -> io::Result<Self> {
+        fn load(game_root: &Path, game_info: &Path) -> io::Result<SearchPaths> {
+            let game_info = game_info.canonicalize()?;
+            let game_info_dir = game_info.parent().unwrap_or(Path::new(""));
+            let kv = KeyValues::load(&game_info)?;
+            SearchPaths::from_keyvalues(&kv, game_root, game_info_dir).ok_or_else(|| {
+                io_error_other(format!("no SearchPaths in `{}`", game_info.display()))
+            })
+        }
+        load(game_root.as_ref(), game_info.as_ref())
+    }
+
+    /// Resolve `GameInfo/FileSystem/SearchPaths` in a parsed `gameinfo.txt`, for this platform.
+    /// `None` if there is no `SearchPaths` block.
+    ///
+    /// * `|gameinfo_path|` is `game_info_dir` and `|all_source_engine_paths|` or no prefix is
+    ///   `game_root`. Case is kept.
+    /// * `.vpk`s are their `_dir.vpk` if it exists.
+    /// * Wildcards like `custom/*` are every matching directory and vpk, sorted.
+    ///
+    /// Other paths aren't checked, the game skips missing paths.
+    pub fn from_keyvalues(kv: &KeyValues, game_root: &Path, game_info_dir: &Path) -> Option<Self> {
+        let kv = kv.filtered(PLATFORM_CONDITIONS);
+        let search_paths = kv.block("GameInfo")?.block("FileSystem")?.block("SearchPaths")?;
 
-        // parse SearchPaths{} return second field trimmed
         let mut paths = Vec::new();
-        for line in between_braces.lines() {
-            // TODO: be more inclusive, for things like "Mod" and "blahblah"
-            let Some((_, path)) = line.split_once("Game") else { continue };
-            // TODO: horrible
-            let path = path.trim().to_lowercase();
-            if path.contains("custom") {
-                continue;
+        for pair in &search_paths.pairs {
+            let Some(value) = pair.value.as_str() else { continue };
+            let ids: Vec<_> = pair.key.split('+').map(str::to_lowercase).collect();
+            for (path, kind) in resolve_search_path(value, game_root, game_info_dir) {
+                paths.push(SearchPath { ids: ids.clone(), path, kind });
             }
-            let path = path.replace("|gameinfo_path|", &format!("{}/", game_info_path.display()));
-            let path = game_root.join(path);
-            paths.push(path);
         }
+        Some(Self { paths })
+    }
+
+    /// Paths with the path ID `id`, in order. Ex: `game` for all game content.
+    pub fn with_id<'a>(&'a self, id: &'a str) -> impl Iterator<Item = &'a SearchPath> {
+        self.paths.iter().filter(move |path| path.has_id(id))
+    }
+}
+
+/// Strip a `|prefix|`, case insensitive.
+fn strip_prefix_ci<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    let start = s.get(..prefix.len())?;
+    start.eq_ignore_ascii_case(prefix).then(|| &s[prefix.len()..])
+}
+
+fn resolve_search_path(
+    value: &str, game_root: &Path, game_info_dir: &Path,
+) -> Vec<(PathBuf, SearchPathKind)> {
+    let (base, rest) = if let Some(rest) = strip_prefix_ci(value, "|gameinfo_path|") {
+        (game_info_dir, rest)
+    } else if let Some(rest) = strip_prefix_ci(value, "|all_source_engine_paths|") {
+        (game_root, rest)
+    } else {
+        (game_root, value)
+    };
+    // without `.` so `|gameinfo_path|.` is the directory itself
+    let path = rest
+        .split(['/', '\\'])
+        .filter(|part| !part.is_empty() && *part != ".")
+        .fold(base.to_path_buf(), |path, part| path.join(part));
+
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned());
+    match name {
+        Some(name) if name.contains('*') => expand_wildcard(&path, &name),
+        Some(_) if path.has_ext("vpk") => vec![(dir_vpk(path), SearchPathKind::Vpk)],
+        _ => vec![(path, SearchPathKind::Dir)],
+    }
+}
 
-        Some(paths)
+/// `name.vpk` -> `name_dir.vpk` if it exists.
+fn dir_vpk(vpk: PathBuf) -> PathBuf {
+    let stem = vpk.file_stem().unwrap_or_default().to_string_lossy();
+    let dir_vpk = vpk.with_file_name(format!("{stem}_dir.vpk"));
+    if dir_vpk.is_file() {
+        dir_vpk
+    } else {
+        vpk
     }
-    search_paths(game_root.as_ref(), game_info.as_ref())
+}
+
+/// Directories and vpks matching a `*` in the file name of `path`, ignoring parts of
+/// multi-part vpks.
+fn expand_wildcard(path: &Path, pattern: &str) -> Vec<(PathBuf, SearchPathKind)> {
+    let (Some(dir), Some((prefix, suffix))) = (path.parent(), pattern.split_once('*')) else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(dir) else { return Vec::new() };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            name.starts_with(prefix) && name.ends_with(suffix)
+        })
+        .filter_map(|path| {
+            if path.is_dir() {
+                Some((path, SearchPathKind::Dir))
+            } else if path.has_ext("vpk") && path.is_proper_vpk_ish().is_ok() {
+                Some((path, SearchPathKind::Vpk))
+            } else {
+                None
+            }
+        })
+        .collect();
+    paths.sort_by(|(a, _), (b, _)| a.cmp(b));
+    paths
+}
+
+/// Parse `gameinfo.txt` and return the `game` search paths, highest priority first.
+/// See [`SearchPaths`].
+///
+/// # Errors
+///
+/// Returns `None` if `game_info` doesn't exist or couldn't be parsed.
+pub fn get_search_paths(
+    game_root: impl AsRef<Path>, game_info: impl AsRef<Path>,
+) -> Option<Vec<PathBuf>> {
+    let search_paths = SearchPaths::load(game_root, game_info).ok()?;
+    Some(
+        search_paths
+            .with_id("game")
+            .map(|search_path| search_path.path.clone())
+            .collect(),
+    )
 }
 
 /// handle_dir
@@ -113,43 +233,53 @@ impl Content {
     }
 }
 
+/// Filter out addon, workshop, custom and download folders by name, for [`dir_content`].
 pub fn filter_no_addons(entry: &walkdir::DirEntry) -> bool {
-    let path = entry.path();
-    !(path.contains("addon")
+    !is_addon(Path::new(entry.file_name()))
+}
+
+/// `path` mentions addons, workshop, custom or download content. Pass a file name or a path
+/// relative to a search path, so the game's install path doesn't count.
+fn is_addon(path: &Path) -> bool {
+    path.contains("addon")
         || path.contains("workshop")
         || path.contains("custom")
-        || path.contains("download"))
+        || path.contains("download")
 }
 
 // TODO: docs
 pub fn get_content<P>(
-    vpk_bin: impl AsRef<Path>, game_root: impl AsRef<Path>, game_info: impl AsRef<Path>, filter: P,
+    game_root: impl AsRef<Path>, game_info: impl AsRef<Path>, filter: P,
 ) -> VpkResult<Content>
 where
     P: FnMut(&walkdir::DirEntry) -> bool,
 {
     fn get_content<P>(
-        vpk_bin: &Path, game_root: impl AsRef<Path>, game_info: &Path, mut filter: P,
+        game_root: impl AsRef<Path>, game_info: &Path, mut filter: P,
     ) -> VpkResult<Content>
     where
         P: FnMut(&walkdir::DirEntry) -> bool,
     {
         // get search paths from gameinfo.txt
-        let search_paths = get_search_paths(game_root, game_info)
-            .ok_or_else(|| {
-                io_error_other(format!("Error parsing game_info.txt: `{}`", game_info.display()))
-            })
-            .map_err(VpkError::Src)?;
+        let search_paths = SearchPaths::load(game_root, game_info).map_err(VpkError::Src)?;
 
         // get content from search paths, including vpks
         let mut content = Content::new();
-        for path in search_paths {
-            dir_content(vpk_bin, path, &mut content, &mut filter)?;
+        for search_path in search_paths.with_id("game") {
+            let path = &search_path.path;
+            // skip custom content like `tf/custom/*`, and missing paths like the game does
+            if search_path.has_id("custom_mod") || !path.exists() {
+                continue;
+            }
+            match search_path.kind {
+                SearchPathKind::Dir => dir_content(path, &mut content, &mut filter)?,
+                SearchPathKind::Vpk => vpk_content(path, &mut content)?,
+            }
         }
         Ok(content)
     }
 
-    get_content(vpk_bin.as_ref(), game_root.as_ref(), game_info.as_ref(), filter)
+    get_content(game_root.as_ref(), game_info.as_ref(), filter)
 }
 
 /// Recursivly get [`Content`] from a directory, including any nested directories
@@ -160,13 +290,11 @@ where
 ///
 /// * `dir` must be a directory and exist.
 /// * [`vpk_content`] must not fail.
-pub fn dir_content<P>(
-    vpk_bin: impl AsRef<Path>, dir: impl AsRef<Path>, content: &mut Content, filter: P,
-) -> VpkResult<()>
+pub fn dir_content<P>(dir: impl AsRef<Path>, content: &mut Content, filter: P) -> VpkResult<()>
 where
     P: FnMut(&walkdir::DirEntry) -> bool,
 {
-    fn dir_content<P>(vpk_bin: &Path, dir: &Path, content: &mut Content, filter: P) -> VpkResult<()>
+    fn dir_content<P>(dir: &Path, content: &mut Content, filter: P) -> VpkResult<()>
     where
         P: FnMut(&walkdir::DirEntry) -> bool,
     {
@@ -184,12 +312,8 @@ where
 
         for entry in entries.filter(filter) {
             let path = entry.path();
-            if path.contains("addon")
-                || path.contains("workshop")
-                || path.contains("custom")
-                || path.contains("download")
-            {
-                panic!("bruh impossible");
+            // relative so the game's install path doesn't count
+            if is_addon(path.strip_prefix(dir).unwrap_or(path)) {
                 continue;
             }
 
@@ -198,7 +322,7 @@ where
                 // wait... this is handled by WalkDir
                 // dir if path.is_dir() => {
                 //     dbg!(dir);
-                //     dir_content(vpk_bin, dir, content)?;
+                //     dir_content(dir, content)?;
                 // }
                 mdl if path.has_ext("mdl") => {
                     content.mdl.push(mdl.to_path_buf());
@@ -207,7 +331,7 @@ where
                     content.vmt.push(vmt.to_path_buf());
                 }
                 vpk if path.has_ext("vpk") => {
-                    vpk_content(vpk_bin, vpk, content)?;
+                    vpk_content(vpk, content)?;
                 }
                 _ => (),
             }
@@ -215,28 +339,26 @@ where
         Ok(())
     }
 
-    dir_content(vpk_bin.as_ref(), dir.as_ref(), content, filter)
+    dir_content(dir.as_ref(), content, filter)
 }
 
-/// Get [`Content`] from a vpk file. Does nothing if there is no content, if it
-/// is a sub-part of a multi-part vpk file, or if `vpk_linux32` silently fails.
+/// Get [`Content`] from a vpk file. Does nothing if there is no content or if it
+/// is a sub-part of a multi-part vpk file.
 /// Debug prints if there any nested vpk files.
 ///
 /// # Errors
 ///
 /// * `vpk` must be a vpk and exist.
-/// * [`vpk::list()`] must not fail.
-pub fn vpk_content(
-    vpk_bin: impl AsRef<Path>, vpk: impl AsRef<Path>, content: &mut Content,
-) -> VpkResult<()> {
-    fn vpk_content(vpk_bin: &Path, vpk: &Path, content: &mut Content) -> VpkResult<()> {
+/// * [`VpkArchive::open()`] must not fail.
+pub fn vpk_content(vpk: impl AsRef<Path>, content: &mut Content) -> VpkResult<()> {
+    fn vpk_content(vpk: &Path, content: &mut Content) -> VpkResult<()> {
         vpk.vpk_exists().map_err(VpkError::Src)?;
         if vpk.is_proper_vpk_ish().is_err() {
             return Ok(());
         }
 
-        let entries = vpk::list(vpk_bin, vpk)?;
-        for entry in entries.lines() {
+        let archive = VpkArchive::open(vpk)?;
+        for entry in archive.paths() {
             let path = Path::new(entry);
             match path {
                 mdl if path.has_ext("mdl") => content.mdl.push(mdl.to_path_buf()),
@@ -252,7 +374,7 @@ pub fn vpk_content(
         Ok(())
     }
 
-    vpk_content(vpk_bin.as_ref(), vpk.as_ref(), content)
+    vpk_content(vpk.as_ref(), content)
 }
 
 #[cfg(test)]
@@ -278,4 +400,137 @@ mod tests {
         let result = get_search_paths(L4D2_ROOT, L4D2_GAMEINFO).unwrap();
         assert_eq!(truth, result);
     }
+
+    #[test]
+    fn search_paths_l4d2() {
+        let kv = KeyValues::parse(
+            r#""GameInfo"
+{
+    game "Left 4 Dead 2"
+    FileSystem
+    {
+        SteamAppId 550
+        SearchPaths
+        {
+            Game update
+            Game left4dead2_dlc3
+            Game+Mod |gameinfo_path|.
+            Game hl2
+            platform |all_source_engine_paths|platform/platform_misc.vpk
+        }
+    }
+}"#,
+        )
+        .unwrap();
+        let root = Path::new("root");
+        let gameinfo_dir = root.join("left4dead2");
+        let search_paths = SearchPaths::from_keyvalues(&kv, root, &gameinfo_dir).unwrap();
+        let paths: Vec<_> = search_paths.with_id("GAME").map(|p| p.path.clone()).collect();
+        assert_eq!(
+            [
+                root.join("update"),
+                root.join("left4dead2_dlc3"),
+                gameinfo_dir.clone(),
+                root.join("hl2")
+            ],
+            &paths[..]
+        );
+        let mod_paths: Vec<_> = search_paths.with_id("mod").collect();
+        assert_eq!(1, mod_paths.len());
+        assert_eq!(["game", "mod"], &mod_paths[0].ids[..]);
+
+        let platform = search_paths.paths.last().unwrap();
+        assert_eq!(SearchPathKind::Vpk, platform.kind);
+        assert_eq!(root.join("platform").join("platform_misc.vpk"), platform.path);
+        assert!(!platform.has_id("game"));
+
+        assert!(SearchPaths::from_keyvalues(&KeyValues::default(), root, &gameinfo_dir).is_none());
+    }
+
+    #[test]
+    fn search_paths_tf2() {
+        let root = Path::new("test/test_search_paths");
+        let tf = root.join("tf");
+        let _ = fs::remove_dir_all(root);
+        fs::create_dir_all(tf.join("custom/my_mod")).unwrap();
+        fs::create_dir_all(root.join("hl2")).unwrap();
+        for file in [
+            "tf/tf2_textures_dir.vpk",
+            "tf/tf2_textures_000.vpk",
+            "tf/custom/pack_dir.vpk",
+            "tf/custom/pack_000.vpk",
+            "tf/custom/readme.txt",
+        ] {
+            fs::write(root.join(file), "").unwrap();
+        }
+        fs::write(
+            tf.join("gameinfo.txt"),
+            r#""GameInfo"
+{
+    FileSystem
+    {
+        SearchPaths
+        {
+            game+mod+custom_mod |gameinfo_path|custom/*
+            game+game_write tf/tf2_textures.vpk
+            game+mod+mod_write+default_write_path |gameinfo_path|.
+            game |all_source_engine_paths|hl2
+        }
+    }
+}"#,
+        )
+        .unwrap();
+
+        let result = SearchPaths::load(root, tf.join("gameinfo.txt"));
+        let game_paths = get_search_paths(root, tf.join("gameinfo.txt"));
+        let tf = tf.canonicalize().unwrap();
+        let _ = fs::remove_dir_all(root);
+
+        let search_paths = result.unwrap();
+        let kinds: Vec<_> = search_paths.paths.iter().map(|p| p.kind).collect();
+        use SearchPathKind::*;
+        assert_eq!([Dir, Vpk, Vpk, Dir, Dir], &kinds[..]);
+        assert!(search_paths.paths[0].path.ends_with("custom/my_mod"));
+        assert!(search_paths.paths[1].path.ends_with("custom/pack_dir.vpk"));
+        assert_eq!(root.join("tf/tf2_textures_dir.vpk"), search_paths.paths[2].path);
+        assert_eq!(tf, search_paths.paths[3].path);
+        assert_eq!(root.join("hl2"), search_paths.paths[4].path);
+        assert_eq!(5, game_paths.unwrap().len());
+    }
+
+    #[test]
+    fn content_custom() {
+        // the install path having "custom" in it shouldn't matter
+        let root = Path::new("test/test_content/custom_install");
+        let tf = root.join("tf");
+        _ = fs::remove_dir_all(root.parent().unwrap());
+        for dir in ["materials", "models", "custom/my_mod/materials"] {
+            fs::create_dir_all(tf.join(dir)).unwrap();
+        }
+        for file in ["materials/a.vmt", "models/b.mdl", "custom/my_mod/materials/c.vmt"] {
+            fs::write(tf.join(file), "").unwrap();
+        }
+        fs::write(
+            tf.join("gameinfo.txt"),
+            r#"GameInfo { FileSystem { SearchPaths {
+                game+mod+custom_mod |gameinfo_path|custom/*
+                game+mod |gameinfo_path|.
+                game |all_source_engine_paths|hl2
+            } } }"#,
+        )
+        .unwrap();
+
+        let content = get_content(root, tf.join("gameinfo.txt"), filter_no_addons);
+        _ = fs::remove_dir_all(root.parent().unwrap());
+
+        let (mdl, vmt) = content.unwrap().into_parts();
+        let names = |paths: Vec<PathBuf>| -> Vec<_> {
+            paths
+                .iter()
+                .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+                .collect()
+        };
+        assert_eq!(vec!["b.mdl"], names(mdl));
+        assert_eq!(vec!["a.vmt"], names(vmt));
+    }
 }
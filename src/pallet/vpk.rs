@@ -1,20 +1,21 @@
-//! Simple wrapper around vpk_linux32 to actually get it to work
+//! List, extract and archive vpks, without `vpk_linux32`.
+//!
+//! See [`reader`] and [`writer`] for the format.
 // TODO: docs
 // TODO: extract_once doc
 // TODO: options and x_once optional dest dir
 // TODO: when is cannonicalize nessessary?
 
 // pub(crate) mod io_utils;
+pub(crate) mod reader;
+pub(crate) mod writer;
 
-use std::collections::HashSet;
 use std::io::Error as IoError;
-use std::path::Path;
-use std::process::{Command, ExitStatus, Stdio};
-use std::{env, fs};
+use std::path::{Path, PathBuf};
 
+pub use reader::*;
 use utils::*;
-use rayon::prelude::ParallelIterator;
-use rayon::str::ParallelString;
+pub use writer::*;
 
 pub(crate) mod utils {
     use std::ffi::OsStr;
@@ -187,12 +188,12 @@ pub enum VpkError {
     Dest(IoError),
     /// Miscellaneous io error. Usually creating file/folders.
     MiscIo(IoError),
-    /// Cmd path is invalid.
-    CmdPath(IoError),
-    /// Error starting a command.
-    CmdExec(IoError),
-    /// A command exited with a failure exit status.
-    CmdFailed(ExitStatus),
+    /// Invalid or unsupported vpk directory.
+    Format(String),
+    /// File data doesn't match the CRC in the directory.
+    Crc { path: String, expected: u32, found: u32 },
+    /// No file with this path in the vpk.
+    NotFound(String),
 }
 
 /// Convience for
@@ -208,9 +209,11 @@ impl std::fmt::Display for VpkError {
             VpkError::Src(err) => write!(f, "Source dir/vpk error: {err}"),
             VpkError::Dest(err) => write!(f, "Destination dir/vpk error: {err}"),
             VpkError::MiscIo(err) => write!(f, "Misc io error: {err}"),
-            VpkError::CmdPath(err) => write!(f, "Command path error: {err}"),
-            VpkError::CmdExec(err) => write!(f, "Command exec error: {err}"),
-            VpkError::CmdFailed(err) => write!(f, "Command failed: {err}"),
+            VpkError::Format(err) => write!(f, "Invalid vpk: {err}"),
+            VpkError::Crc { path, expected, found } => {
+                write!(f, "CRC mismatch for `{path}`: expected {expected:08x}, found {found:08x}")
+            }
+            VpkError::NotFound(path) => write!(f, "`{path}` not found in vpk"),
         }
     }
 }
@@ -237,197 +240,83 @@ impl Default for Options {
 // canonicalize() or parent() return Src or Dest errors
 // creating/renaming files/dirs return MiscIo errors
 
-/// Runs `vpk_linux32` with the appropriate `LD_LIBRARY_PATH` enviroment variable
-/// set to get it to run.
-/// UNTESTED: MIGHT work on Windows with vpk.exe.
-///
-/// Port of the script on [Valve Developer Community](https://developer.valvesoftware.com/wiki/VPK#Linux_.2F_Unix)
-pub fn vpk_cmd(vpk: impl AsRef<Path>) -> VpkResult<Command> {
-    fn vpk_cmd(vpk: &Path) -> VpkResult<Command> {
-        vpk.file_exists().map_err(VpkError::CmdPath)?;
-        let vpk = env::current_dir()?.join(vpk);
-
-        let parent = vpk.parent().ok_or_else(|| {
-            VpkError::CmdPath(io_error_other(format!(
-                "`{}` has no parent directory",
-                vpk.display()
-            )))
-        })?;
-        parent.dir_exists().map_err(VpkError::CmdPath)?;
-
-        let mut cmd = Command::new(&vpk);
-        cmd.env("LD_LIBRARY_PATH", parent);
-        Ok(cmd)
-    }
-    vpk_cmd(vpk.as_ref())
-}
-
-/// Get a list of all the files in a vpk, separated by a newline.
-///
-/// See [`String::from_utf8_lossy`].
+/// Get a list of all the files in a vpk, in the order of its directory tree.
+/// See [`VpkArchive::paths`].
 ///
 /// # Note
 /// Converts invalid UTF-8 bytes to the replacement character `U+FFFD`.
 ///
 /// # Errors
-/// * `cmd_path` must be the path to vpk_linux32.
-/// * `vpk` must exist.
-/// * Returns `Err(VpkError::CmdFailed)` if the listing failed, although it seems
-/// to just silently fail.
-pub fn list(cmd_path: impl AsRef<Path>, vpk: impl AsRef<Path>) -> VpkResult<String> {
-    fn list(path: &Path, vpk: &Path) -> VpkResult<String> {
-        vpk.vpk_exists().map_err(VpkError::Src)?;
-        let output =
-            vpk_cmd(path)?.args(["l".as_ref(), vpk]).output().map_err(VpkError::CmdExec)?;
-
-        // vpk_linux32 seems to just silently fail
-        if !output.status.success() {
-            return Err(VpkError::CmdFailed(output.status));
-        }
-
-        Ok(string_from_utf8_lossy(output.stdout))
-    }
-    list(cmd_path.as_ref(), vpk.as_ref())
+/// * `vpk` must exist and be a valid vpk, see [`VpkArchive::open`].
+pub fn list(vpk: impl AsRef<Path>) -> VpkResult<Vec<String>> {
+    Ok(VpkArchive::open(vpk)?.paths().map(str::to_string).collect())
 }
 
-/// Extract a vpk to a folder, one file at a time. If you dont do this, it breaks
-/// and gives weird output, filenames, and/or crashes.
+/// Extract a vpk to a folder, creating any folders. See [`VpkArchive::extract_all`].
+///
+/// # Errors
+/// * `src_vpk` must exist and be a valid vpk, see [`VpkArchive::open`].
 pub fn extract_all(
-    cmd_path: impl AsRef<Path>, src_vpk: impl AsRef<Path>, dest_dir: impl AsRef<Path>,
-    options: Options,
+    src_vpk: impl AsRef<Path>, dest_dir: impl AsRef<Path>, options: Options,
 ) -> VpkResult<()> {
-    fn extract_all(
-        cmd_path: &Path, src_vpk: &Path, dest_dir: &Path, options: Options,
-    ) -> VpkResult<()> {
-        let files = list(cmd_path, src_vpk)?;
-
-        // `vpk x VPK FILE` doesnt create folder structure itself.
-        // This collects all folders and sub/parent folders so we can make all
-        // folders in one shot, to minimize calls to the file system.
-        let parents: HashSet<&Path> = files
-            .lines()
-            .flat_map(|file| {
-                Path::new(file)
-                    .ancestors()
-                    .skip(1)
-                    .filter(|parent| !parent.as_os_str().is_empty())
-            })
-            .collect();
-
-        // TODO: prevent unnessessary dir creation for nested dirs
-        for dir in parents {
-            if options.verbose {
-                eprintln!("creating dir `{}`", dir.display());
-            }
-            fs::create_dir_all(dest_dir.join(dir))?;
-        }
-
-        // extract files one by one
-        fs::create_dir_all(dest_dir)?;
-        let src_vpk = src_vpk.canonicalize().map_err(VpkError::Src)?;
-        files
-            .par_lines()
-            .map(|file| extract_one(cmd_path, &src_vpk, dest_dir, file))
-            .collect()
+    fn extract_all(src_vpk: &Path, dest_dir: &Path, options: Options) -> VpkResult<()> {
+        VpkArchive::open(src_vpk)?.extract_all(dest_dir, &options)
     }
 
-    extract_all(cmd_path.as_ref(), src_vpk.as_ref(), dest_dir.as_ref(), options)
+    extract_all(src_vpk.as_ref(), dest_dir.as_ref(), options)
 }
 
-// TODO: docs and optional
-// TODO: inputs must be valid (keep unchecked version around for extract_all)
-fn extract_one(
-    cmd_path: impl AsRef<Path>, src_vpk: impl AsRef<Path>, dest_dir: impl AsRef<Path>,
-    entry: impl AsRef<Path>,
-) -> VpkResult<()> {
-    fn extract_one(
-        cmd_path: &Path, src_vpk: &Path, dest_dir: &Path, entry: &Path,
-    ) -> VpkResult<()> {
-        let status = vpk_cmd(cmd_path)?
-            .args(["x".as_ref(), src_vpk, entry])
-            .current_dir(dest_dir)
-            .stdin(Stdio::null())
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .map_err(VpkError::CmdExec)?;
-        if !status.success() {
-            return Err(VpkError::CmdFailed(status));
-        }
-        Ok(())
-    }
-
-    extract_one(cmd_path.as_ref(), src_vpk.as_ref(), dest_dir.as_ref(), entry.as_ref())
+/// Extract the file at `path` in `src_vpk` to the same path in `dest_dir`.
+/// Returns the path of the extracted file.
+///
+/// # Errors
+/// * `src_vpk` must exist and be a valid vpk, see [`VpkArchive::open`].
+/// * Returns `Err(VpkError::NotFound)` if there is no file at `path`, see [`VpkArchive::get`].
+pub fn extract_one(
+    src_vpk: impl AsRef<Path>, dest_dir: impl AsRef<Path>, path: &str,
+) -> VpkResult<PathBuf> {
+    let vpk = VpkArchive::open(src_vpk)?;
+    let entry = vpk.get(path).ok_or_else(|| VpkError::NotFound(path.to_string()))?;
+    vpk.extract_entry(entry, dest_dir)
 }
 
-// TODO: copy folder to prevent clobbering
-/// Archive the contents of `src_dir` into a vpk at its parent folder or
-/// move into `dest_vpk` if it is `Some`. Creates any nessessary folders.
+/// Archive the contents of `src_dir` into a vpk at its parent folder or into `dest_vpk`
+/// if it is `Some`. Creates any nessessary folders.
+/// Returns the paths of the directory and any chunks, see [`VpkWriter::write`].
 ///
 /// # Note
-/// `vpk_valve32` always outputs to `src_dir/../src_dir.vpk`, so will overwrite anything there.
+/// Without `dest_vpk`, always outputs to `src_dir/../src_dir.vpk`, so will overwrite anything
+/// there.
 ///
 /// # Errors
-/// * `src_dir` must be a exist and be directory. `dest_vpk` must have a `.vpk` extension.
-/// * Errors if `dest_vpk` is on a different file system than `src_dir`.See [`fs::rename`]
-/// * Can error when creating `dest_dir` parent folders
+/// * `src_dir` must exist, be a directory and have files. `dest_vpk` must have a `.vpk`
+///   extension.
+/// * Can error when creating `dest_vpk` parent folders
 /// * Can error for all other file operations.
 pub fn archive(
-    cmd_path: impl AsRef<Path>, src_dir: impl AsRef<Path>, dest_vpk: Option<impl AsRef<Path>>,
-) -> VpkResult<()> {
-    // creates `dest_vpk` folders
-    // archives `src_dir`
-    // moves `src_dir.vpk` into `dest_vpk`
-    fn archive(cmd_path: &Path, src_dir: &Path, dest_vpk: Option<&Path>) -> VpkResult<()> {
-        // Create nessessary folders for dest_vpk
-        if let Some(dest_vpk) = dest_vpk {
-            // dont use is_vpk, doesnt exist yet
-            dest_vpk.has_ext_res("vpk").map_err(VpkError::Dest)?;
-
-            let dest_vpk = env::current_dir()?.join(dest_vpk);
-            let dest_dir = dest_vpk
-                .parent()
-                .ok_or_else(|| {
-                    io_error_other(format!("`{}` has no parent dir", dest_vpk.display()))
-                })
-                .map_err(VpkError::Dest)?;
-            fs::create_dir_all(dest_dir)?;
+    src_dir: impl AsRef<Path>, dest_vpk: Option<impl AsRef<Path>>, options: WriteOptions,
+) -> VpkResult<Vec<PathBuf>> {
+    fn archive(
+        src_dir: &Path, dest_vpk: Option<&Path>, options: WriteOptions,
+    ) -> VpkResult<Vec<PathBuf>> {
+        let mut writer = VpkWriter::new(options);
+        writer.add_dir(src_dir)?;
+        if writer.paths().next().is_none() {
+            let err = io_error_other(format!("`{}` has no files", src_dir.display()));
+            return Err(VpkError::Src(err));
         }
 
-        // Archive path/to/src_dir -> path/to/src_dir.vpk
-        src_dir.dir_exists().map_err(VpkError::Src)?;
-        let src_dir = src_dir.canonicalize().map_err(VpkError::Src)?;
-        let mut cmd = vpk_cmd(cmd_path).unwrap();
-        let status = cmd
-            // .args([Path::new("a"), &src_dir])
-            .args([&src_dir])
-            .stdin(Stdio::null())
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .map_err(VpkError::CmdExec)?;
-
-        if !status.success() {
-            return Err(VpkError::CmdFailed(status));
-        }
-
-        // Move out_vpk to dest_vpk
-        if let Some(dest_vpk) = dest_vpk {
-            // path/to/src_dir -> path/to/src_dir.vpk
-            let mut out_vpk = src_dir;
-            out_vpk.set_extension("vpk");
-
-            // std::fs really needs a move function
-            // fs::rename renames OR moves (errors if cross-filessytems)
-            fs::rename(out_vpk, dest_vpk)?;
-        }
-
-        Ok(())
+        // path/to/src_dir -> path/to/src_dir.vpk
+        let dest_vpk = match dest_vpk {
+            Some(dest_vpk) => dest_vpk.to_path_buf(),
+            None => src_dir.canonicalize().map_err(VpkError::Src)?.with_extension("vpk"),
+        };
+        writer.write(dest_vpk)
     }
 
     // Some(T) -> Some(T.as_ref()) rigmarole
     let dest_vpk = dest_vpk.as_ref().map(|v| v.as_ref());
-    archive(cmd_path.as_ref(), src_dir.as_ref(), dest_vpk)
+    archive(src_dir.as_ref(), dest_vpk, options)
 }
 
 #[cfg(test)]
@@ -439,6 +328,7 @@ mod tests {
     #[cfg(not(unix))]
     compile_error!("can only be tested on Linux/Unix right now");
 
+    use std::fs;
     use std::path::PathBuf;
 
     use lazy_static::lazy_static;
@@ -453,22 +343,12 @@ mod tests {
         static ref HOME: PathBuf = std::env::var("HOME").unwrap().into();
         static ref TF2: PathBuf = HOME.join(".local/share/Steam/steamapps/common/Team Fortress 2");
         static ref TF2_TF: PathBuf = TF2.join("tf");
-        static ref VPK_CMD: PathBuf = TF2.join("bin/vpk_linux32");
         static ref TEST_DIR: &'static Path = Path::new("test");
     }
 
-    // lazy_static outputs magic types
-    // fixes using VPK_CMD as argument (normally only impls Deref<PathBuf>)
-    impl AsRef<Path> for VPK_CMD {
-        fn as_ref(&self) -> &Path {
-            self.as_path()
-        }
-    }
-
     fn assert_env() {
         assert!(TF2.is_dir());
         assert!(TF2_TF.is_dir());
-        assert!(VPK_CMD.is_file());
 
         let cwd = std::env::current_dir().unwrap();
         let cwd_test = cwd.join("test");
@@ -495,33 +375,34 @@ mod tests {
         eprintln!("TODO: horrible test as tf2 can just update");
         let tf2_vpk = TF2_TF.join("tf2_sound_misc_dir.vpk");
 
-        let list = list(&VPK_CMD, tf2_vpk).unwrap();
+        let list = list(tf2_vpk).unwrap();
 
-        assert_eq!(3227, list.lines().count());
+        assert_eq!(3227, list.len());
     }
 
     #[test]
     fn list_test() {
-        assert_env();
-        let list = list(&VPK_CMD, TRUTH_VPK).unwrap();
-        let truth = "file1.txt\n\
-            file2.txt\n\
-            folder1/folder1_file1.txt\n\
-            folder1/folder2/folder2_file2.txt\n\
-            hello_world/hello_world.txt\n";
-
-        assert_eq!(truth, list);
+        let mut list = list(TRUTH_VPK).unwrap();
+        list.sort_unstable();
+        let truth = [
+            "file1.txt",
+            "file2.txt",
+            "folder1/folder1_file1.txt",
+            "folder1/folder2/folder2_file2.txt",
+            "hello_world/hello_world.txt",
+        ];
+
+        assert_eq!(truth, &list[..]);
     }
 
     #[test]
     fn extract_test() {
-        assert_env();
         let test_dir = &TEST_DIR.join("test_extract");
         let dest_dir = &test_dir.join("dest");
         _ = fs::remove_dir_all(test_dir);
 
         eprintln!("extracting...\t{} -> {}", filename(TRUTH_VPK), filename(dest_dir));
-        extract_all(&VPK_CMD, TRUTH_VPK, dest_dir, Options::default()).unwrap();
+        extract_all(TRUTH_VPK, dest_dir, Options::default()).unwrap();
 
         eprintln!("checking contents");
 
@@ -540,12 +421,20 @@ mod tests {
             fs::read_to_string(dest_dir.join("hello_world/hello_world.txt")).unwrap()
         );
 
+        let threaded = Options { threads: 0, ..Default::default() };
+        extract_all(TRUTH_VPK, test_dir.join("threaded"), threaded).unwrap();
+        assert_eq!("file1", fs::read_to_string(test_dir.join("threaded/file1.txt")).unwrap());
+
+        let one = extract_one(TRUTH_VPK, test_dir.join("one"), "Hello_World/hello_world.txt");
+        assert_eq!(test_dir.join("one/hello_world/hello_world.txt"), one.unwrap());
+        let missing = extract_one(TRUTH_VPK, test_dir.join("one"), "nope.txt");
+        assert!(matches!(missing, Err(VpkError::NotFound(_))));
+
         _ = fs::remove_dir_all(test_dir);
     }
 
     #[test]
     fn archive_test() {
-        assert_env();
         let test_dir = &TEST_DIR.join("test_archive");
         let dest_dir = &test_dir.join("dest");
         let dest_vpk = &dest_dir.with_extension("vpk");
@@ -569,19 +458,20 @@ mod tests {
         });
 
         eprintln!("extracting...\t{} -> {}", filename(src_vpk), filename(src_dir));
-        extract_all(&VPK_CMD, src_vpk, src_dir, Options::default()).unwrap();
+        extract_all(src_vpk, src_dir, Options::default()).unwrap();
         _ = fs::remove_file(src_vpk);
 
         // archive
 
         eprintln!("archiving...\t{} -> {}", filename(src_dir), filename(dest_vpk));
-        archive(&VPK_CMD, src_dir, Some(dest_vpk)).unwrap();
+        let written = archive(src_dir, Some(dest_vpk), WriteOptions::default()).unwrap();
+        assert_eq!(vec![dest_vpk.clone()], written);
         eprintln!("Setup done");
 
         // extract and test
 
         eprintln!("extracting...\t{} -> {}", filename(dest_vpk), filename(dest_dir));
-        extract_all(&VPK_CMD, dest_vpk, dest_dir, Options::default()).unwrap();
+        extract_all(dest_vpk, dest_dir, Options::default()).unwrap();
         eprintln!("testing {}...", filename(dest_dir));
 
         // tests:
@@ -601,6 +491,17 @@ mod tests {
             fs::read_to_string(dest_dir.join("hello_world/hello_world.txt")).unwrap()
         );
 
+        // next to src_dir without dest_vpk
+        let v1 = WriteOptions { version: 1, ..Default::default() };
+        let written = archive(src_dir, None::<&Path>, v1).unwrap();
+        assert_eq!(vec![src_vpk.canonicalize().unwrap()], written);
+        assert_eq!(5, list(src_vpk).unwrap().len());
+
+        let empty_dir = &test_dir.join("empty");
+        fs::create_dir_all(empty_dir).unwrap();
+        let empty = archive(empty_dir, Some(test_dir.join("empty.vpk")), WriteOptions::default());
+        assert!(matches!(empty, Err(VpkError::Src(_))));
+
         _ = fs::remove_dir_all(test_dir);
     }
 }
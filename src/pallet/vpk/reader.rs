@@ -0,0 +1,573 @@
+//! Native reader for VPK v1 and v2 files, no `vpk_linux32` needed.
+//!
+//! A vpk is either a single `name.vpk` with the files stored after the directory tree, or a
+//! multi-part `name_dir.vpk` with the files stored in `name_000.vpk`, `name_001.vpk`, etc.
+//! The parts of the directory tree are little endian.
+//!
+//! See <https://developer.valvesoftware.com/wiki/VPK_(file_format)>
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rayon::prelude::*;
+
+use super::utils::*;
+use super::{Options, VpkError, VpkResult};
+
+/// First 4 bytes of every vpk directory.
+pub const SIGNATURE: u32 = 0x55aa_1234;
+/// Archive index of entries stored in the directory file itself, after the tree.
+pub const DIR_ARCHIVE_INDEX: u16 = 0x7fff;
+/// Written after every entry in the tree.
+const ENTRY_TERMINATOR: u16 = 0xffff;
+
+/// Header of a vpk directory file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VpkHeader {
+    /// 1 or 2.
+    pub version: u32,
+    /// Size of the directory tree in bytes.
+    pub tree_size: u32,
+    // v2 only, 0 for v1:
+    /// Size of the file data stored in the directory file.
+    pub file_data_size: u32,
+    pub archive_md5_size: u32,
+    pub other_md5_size: u32,
+    pub signature_size: u32,
+}
+
+impl VpkHeader {
+    /// Size of the header in bytes, the tree starts after it.
+    pub const fn size(&self) -> u32 {
+        if self.version == 1 {
+            12
+        } else {
+            28
+        }
+    }
+}
+
+/// A file in a vpk.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VpkEntry {
+    /// Full path with forward slashes, ex: `materials/dev/dev_measuregeneric01b.vmt`
+    pub path: String,
+    /// CRC32 of the whole file, see [`crc32`].
+    pub crc: u32,
+    /// Start of the file, stored in the directory tree.
+    pub preload: Vec<u8>,
+    /// Which `_###.vpk` the rest of the file is in, or [`DIR_ARCHIVE_INDEX`].
+    pub archive_index: u16,
+    /// Offset into the archive, after the tree for [`DIR_ARCHIVE_INDEX`].
+    pub offset: u32,
+    /// Length of the file stored in the archive, not including the preload data.
+    pub length: u32,
+}
+
+impl VpkEntry {
+    /// Total size of the file in bytes.
+    pub fn len(&self) -> usize {
+        self.preload.len() + self.length as usize
+    }
+}
+
+/// An opened vpk. Only the directory tree is kept in memory, file data is read on demand.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VpkArchive {
+    /// The single `.vpk` or `_dir.vpk`.
+    path: PathBuf,
+    header: VpkHeader,
+    /// In the order of the directory tree.
+    entries: Vec<VpkEntry>,
+    /// Lowercase paths to indices into `entries`.
+    index: HashMap<String, usize>,
+}
+
+impl VpkArchive {
+    /// Open a single `.vpk` or the `_dir.vpk` of a multi-part vpk and read its directory tree.
+    ///
+    /// # Errors
+    ///
+    /// * `path` must be a vpk and exist.
+    /// * Returns `Err(VpkError::Format)` if the directory is invalid or an unsupported version.
+    pub fn open(path: impl AsRef<Path>) -> VpkResult<Self> {
+        fn open(path: &Path) -> VpkResult<VpkArchive> {
+            path.vpk_exists().map_err(VpkError::Src)?;
+            let mut file = File::open(path).map_err(VpkError::Src)?;
+
+            let mut header = [0; 28];
+            let read = file.read(&mut header)?;
+            let header = parse_header(&header[..read])?;
+
+            let mut tree = vec![0; header.tree_size as usize];
+            file.seek(SeekFrom::Start(header.size() as u64))?;
+            file.read_exact(&mut tree)
+                .map_err(|_| format_error("directory tree is truncated"))?;
+
+            VpkArchive::from_tree(path, header, &tree)
+        }
+        open(path.as_ref())
+    }
+
+    fn from_tree(path: &Path, header: VpkHeader, tree: &[u8]) -> VpkResult<Self> {
+        let entries = parse_tree(tree)?;
+        let index = entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (entry.path.to_lowercase(), i))
+            .collect();
+        Ok(Self { path: path.to_path_buf(), header, entries, index })
+    }
+
+    /// Paths of all files, in the order of the directory tree.
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|entry| entry.path.as_str())
+    }
+
+    /// The entry at `path`. Case insensitive and `\` is the same as `/`, like the engine.
+    pub fn get(&self, path: &str) -> Option<&VpkEntry> {
+        let path = path.replace('\\', "/").to_lowercase();
+        self.index.get(path.trim_start_matches('/')).map(|&i| &self.entries[i])
+    }
+
+    /// Path of the archive with `index`, `name_dir.vpk` -> `name_###.vpk`.
+    /// The directory file itself for [`DIR_ARCHIVE_INDEX`].
+    pub fn archive_path(&self, index: u16) -> PathBuf {
+        let stem = self.path.file_stem().unwrap_or_default().to_string_lossy();
+        match stem.strip_suffix("_dir") {
+            Some(name) if index != DIR_ARCHIVE_INDEX => {
+                self.path.with_file_name(format!("{name}_{index:03}.vpk"))
+            }
+            _ => self.path.clone(),
+        }
+    }
+
+    /// Read the file at `path`, see [`Self::get`] and [`Self::read_entry`].
+    pub fn read(&self, path: &str) -> VpkResult<Vec<u8>> {
+        let entry = self.get(path).ok_or_else(|| VpkError::NotFound(path.to_string()))?;
+        self.read_entry(entry)
+    }
+
+    /// Read the preload and archive data of `entry` and check its CRC.
+    ///
+    /// # Errors
+    ///
+    /// * The archive `entry` is in must exist.
+    /// * Returns `Err(VpkError::Crc)` if the data doesn't match [`VpkEntry::crc`].
+    pub fn read_entry(&self, entry: &VpkEntry) -> VpkResult<Vec<u8>> {
+        let mut data = Vec::with_capacity(entry.len());
+        data.extend_from_slice(&entry.preload);
+
+        if entry.length > 0 {
+            let mut offset = entry.offset as u64;
+            if entry.archive_index == DIR_ARCHIVE_INDEX {
+                offset += (self.header.size() + self.header.tree_size) as u64;
+            }
+            let mut file =
+                File::open(self.archive_path(entry.archive_index)).map_err(VpkError::Src)?;
+            file.seek(SeekFrom::Start(offset))?;
+            let start = data.len();
+            data.resize(entry.len(), 0);
+            file.read_exact(&mut data[start..])
+                .map_err(|_| format_error(format!("data for `{}` is truncated", entry.path)))?;
+        }
+
+        let crc = crc32(&data);
+        if crc != entry.crc {
+            return Err(VpkError::Crc {
+                path: entry.path.clone(),
+                expected: entry.crc,
+                found: crc,
+            });
+        }
+        Ok(data)
+    }
+
+    /// Check that every path is a plain relative path, so extracting can't write outside
+    /// the destination folder with paths like `../x` or `/etc/x`.
+    ///
+    /// # Errors
+    ///
+    /// * Returns `Err(VpkError::Format)` with the first bad path.
+    pub fn check_paths(&self) -> VpkResult<()> {
+        self.entries.iter().try_for_each(|entry| relative_path(&entry.path).map(|_| ()))
+    }
+
+    /// Extract every file into `dest_dir`, creating any folders.
+    /// Nothing is extracted if any path is unsafe, see [`Self::check_paths`].
+    ///
+    /// # Errors
+    ///
+    /// * Returns `Err(VpkError::Crc)` if any file is corrupt, other files may still be extracted.
+    pub fn extract_all(&self, dest_dir: impl AsRef<Path>, options: &Options) -> VpkResult<()> {
+        fn extract_all(vpk: &VpkArchive, dest_dir: &Path, options: &Options) -> VpkResult<()> {
+            vpk.check_paths()?;
+            fs::create_dir_all(dest_dir)?;
+
+            let total = vpk.entries.len();
+            let done = AtomicUsize::new(0);
+            let extract = |entry: &VpkEntry| -> VpkResult<()> {
+                let dest = vpk.extract_entry(entry, dest_dir)?;
+                let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+                if options.verbose {
+                    eprintln!("extracted `{}`", dest.display());
+                }
+                if options.show_progress {
+                    eprint!("\r{done}/{total}");
+                }
+                Ok(())
+            };
+            let result = if options.threads == 1 {
+                vpk.entries.iter().try_for_each(extract)
+            } else {
+                vpk.entries.par_iter().try_for_each(extract)
+            };
+            if options.show_progress {
+                eprintln!();
+            }
+            result
+        }
+        extract_all(self, dest_dir.as_ref(), options)
+    }
+
+    /// Extract `entry` to its path in `dest_dir`, creating any folders.
+    /// Returns the path of the extracted file.
+    ///
+    /// # Errors
+    ///
+    /// * Returns `Err(VpkError::Format)` if the path of `entry` isn't a plain relative path.
+    /// * See [`Self::read_entry`].
+    pub fn extract_entry(
+        &self, entry: &VpkEntry, dest_dir: impl AsRef<Path>,
+    ) -> VpkResult<PathBuf> {
+        let dest = dest_dir.as_ref().join(relative_path(&entry.path)?);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&dest, self.read_entry(entry)?)?;
+        Ok(dest)
+    }
+}
+
+/// `path` if it only has normal components, ex: not `..`, `/` or `C:`.
+fn relative_path(path: &str) -> VpkResult<&Path> {
+    let relative = Path::new(path);
+    let mut components = relative.components().peekable();
+    let is_normal = components.peek().is_some()
+        && components
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if is_normal {
+        Ok(relative)
+    } else {
+        Err(format_error(format!("unsafe path `{path}`")))
+    }
+}
+
+fn format_error(msg: impl Into<String>) -> VpkError {
+    VpkError::Format(msg.into())
+}
+
+fn parse_header(bytes: &[u8]) -> VpkResult<VpkHeader> {
+    let mut reader = ByteReader::new(bytes);
+    if reader.u32()? != SIGNATURE {
+        return Err(format_error("missing vpk signature"));
+    }
+    let version = reader.u32()?;
+    let tree_size = reader.u32()?;
+    match version {
+        1 => Ok(VpkHeader { version, tree_size, ..Default::default() }),
+        2 => Ok(VpkHeader {
+            version,
+            tree_size,
+            file_data_size: reader.u32()?,
+            archive_md5_size: reader.u32()?,
+            other_md5_size: reader.u32()?,
+            signature_size: reader.u32()?,
+        }),
+        _ => Err(format_error(format!("unsupported vpk version {version}"))),
+    }
+}
+
+/// The tree is grouped by extension, then directory, then file name.
+/// Each list ends with an empty string, a single space means none.
+fn parse_tree(tree: &[u8]) -> VpkResult<Vec<VpkEntry>> {
+    let mut reader = ByteReader::new(tree);
+    let mut entries = Vec::new();
+    loop {
+        let ext = reader.str()?;
+        if ext.is_empty() {
+            break;
+        }
+        loop {
+            let dir = reader.str()?;
+            if dir.is_empty() {
+                break;
+            }
+            loop {
+                let name = reader.str()?;
+                if name.is_empty() {
+                    break;
+                }
+                let mut path = String::new();
+                if dir != " " {
+                    path.push_str(&dir);
+                    path.push('/');
+                }
+                path.push_str(&name);
+                if ext != " " {
+                    path.push('.');
+                    path.push_str(&ext);
+                }
+                entries.push(parse_entry(&mut reader, path)?);
+            }
+        }
+    }
+    Ok(entries)
+}
+
+fn parse_entry(reader: &mut ByteReader, path: String) -> VpkResult<VpkEntry> {
+    let crc = reader.u32()?;
+    let preload_size = reader.u16()?;
+    let archive_index = reader.u16()?;
+    let offset = reader.u32()?;
+    let length = reader.u32()?;
+    if reader.u16()? != ENTRY_TERMINATOR {
+        return Err(format_error(format!("bad terminator for `{path}`")));
+    }
+    let preload = reader.bytes(preload_size as usize)?.to_vec();
+    Ok(VpkEntry { path, crc, preload, archive_index, offset, length })
+}
+
+/// Little endian reader over a slice, errors instead of reading past the end.
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    const fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn bytes(&mut self, len: usize) -> VpkResult<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(format_error("unexpected end of directory"));
+        }
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> VpkResult<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> VpkResult<u32> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Null terminated string, converting invalid UTF-8 to `U+FFFD`.
+    fn str(&mut self) -> VpkResult<String> {
+        let len = self
+            .bytes
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| format_error("unterminated string"))?;
+        let bytes = self.bytes(len + 1)?;
+        Ok(string_from_utf8_lossy(bytes[..len].to_vec()))
+    }
+}
+
+/// Lookup table for the reversed polynomial `0xedb88320`.
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { 0xedb8_8320 ^ (crc >> 1) } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC32 (IEEE, same as zlib) checksum vpks use for file data.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    !bytes
+        .iter()
+        .fold(!0, |crc, &b| CRC_TABLE[((crc ^ b as u32) & 0xff) as usize] ^ (crc >> 8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRUTH_DIR: &str = "test/truth";
+    const TRUTH_VPK: &str = "test/truth.vpk";
+
+    #[test]
+    fn crc() {
+        assert_eq!(0, crc32(b""));
+        assert_eq!(0xcbf4_3926, crc32(b"123456789"));
+    }
+
+    #[test]
+    fn truth() {
+        let vpk = VpkArchive::open(TRUTH_VPK).unwrap();
+        assert_eq!(2, vpk.header.version);
+
+        let mut paths: Vec<_> = vpk.paths().collect();
+        paths.sort_unstable();
+        assert_eq!(
+            vec![
+                "file1.txt",
+                "file2.txt",
+                "folder1/folder1_file1.txt",
+                "folder1/folder2/folder2_file2.txt",
+                "hello_world/hello_world.txt",
+            ],
+            paths
+        );
+
+        for path in paths {
+            let truth = fs::read(Path::new(TRUTH_DIR).join(path)).unwrap();
+            assert_eq!(truth, vpk.read(path).unwrap(), "{path}");
+        }
+        assert_eq!(b"Hello World!".to_vec(), vpk.read("Hello_World\\hello_world.txt").unwrap());
+        assert!(matches!(vpk.read("nope.txt"), Err(VpkError::NotFound(_))));
+        assert_eq!(Path::new(TRUTH_VPK), vpk.archive_path(0));
+
+        let dest_dir = Path::new("test/test_reader_extract");
+        _ = fs::remove_dir_all(dest_dir);
+        vpk.extract_all(dest_dir, &Options::default()).unwrap();
+        assert_eq!(
+            "folder2_file2",
+            fs::read_to_string(dest_dir.join("folder1/folder2/folder2_file2.txt")).unwrap()
+        );
+        _ = fs::remove_dir_all(dest_dir);
+    }
+
+    /// A v1 `_dir.vpk` with one extension and directory per entry, a space for none.
+    fn v1_dir(entries: &[VpkEntry]) -> Vec<u8> {
+        let cstr = |tree: &mut Vec<u8>, s: &str| {
+            tree.extend_from_slice(s.as_bytes());
+            tree.push(0);
+        };
+        let mut tree = Vec::new();
+        for entry in entries {
+            let (dir, file) = entry.path.rsplit_once('/').unwrap_or((" ", &entry.path));
+            let (name, ext) = file.rsplit_once('.').unwrap_or((file, " "));
+            for s in [ext, dir, name] {
+                cstr(&mut tree, s);
+            }
+            tree.extend_from_slice(&entry.crc.to_le_bytes());
+            tree.extend_from_slice(&(entry.preload.len() as u16).to_le_bytes());
+            tree.extend_from_slice(&entry.archive_index.to_le_bytes());
+            tree.extend_from_slice(&entry.offset.to_le_bytes());
+            tree.extend_from_slice(&entry.length.to_le_bytes());
+            tree.extend_from_slice(&ENTRY_TERMINATOR.to_le_bytes());
+            tree.extend_from_slice(&entry.preload);
+            // end of names and directories
+            tree.extend_from_slice(&[0, 0]);
+        }
+        tree.push(0);
+
+        let mut dir = Vec::new();
+        for n in [SIGNATURE, 1, tree.len() as u32] {
+            dir.extend_from_slice(&n.to_le_bytes());
+        }
+        dir.extend(tree);
+        dir
+    }
+
+    #[test]
+    fn multi_part() {
+        let test_dir = Path::new("test/test_reader_multi");
+        _ = fs::remove_dir_all(test_dir);
+        fs::create_dir_all(test_dir).unwrap();
+
+        let (a, b) = (b"\"LightmappedGeneric\" {}".as_slice(), b"no extension".as_slice());
+        let entry = |path: &str, crc, archive_index, offset, length| VpkEntry {
+            path: path.to_string(),
+            crc,
+            archive_index,
+            offset,
+            length,
+            ..Default::default()
+        };
+        let dir = v1_dir(&[
+            VpkEntry {
+                preload: a[..4].to_vec(),
+                ..entry("materials/dev/a.vmt", crc32(a), 1, 3, a.len() as u32 - 4)
+            },
+            entry("b", crc32(b), 0, 0, b.len() as u32),
+            entry("bad/crc.txt", 1234, 0, 0, b.len() as u32),
+        ]);
+        fs::write(test_dir.join("pak01_dir.vpk"), dir).unwrap();
+        fs::write(test_dir.join("pak01_000.vpk"), b).unwrap();
+        fs::write(test_dir.join("pak01_001.vpk"), [b"xyz", &a[4..]].concat()).unwrap();
+
+        let vpk = VpkArchive::open(test_dir.join("pak01_dir.vpk")).unwrap();
+        assert_eq!(1, vpk.header.version);
+        assert_eq!(
+            vec!["materials/dev/a.vmt", "b", "bad/crc.txt"],
+            vpk.paths().collect::<Vec<_>>()
+        );
+        assert_eq!(test_dir.join("pak01_001.vpk"), vpk.archive_path(1));
+        assert_eq!(a.len(), vpk.get("materials/dev/a.vmt").unwrap().len());
+        assert_eq!(a.to_vec(), vpk.read("materials/dev/a.vmt").unwrap());
+        assert_eq!(b.to_vec(), vpk.read("b").unwrap());
+        assert!(matches!(vpk.read("bad/crc.txt"), Err(VpkError::Crc { expected: 1234, .. })));
+
+        // not a vpk directory
+        let part = VpkArchive::open(test_dir.join("pak01_000.vpk"));
+        assert!(matches!(part, Err(VpkError::Format(_))));
+
+        _ = fs::remove_dir_all(test_dir);
+    }
+
+    #[test]
+    fn unsafe_paths() {
+        let test_dir = Path::new("test/test_reader_unsafe");
+        let dest_dir = test_dir.join("dest");
+        _ = fs::remove_dir_all(test_dir);
+        fs::create_dir_all(test_dir).unwrap();
+
+        let entry = |path: &str| VpkEntry {
+            path: path.to_string(),
+            crc: crc32(b"x"),
+            preload: b"x".to_vec(),
+            archive_index: DIR_ARCHIVE_INDEX,
+            ..Default::default()
+        };
+        for path in ["../escape.txt", "/tmp/escape.txt", "a/../../escape.txt"] {
+            let dir = v1_dir(&[entry("good.txt"), entry(path)]);
+            fs::write(test_dir.join("evil.vpk"), dir).unwrap();
+
+            let vpk = VpkArchive::open(test_dir.join("evil.vpk")).unwrap();
+            assert_eq!(vec!["good.txt", path], vpk.paths().collect::<Vec<_>>());
+            assert!(matches!(vpk.check_paths(), Err(VpkError::Format(_))), "{path}");
+            assert!(
+                matches!(vpk.extract_all(&dest_dir, &Options::default()), Err(VpkError::Format(_))),
+                "{path}"
+            );
+            assert!(!dest_dir.join("good.txt").exists(), "extracted before checking paths");
+            assert!(!test_dir.join("escape.txt").exists());
+        }
+
+        let vpk = VpkArchive::open(test_dir.join("evil.vpk")).unwrap();
+        let good = vpk.get("good.txt").unwrap();
+        assert_eq!(dest_dir.join("good.txt"), vpk.extract_entry(good, &dest_dir).unwrap());
+        assert_eq!("x", fs::read_to_string(dest_dir.join("good.txt")).unwrap());
+
+        _ = fs::remove_dir_all(test_dir);
+    }
+}
@@ -0,0 +1,466 @@
+//! Native writer for VPK v1 and v2 files, no `vpk_linux32` needed.
+//!
+//! Writes a single `name.vpk` with the file data after the directory tree, or a multi-chunk
+//! `name_dir.vpk` with the data split into `name_000.vpk`, `name_001.vpk`, etc.
+//! See [`super::reader`] for the format.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use walkdir::WalkDir;
+
+use super::reader::{crc32, VpkEntry, DIR_ARCHIVE_INDEX, SIGNATURE};
+use super::utils::*;
+use super::{VpkError, VpkResult};
+
+/// Size of the blocks of chunk data checksummed in a v2 directory.
+const ARCHIVE_MD5_BLOCK: usize = 1024 * 1024;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// 1 or 2. 2 adds MD5 checksums, used by newer games.
+    pub version: u32,
+    /// Max size of each `_###.vpk` chunk in bytes, files bigger than this get their own chunk.
+    /// `None` for a single vpk.
+    pub chunk_size: Option<u32>,
+    /// Max bytes of each file stored in the directory tree, read without opening a chunk.
+    pub preload_size: u16,
+}
+
+impl WriteOptions {
+    /// Chunk size Valve's tools use, 200 MiB.
+    pub const VALVE_CHUNK_SIZE: u32 = 200 * 1024 * 1024;
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self { version: 2, chunk_size: None, preload_size: 0 }
+    }
+}
+
+/// Collects files and writes them as a vpk.
+///
+/// Paths are stored lowercase with forward slashes, like Valve's tools.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VpkWriter {
+    pub options: WriteOptions,
+    /// Sorted by path.
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+impl VpkWriter {
+    pub const fn new(options: WriteOptions) -> Self {
+        Self { options, files: BTreeMap::new() }
+    }
+
+    /// Add or replace the file at `path`.
+    pub fn add(&mut self, path: impl AsRef<str>, data: impl Into<Vec<u8>>) {
+        let path = path.as_ref().replace('\\', "/").trim_start_matches('/').to_lowercase();
+        self.files.insert(path, data.into());
+    }
+
+    /// Add every file in `dir` and its subdirectories, relative to `dir`.
+    ///
+    /// # Errors
+    ///
+    /// * `dir` must be a directory and exist.
+    /// * Can error reading files.
+    pub fn add_dir(&mut self, dir: impl AsRef<Path>) -> VpkResult<()> {
+        fn add_dir(writer: &mut VpkWriter, dir: &Path) -> VpkResult<()> {
+            dir.dir_exists().map_err(VpkError::Src)?;
+            for entry in WalkDir::new(dir).follow_links(true) {
+                let entry = entry.map_err(|err| VpkError::Src(err.into()))?;
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+                let path = entry.path();
+                let relative = path.strip_prefix(dir).unwrap_or(path);
+                let data = fs::read(path).map_err(VpkError::Src)?;
+                writer.add(relative.to_string_lossy(), data);
+            }
+            Ok(())
+        }
+        add_dir(self, dir.as_ref())
+    }
+
+    /// Paths of all files, sorted.
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.files.keys().map(String::as_str)
+    }
+
+    /// Write the vpk to `dest_vpk`, creating any folders. With a chunk size, `_dir` is added to
+    /// the file name if missing and the chunks are written next to it.
+    /// Returns the paths of the directory and any chunks.
+    ///
+    /// # Errors
+    ///
+    /// * `dest_vpk` must have a `.vpk` extension.
+    /// * Returns `Err(VpkError::Format)` for an unsupported version, a path without a file
+    ///   name, or too much data.
+    /// * Can error when creating folders or writing files.
+    pub fn write(&self, dest_vpk: impl AsRef<Path>) -> VpkResult<Vec<PathBuf>> {
+        fn write(writer: &VpkWriter, dest_vpk: &Path) -> VpkResult<Vec<PathBuf>> {
+            dest_vpk.has_ext_res("vpk").map_err(VpkError::Dest)?;
+            let options = &writer.options;
+            if !(1..=2).contains(&options.version) {
+                return Err(format_error(format!("unsupported vpk version {}", options.version)));
+            }
+
+            let Layout { entries, embedded, chunks } = writer.layout()?;
+            let tree = build_tree(&entries)?;
+            let dir = build_dir(options.version, &tree, &embedded, &chunks)?;
+
+            let dir_path = match options.chunk_size {
+                Some(_) => dir_vpk_path(dest_vpk),
+                None => dest_vpk.to_path_buf(),
+            };
+            if let Some(parent) = dir_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&dir_path, dir)?;
+
+            let mut paths = vec![dir_path];
+            for (i, chunk) in chunks.iter().enumerate() {
+                let chunk_path = chunk_vpk_path(&paths[0], i);
+                fs::write(&chunk_path, chunk)?;
+                paths.push(chunk_path);
+            }
+            Ok(paths)
+        }
+        write(self, dest_vpk.as_ref())
+    }
+
+    /// Split files into preload data, data after the tree and chunks.
+    fn layout(&self) -> VpkResult<Layout> {
+        let too_big = || format_error("too much data for a vpk");
+        let mut entries = Vec::with_capacity(self.files.len());
+        let mut embedded = Vec::new();
+        let mut chunks: Vec<Vec<u8>> = Vec::new();
+
+        for (path, data) in &self.files {
+            let preload_len = data.len().min(self.options.preload_size as usize);
+            let (preload, rest) = data.split_at(preload_len);
+
+            let (archive_index, offset) = match self.options.chunk_size {
+                None => {
+                    embedded.extend_from_slice(rest);
+                    (DIR_ARCHIVE_INDEX, embedded.len() - rest.len())
+                }
+                Some(chunk_size) => {
+                    let full = chunks.last().is_none_or(|chunk| {
+                        !chunk.is_empty() && chunk.len() + rest.len() > chunk_size as usize
+                    });
+                    if full {
+                        chunks.push(Vec::new());
+                    }
+                    let index = u16::try_from(chunks.len() - 1)
+                        .ok()
+                        .filter(|&index| index < DIR_ARCHIVE_INDEX)
+                        .ok_or_else(too_big)?;
+                    let chunk = chunks.last_mut().unwrap();
+                    chunk.extend_from_slice(rest);
+                    (index, chunk.len() - rest.len())
+                }
+            };
+
+            entries.push(VpkEntry {
+                path: path.clone(),
+                crc: crc32(data),
+                preload: preload.to_vec(),
+                archive_index,
+                offset: u32::try_from(offset).map_err(|_| too_big())?,
+                length: u32::try_from(rest.len()).map_err(|_| too_big())?,
+            });
+        }
+        Ok(Layout { entries, embedded, chunks })
+    }
+}
+
+/// Where the data of each file goes.
+struct Layout {
+    entries: Vec<VpkEntry>,
+    /// Data after the tree, for a single vpk.
+    embedded: Vec<u8>,
+    /// Data of each `_###.vpk`.
+    chunks: Vec<Vec<u8>>,
+}
+
+fn format_error(msg: impl Into<String>) -> VpkError {
+    VpkError::Format(msg.into())
+}
+
+/// `name.vpk` -> `name_dir.vpk`, unchanged if it already ends in `_dir`.
+fn dir_vpk_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    if stem.ends_with("_dir") {
+        path.to_path_buf()
+    } else {
+        path.with_file_name(format!("{stem}_dir.vpk"))
+    }
+}
+
+/// `name_dir.vpk` -> `name_###.vpk`
+fn chunk_vpk_path(dir_path: &Path, index: usize) -> PathBuf {
+    let stem = dir_path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = stem.strip_suffix("_dir").unwrap_or(&stem);
+    dir_path.with_file_name(format!("{name}_{index:03}.vpk"))
+}
+
+fn push_str(bytes: &mut Vec<u8>, s: &str) {
+    bytes.extend_from_slice(s.as_bytes());
+    bytes.push(0);
+}
+
+/// Group entries by extension, then directory, see [`super::reader`].
+fn build_tree(entries: &[VpkEntry]) -> VpkResult<Vec<u8>> {
+    type Dirs<'a> = BTreeMap<&'a str, Vec<(&'a str, &'a VpkEntry)>>;
+    let mut exts: BTreeMap<&str, Dirs> = BTreeMap::new();
+    for entry in entries {
+        let (dir, file) = entry.path.rsplit_once('/').unwrap_or((" ", &entry.path));
+        let (name, ext) = file.rsplit_once('.').unwrap_or((file, " "));
+        // empty strings end lists in the tree
+        if [dir, name, ext].contains(&"") {
+            return Err(format_error(format!("can't store `{}` in a vpk", entry.path)));
+        }
+        exts.entry(ext).or_default().entry(dir).or_default().push((name, entry));
+    }
+
+    let mut tree = Vec::new();
+    for (ext, dirs) in exts {
+        push_str(&mut tree, ext);
+        for (dir, files) in dirs {
+            push_str(&mut tree, dir);
+            for (name, entry) in files {
+                push_str(&mut tree, name);
+                tree.extend_from_slice(&entry.crc.to_le_bytes());
+                tree.extend_from_slice(&(entry.preload.len() as u16).to_le_bytes());
+                tree.extend_from_slice(&entry.archive_index.to_le_bytes());
+                tree.extend_from_slice(&entry.offset.to_le_bytes());
+                tree.extend_from_slice(&entry.length.to_le_bytes());
+                tree.extend_from_slice(&0xffffu16.to_le_bytes());
+                tree.extend_from_slice(&entry.preload);
+            }
+            tree.push(0);
+        }
+        tree.push(0);
+    }
+    tree.push(0);
+    Ok(tree)
+}
+
+/// The directory file. v2 ends with checksums of the chunks, the tree and the whole file.
+fn build_dir(version: u32, tree: &[u8], embedded: &[u8], chunks: &[Vec<u8>]) -> VpkResult<Vec<u8>> {
+    let len = |bytes: usize| u32::try_from(bytes).map_err(|_| format_error("vpk tree too big"));
+
+    let mut archive_md5 = Vec::new();
+    if version == 2 {
+        for (index, chunk) in chunks.iter().enumerate() {
+            for (block, data) in chunk.chunks(ARCHIVE_MD5_BLOCK).enumerate() {
+                for n in [index, block * ARCHIVE_MD5_BLOCK, data.len()] {
+                    archive_md5.extend_from_slice(&len(n)?.to_le_bytes());
+                }
+                archive_md5.extend_from_slice(&md5(data));
+            }
+        }
+    }
+
+    let mut dir = Vec::new();
+    dir.extend_from_slice(&SIGNATURE.to_le_bytes());
+    dir.extend_from_slice(&version.to_le_bytes());
+    dir.extend_from_slice(&len(tree.len())?.to_le_bytes());
+    if version == 2 {
+        // file data, archive md5, other md5, signature
+        for size in [len(embedded.len())?, len(archive_md5.len())?, 48, 0] {
+            dir.extend_from_slice(&size.to_le_bytes());
+        }
+    }
+    dir.extend_from_slice(tree);
+    dir.extend_from_slice(embedded);
+    if version == 2 {
+        dir.extend_from_slice(&archive_md5);
+        dir.extend_from_slice(&md5(tree));
+        dir.extend_from_slice(&md5(&archive_md5));
+        let whole_file = md5(&dir);
+        dir.extend_from_slice(&whole_file);
+    }
+    Ok(dir)
+}
+
+/// MD5 digest, as used for checksums in v2 vpks.
+pub fn md5(bytes: &[u8]) -> [u8; 16] {
+    const SHIFTS: [u32; 16] = [7, 12, 17, 22, 5, 9, 14, 20, 4, 11, 16, 23, 6, 10, 15, 21];
+    // floor(abs(sin(i + 1)) * 2^32)
+    let k: [u32; 64] =
+        std::array::from_fn(|i| ((i as f64 + 1.0).sin().abs() * 4_294_967_296.0) as u32);
+
+    let mut message = bytes.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(bytes.len() as u64).wrapping_mul(8).to_le_bytes());
+
+    let mut state: [u32; 4] = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476];
+    for block in message.chunks_exact(64) {
+        let words: [u32; 16] = std::array::from_fn(|i| {
+            u32::from_le_bytes([block[i * 4], block[i * 4 + 1], block[i * 4 + 2], block[i * 4 + 3]])
+        });
+        let [mut a, mut b, mut c, mut d] = state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let f = f.wrapping_add(a).wrapping_add(k[i]).wrapping_add(words[g]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(f.rotate_left(SHIFTS[i / 16 * 4 + i % 4]));
+        }
+        for (state, n) in state.iter_mut().zip([a, b, c, d]) {
+            *state = state.wrapping_add(n);
+        }
+    }
+
+    let mut digest = [0; 16];
+    for (bytes, n) in digest.chunks_exact_mut(4).zip(state) {
+        bytes.copy_from_slice(&n.to_le_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::reader::VpkArchive;
+    use super::*;
+
+    const TRUTH_DIR: &str = "test/truth";
+    const TRUTH_VPK: &str = "test/truth.vpk";
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    fn u32_at(bytes: &[u8], i: usize) -> usize {
+        u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]) as usize
+    }
+
+    /// Checks the tree, archive md5 section and whole file checksums of a v2 directory.
+    fn assert_md5(dir: &[u8]) {
+        assert_eq!(2, u32_at(dir, 4));
+        let [tree, data, archive] = [8, 12, 16].map(|i| u32_at(dir, i));
+        let archive_start = 28 + tree + data;
+        let other = archive_start + archive;
+        assert_eq!(other + 48, dir.len());
+        assert_eq!(md5(&dir[28..28 + tree]), dir[other..other + 16]);
+        assert_eq!(md5(&dir[archive_start..other]), dir[other + 16..other + 32]);
+        assert_eq!(md5(&dir[..other + 32]), dir[other + 32..]);
+    }
+
+    #[test]
+    fn md5_digest() {
+        assert_eq!("d41d8cd98f00b204e9800998ecf8427e", hex(&md5(b"")));
+        assert_eq!("900150983cd24fb0d6963f7d28e17f72", hex(&md5(b"abc")));
+        assert_eq!(
+            "57edf4a22be3c955ac49da2e2107b67a",
+            hex(&md5(
+                b"12345678901234567890123456789012345678901234567890123456789012345678901234567890"
+            ))
+        );
+        // made by vpk_linux32
+        assert_md5(&fs::read(TRUTH_VPK).unwrap());
+    }
+
+    #[test]
+    fn truth_round_trip() {
+        let test_dir = Path::new("test/test_writer_truth");
+        _ = fs::remove_dir_all(test_dir);
+
+        let mut writer = VpkWriter::default();
+        writer.add_dir(TRUTH_DIR).unwrap();
+        let paths = writer.write(test_dir.join("truth.vpk")).unwrap();
+        assert_eq!(vec![test_dir.join("truth.vpk")], paths);
+
+        // same layout as vpk_linux32, in a different order
+        let written = fs::read(&paths[0]).unwrap();
+        let truth = fs::read(TRUTH_VPK).unwrap();
+        assert_eq!(truth.len(), written.len());
+        assert_eq!(truth[..28], written[..28]);
+        assert_md5(&written);
+
+        let vpk = VpkArchive::open(&paths[0]).unwrap();
+        let truth_vpk = VpkArchive::open(TRUTH_VPK).unwrap();
+        assert_eq!(writer.paths().collect::<Vec<_>>(), {
+            let mut paths: Vec<_> = truth_vpk.paths().collect();
+            paths.sort_unstable();
+            paths
+        });
+        for path in truth_vpk.paths() {
+            assert_eq!(truth_vpk.read(path).unwrap(), vpk.read(path).unwrap(), "{path}");
+        }
+
+        _ = fs::remove_dir_all(test_dir);
+    }
+
+    #[test]
+    fn chunks() {
+        let test_dir = Path::new("test/test_writer_chunks");
+        _ = fs::remove_dir_all(test_dir);
+
+        let big = vec![7; 300];
+        let files = [
+            ("Scripts\\vscripts\\map.nut", b"printl(\"hi\")".to_vec()),
+            ("materials/generated/a.vmt", b"\"LightmappedGeneric\" {}".to_vec()),
+            ("materials/generated/big.vtf", big.clone()),
+            ("readme", b"no extension".to_vec()),
+            ("tiny.txt", b"hi".to_vec()),
+        ];
+        for version in [1, 2] {
+            let options = WriteOptions { version, chunk_size: Some(100), preload_size: 4 };
+            let mut writer = VpkWriter::new(options);
+            for (path, data) in &files {
+                writer.add(path, data.clone());
+            }
+            let dest = test_dir.join(format!("v{version}.vpk"));
+            let paths = writer.write(dest).unwrap();
+            assert_eq!(test_dir.join(format!("v{version}_dir.vpk")), paths[0]);
+            // small files share a chunk, the big one gets its own
+            assert_eq!(4, paths.len(), "{paths:?}");
+            assert_eq!(test_dir.join(format!("v{version}_002.vpk")), paths[3]);
+            assert_eq!(296, fs::metadata(&paths[2]).unwrap().len());
+            if version == 2 {
+                let dir = fs::read(&paths[0]).unwrap();
+                assert_md5(&dir);
+                // one block per chunk
+                assert_eq!(3 * 28, u32_at(&dir, 16));
+            }
+
+            let vpk = VpkArchive::open(&paths[0]).unwrap();
+            assert_eq!(version as usize, u32_at(&fs::read(&paths[0]).unwrap(), 4));
+            assert_eq!(files.len(), vpk.paths().count());
+            for (path, data) in &files {
+                assert_eq!(*data, vpk.read(path).unwrap(), "{path}");
+            }
+            let tiny = vpk.get("tiny.txt").unwrap();
+            assert_eq!((b"hi".to_vec(), 0), (tiny.preload.clone(), tiny.length));
+            assert!(vpk.get("scripts/vscripts/map.nut").is_some());
+        }
+
+        let bad = |path: &str| {
+            let mut writer = VpkWriter::default();
+            writer.add(path, "");
+            writer.write(test_dir.join("bad.vpk"))
+        };
+        assert!(matches!(bad(".gitignore"), Err(VpkError::Format(_))));
+        assert!(matches!(bad("dir/"), Err(VpkError::Format(_))));
+        assert!(matches!(
+            VpkWriter::default().write(test_dir.join("bad.zip")),
+            Err(VpkError::Dest(_))
+        ));
+
+        _ = fs::remove_dir_all(test_dir);
+    }
+}
@@ -0,0 +1,1077 @@
+//! Typed structs for the entities most maps need. Spawns, lights, props, logic, sounds, etc.
+//!
+//! See also: <https://developer.valvesoftware.com/wiki/List_of_entities>
+
+use rgb::RGB8;
+use vmf_parser_nom::ast::Property;
+
+use crate::{
+    map::{BrushEntity, Entity},
+    prelude::{Solid, Vector3},
+    source::entity::{lower_as, props_push_string, props_push_value, rgb_string},
+    source::{ColorBrightness, PointEntity},
+    vmf::ToLower,
+};
+
+/// How a prop collides.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PropSolid {
+    /// No collisions.
+    NotSolid = 0,
+    /// Use the bounding box of the model.
+    BoundingBox = 2,
+    /// Use the collision model of the model.
+    #[default]
+    VPhysics = 6,
+}
+
+/// See also: <https://developer.valvesoftware.com/wiki/Info_player_start>
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InfoPlayerStart<S> {
+    /// Common Fields. Including classname, origin, angles, flags, etc.
+    pub point_entity: PointEntity<S>,
+    /// Flag. Master, the spawn point used if there are multiple.
+    pub is_master: bool,
+}
+
+impl<'a, S> ToLower<Entity<S>> for InfoPlayerStart<S>
+where
+    S: AsRef<str> + Clone + From<&'a str> + From<String>,
+{
+    fn into_lower(mut self) -> Entity<S> {
+        self.point_entity.flags |= self.is_master as i32;
+        lower_as(self.point_entity, "info_player_start")
+    }
+}
+
+/// A team's spawn point, TF2.
+/// See also: <https://developer.valvesoftware.com/wiki/Info_player_teamspawn>
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InfoPlayerTeamspawn<S> {
+    /// Common Fields. Including classname, origin, angles, flags, etc.
+    pub point_entity: PointEntity<S>,
+    /// Team `(TeamNum)` 0 for any, 2 for RED and 3 for BLU.
+    pub team: i32,
+    /// Start Disabled `(StartDisabled)`
+    pub start_disabled: bool,
+    /// Associated Control Point `(controlpoint)` The team_control_point associated with
+    /// this spawn. Ownership of control points will control this spawn point's enabled state.
+    pub control_point: S,
+    /// Blue Spawn for Round `(round_bluespawn)` Blue spawn point when the associated round is being played.
+    pub round_blue_spawn: S,
+    /// Red Spawn for Round `(round_redspawn)` Red spawn point when the associated round is being played.
+    pub round_red_spawn: S,
+}
+
+impl<'a, S> ToLower<Entity<S>> for InfoPlayerTeamspawn<S>
+where
+    S: AsRef<str> + Clone + From<&'a str> + From<String>,
+{
+    fn into_lower(self) -> Entity<S> {
+        let mut entity = lower_as(self.point_entity, "info_player_teamspawn");
+        let props = &mut entity.props;
+
+        props.push(Property::new("TeamNum", self.team.to_string()));
+        props_push_value(props, "StartDisabled", self.start_disabled as i32);
+        props_push_string(props, "controlpoint", self.control_point);
+        props_push_string(props, "round_bluespawn", self.round_blue_spawn);
+        props_push_string(props, "round_redspawn", self.round_red_spawn);
+
+        entity
+    }
+}
+
+/// An invisible omnidirectional light source.
+/// See also: <https://developer.valvesoftware.com/wiki/Light>
+#[derive(Clone, Debug, PartialEq)]
+pub struct Light<S> {
+    /// Common Fields. Including classname, origin, angles, flags, etc.
+    pub point_entity: PointEntity<S>,
+    /// Flag. Initially dark, needs to be turned on.
+    pub initially_dark: bool,
+
+    /// Brightness `(_light)` Color and brightness of the light.
+    pub color: ColorBrightness,
+    /// BrightnessHDR `(_lightHDR)` Override for Brightness when compiling HDR lighting.
+    /// Defaults to -1 -1 -1 1, which means "same as LDR".
+    pub color_hdr: Option<ColorBrightness>,
+    /// BrightnessScaleHDR `(_lightscaleHDR)` Amount to scale the light by when compiling for HDR.
+    pub hdr_scale: f64,
+    /// Appearance `(style)` Preset patterns of flickering. 0 is normal.
+    pub style: i32,
+    /// Custom Appearance `(pattern)` A string of letters from `a` (dark) to `z` (bright),
+    /// 10 per second. Ex: `mmnmmommommnonmmonqnmmo` for a flicker.
+    pub pattern: S,
+    /// Constant `(_constant_attn)` Constant part of the falloff.
+    pub constant_attn: f64,
+    /// Linear `(_linear_attn)` Linear part of the falloff.
+    pub linear_attn: f64,
+    /// Quadratic `(_quadratic_attn)` Quadratic part of the falloff. Physically correct.
+    pub quadratic_attn: f64,
+    /// 50 percent falloff distance `(_fifty_percent_distance)` Distance at which brightness
+    /// should have fallen to 50%. Overrides the attenuations if not 0.
+    pub fifty_percent_dist: f64,
+    /// 0 percent falloff distance `(_zero_percent_distance)` Distance at which brightness
+    /// should have fallen to (roughly) 0%. Used with `fifty_percent_dist`.
+    pub zero_percent_dist: f64,
+    /// Maximum Distance `(_distance)` Distance the light is cut off at. 0 for none.
+    pub max_dist: f64,
+}
+
+impl<S: Default> Default for Light<S> {
+    fn default() -> Self {
+        Self {
+            point_entity: PointEntity::default(),
+            initially_dark: false,
+            color: ColorBrightness::new(255, 255, 255, 200),
+            color_hdr: None,
+            hdr_scale: 1.0,
+            style: 0,
+            pattern: S::default(),
+            constant_attn: 0.0,
+            linear_attn: 0.0,
+            quadratic_attn: 1.0,
+            fifty_percent_dist: 0.0,
+            zero_percent_dist: 0.0,
+            max_dist: 0.0,
+        }
+    }
+}
+
+impl<'a, S> Light<S>
+where
+    S: AsRef<str> + Clone + From<&'a str> + From<String>,
+{
+    fn lower_as(mut self, classname: &'a str) -> Entity<S> {
+        // -1 -1 -1 1 is same as LDR
+        let color_hdr = self.color_hdr.map_or("-1 -1 -1 1".to_string(), |color| color.to_string());
+        self.point_entity.flags |= self.initially_dark as i32;
+        let mut entity = lower_as(self.point_entity, classname);
+        let props = &mut entity.props;
+        props.reserve_exact(11);
+
+        props.push(Property::new("_light", self.color.to_string()));
+        props.push(Property::new("_lightHDR", color_hdr));
+        props.push(Property::new("_lightscaleHDR", self.hdr_scale.to_string()));
+        props_push_value(props, "style", self.style);
+        props_push_string(props, "pattern", self.pattern);
+        props.push(Property::new("_constant_attn", self.constant_attn.to_string()));
+        props.push(Property::new("_linear_attn", self.linear_attn.to_string()));
+        props.push(Property::new("_quadratic_attn", self.quadratic_attn.to_string()));
+        props_push_value(props, "_fifty_percent_distance", self.fifty_percent_dist);
+        props_push_value(props, "_zero_percent_distance", self.zero_percent_dist);
+        props_push_value(props, "_distance", self.max_dist);
+
+        entity
+    }
+}
+
+impl<'a, S> ToLower<Entity<S>> for Light<S>
+where
+    S: AsRef<str> + Clone + From<&'a str> + From<String>,
+{
+    fn into_lower(self) -> Entity<S> {
+        self.lower_as("light")
+    }
+}
+
+/// A cone shaped light source.
+/// See also: <https://developer.valvesoftware.com/wiki/Light_spot>
+#[derive(Clone, Debug, PartialEq)]
+pub struct LightSpot<S> {
+    /// Fields shared with [`Light`].
+    pub light: Light<S>,
+    /// Pitch `(pitch)` Overrides the pitch value in Angles. Like `light_environment`,
+    /// 90 is straight up and -90 is straight down.
+    pub pitch: Option<f64>,
+    /// Inner (bright) angle `(_inner_cone)` Degrees from the center of full brightness.
+    pub inner_angle: f64,
+    /// Outer (fading) angle `(_cone)` Degrees from the center the light fades out to.
+    pub outer_angle: f64,
+    /// Focus `(_exponent)` Changes the distance between the inner and outer cones.
+    pub focus: f64,
+}
+
+impl<S: Default> Default for LightSpot<S> {
+    fn default() -> Self {
+        Self {
+            light: Light::default(),
+            pitch: None,
+            inner_angle: 30.0,
+            outer_angle: 45.0,
+            focus: 1.0,
+        }
+    }
+}
+
+impl<'a, S> ToLower<Entity<S>> for LightSpot<S>
+where
+    S: AsRef<str> + Clone + From<&'a str> + From<String>,
+{
+    fn into_lower(self) -> Entity<S> {
+        let pitch = self.pitch.unwrap_or(-self.light.point_entity.angles.pitch);
+        let mut entity = self.light.lower_as("light_spot");
+        let props = &mut entity.props;
+        props.reserve_exact(4);
+
+        props.push(Property::new("pitch", pitch.to_string()));
+        props.push(Property::new("_inner_cone", self.inner_angle.to_string()));
+        props.push(Property::new("_cone", self.outer_angle.to_string()));
+        props.push(Property::new("_exponent", self.focus.to_string()));
+
+        entity
+    }
+}
+
+/// A directional light like the sun, without ambient light. Multiple are allowed, unlike
+/// `light_environment`. Handled by vrad, but missing from most FGDs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LightDirectional<S> {
+    /// Common Fields. Including classname, origin, angles, flags, etc.
+    pub point_entity: PointEntity<S>,
+    /// Pitch `(pitch)` Overrides the pitch value in Angles. Like `light_environment`,
+    /// 90 is straight up and -90 is straight down.
+    pub pitch: Option<f64>,
+    /// Brightness `(_light)` Color and brightness of the light.
+    pub color: ColorBrightness,
+    /// BrightnessHDR `(_lightHDR)` Override for Brightness when compiling HDR lighting.
+    /// Defaults to -1 -1 -1 1, which means "same as LDR".
+    pub color_hdr: Option<ColorBrightness>,
+    /// BrightnessScaleHDR `(_lightscaleHDR)` Amount to scale the light by when compiling for HDR.
+    pub hdr_scale: f64,
+    /// SunSpreadAngle `(SunSpreadAngle)` The angular extent of the light for soft shadows.
+    pub sun_spread_angle: f64,
+}
+
+impl<S: Default> Default for LightDirectional<S> {
+    fn default() -> Self {
+        Self {
+            point_entity: PointEntity::default(),
+            pitch: None,
+            color: ColorBrightness::new(255, 255, 255, 200),
+            color_hdr: None,
+            hdr_scale: 1.0,
+            sun_spread_angle: 5.0,
+        }
+    }
+}
+
+impl<'a, S> ToLower<Entity<S>> for LightDirectional<S>
+where
+    S: AsRef<str> + Clone + From<&'a str> + From<String>,
+{
+    fn into_lower(self) -> Entity<S> {
+        let pitch = self.pitch.unwrap_or(-self.point_entity.angles.pitch);
+        // -1 -1 -1 1 is same as LDR
+        let color_hdr = self.color_hdr.map_or("-1 -1 -1 1".to_string(), |color| color.to_string());
+        let mut entity = lower_as(self.point_entity, "light_directional");
+        let props = &mut entity.props;
+        props.reserve_exact(5);
+
+        props.push(Property::new("pitch", pitch.to_string()));
+        props.push(Property::new("_light", self.color.to_string()));
+        props.push(Property::new("_lightHDR", color_hdr));
+        props.push(Property::new("_lightscaleHDR", self.hdr_scale.to_string()));
+        props.push(Property::new("SunSpreadAngle", self.sun_spread_angle.to_string()));
+
+        entity
+    }
+}
+
+/// A sprite of the sun in the skybox. Only drawn where the sky is visible.
+/// See also: <https://developer.valvesoftware.com/wiki/Env_sun>
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnvSun<S> {
+    /// Common Fields. Including classname, origin, angles, flags, etc.
+    pub point_entity: PointEntity<S>,
+    /// Viewer entity `(target)` Name of an entity the sun is in the direction of.
+    /// Ignored if `use_angles`.
+    pub target: S,
+    /// UseAngles `(use_angles)` Use angles and pitch instead of `target`.
+    pub use_angles: bool,
+    /// Pitch `(pitch)` Like `light_environment`, 90 is straight up and -90 is straight down.
+    pub pitch: Option<f64>,
+    /// Sun Color `(rendercolor)` Color of the sun sprite.
+    pub color: RGB8,
+    /// Overlay Color `(overlaycolor)` Color of the glow around the sun. Black to use `color`.
+    pub overlay_color: RGB8,
+    /// Size `(size)` Size of the sun sprite.
+    pub size: i32,
+    /// Overlay Size `(overlaysize)` Size of the glow. -1 to use `size`.
+    pub overlay_size: i32,
+    /// Material Name `(material)` Material of the sun sprite.
+    pub material: S,
+    /// Overlay Material Name `(overlaymaterial)` Material of the glow.
+    pub overlay_material: S,
+    /// HDR Color Scale `(HDRColorScale)` Multiplier for the color when in HDR mode.
+    pub hdr_color_scale: f64,
+}
+
+impl<S: Default + From<&'static str>> Default for EnvSun<S> {
+    fn default() -> Self {
+        Self {
+            point_entity: PointEntity::default(),
+            target: S::default(),
+            use_angles: true,
+            pitch: None,
+            color: RGB8::new(100, 80, 80),
+            overlay_color: RGB8::new(0, 0, 0),
+            size: 16,
+            overlay_size: -1,
+            material: "sprites/light_glow02_add_noz".into(),
+            overlay_material: "sprites/light_glow02_add_noz".into(),
+            hdr_color_scale: 1.0,
+        }
+    }
+}
+
+impl<'a, S> ToLower<Entity<S>> for EnvSun<S>
+where
+    S: AsRef<str> + Clone + From<&'a str> + From<String>,
+{
+    fn into_lower(self) -> Entity<S> {
+        let pitch = self.pitch.unwrap_or(-self.point_entity.angles.pitch);
+        let mut entity = lower_as(self.point_entity, "env_sun");
+        let props = &mut entity.props;
+        props.reserve_exact(10);
+
+        props_push_string(props, "target", self.target);
+        props.push(Property::new("use_angles", (self.use_angles as i32).to_string()));
+        props.push(Property::new("pitch", pitch.to_string()));
+        props.push(Property::new("rendercolor", rgb_string(self.color)));
+        props.push(Property::new("overlaycolor", rgb_string(self.overlay_color)));
+        props.push(Property::new("size", self.size.to_string()));
+        props.push(Property::new("overlaysize", self.overlay_size.to_string()));
+        props.push(Property::new("material", self.material));
+        props.push(Property::new("overlaymaterial", self.overlay_material));
+        props.push(Property::new("HDRColorScale", self.hdr_color_scale.to_string()));
+
+        entity
+    }
+}
+
+/// Wind that blows particles, ropes and cloth. Blows towards the yaw of the angles.
+/// See also: <https://developer.valvesoftware.com/wiki/Env_wind>
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnvWind<S> {
+    /// Common Fields. Including classname, origin, angles, flags, etc.
+    pub point_entity: PointEntity<S>,
+    /// Min normal speed `(minwind)`
+    pub min_wind: i32,
+    /// Max normal speed `(maxwind)`
+    pub max_wind: i32,
+    /// Min gust speed `(mingust)`
+    pub min_gust: i32,
+    /// Max gust speed `(maxgust)`
+    pub max_gust: i32,
+    /// Min gust delay `(mingustdelay)` Minimum seconds between gusts.
+    pub min_gust_delay: i32,
+    /// Max gust delay `(maxgustdelay)` Maximum seconds between gusts.
+    pub max_gust_delay: i32,
+    /// Gust Duration `(gustduration)` Seconds a gust lasts.
+    pub gust_duration: i32,
+    /// Max gust dir change `(gustdirchange)` Degrees the wind can turn during a gust.
+    pub gust_dir_change: i32,
+}
+
+impl<S: Default> Default for EnvWind<S> {
+    fn default() -> Self {
+        Self {
+            point_entity: PointEntity::default(),
+            min_wind: 20,
+            max_wind: 50,
+            min_gust: 100,
+            max_gust: 250,
+            min_gust_delay: 10,
+            max_gust_delay: 20,
+            gust_duration: 5,
+            gust_dir_change: 20,
+        }
+    }
+}
+
+impl<'a, S> ToLower<Entity<S>> for EnvWind<S>
+where
+    S: AsRef<str> + Clone + From<&'a str> + From<String>,
+{
+    fn into_lower(self) -> Entity<S> {
+        let mut entity = lower_as(self.point_entity, "env_wind");
+        let props = &mut entity.props;
+        props.reserve_exact(8);
+
+        props.push(Property::new("minwind", self.min_wind.to_string()));
+        props.push(Property::new("maxwind", self.max_wind.to_string()));
+        props.push(Property::new("mingust", self.min_gust.to_string()));
+        props.push(Property::new("maxgust", self.max_gust.to_string()));
+        props.push(Property::new("mingustdelay", self.min_gust_delay.to_string()));
+        props.push(Property::new("maxgustdelay", self.max_gust_delay.to_string()));
+        props.push(Property::new("gustduration", self.gust_duration.to_string()));
+        props.push(Property::new("gustdirchange", self.gust_dir_change.to_string()));
+
+        entity
+    }
+}
+
+/// Where a cubemap is taken with `buildcubemaps`.
+/// See also: <https://developer.valvesoftware.com/wiki/Env_cubemap>
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EnvCubemap<S> {
+    /// Common Fields. Including classname, origin, angles, flags, etc.
+    pub point_entity: PointEntity<S>,
+    /// Cubemap Size `(cubemapsize)` 0 is the default size, 1 is 1x1 up to 9 for 256x256.
+    pub size: i32,
+    /// Brush faces `(sides)` Space separated ids of the faces to use this cubemap.
+    /// Empty for the closest faces.
+    pub sides: S,
+}
+
+impl<'a, S> ToLower<Entity<S>> for EnvCubemap<S>
+where
+    S: AsRef<str> + Clone + From<&'a str> + From<String>,
+{
+    fn into_lower(self) -> Entity<S> {
+        let mut entity = lower_as(self.point_entity, "env_cubemap");
+        let props = &mut entity.props;
+
+        props_push_value(props, "cubemapsize", self.size);
+        props_push_string(props, "sides", self.sides);
+
+        entity
+    }
+}
+
+/// A model compiled into the map that never moves.
+/// See also: <https://developer.valvesoftware.com/wiki/Prop_static>
+#[derive(Clone, Debug, PartialEq)]
+pub struct PropStatic<S> {
+    /// Common Fields. Including classname, origin, angles, flags, etc.
+    pub point_entity: PointEntity<S>,
+    /// World Model `(model)` Ex: `models/props_junk/wood_crate001a.mdl`.
+    pub model: S,
+    /// Skin `(skin)`
+    pub skin: i32,
+    /// Collisions `(solid)`
+    pub solid: PropSolid,
+    /// Disable Shadows `(disableshadows)`
+    pub disable_shadows: bool,
+    /// Disable Vertex lighting `(disablevertexlighting)` Use one lighting sample for the
+    /// whole model instead of per vertex.
+    pub disable_vertex_lighting: bool,
+    /// Start Fade Dist `(fademindist)` Distance the model starts to fade. -1 for none.
+    pub fade_min_dist: f64,
+    /// End Fade Dist `(fademaxdist)` Distance the model is completely faded. 0 for none.
+    pub fade_max_dist: f64,
+    /// Lighting Origin `(lightingorigin)` Name of an info_lighting to sample lighting from.
+    pub lighting_origin: S,
+}
+
+impl<S: Default> Default for PropStatic<S> {
+    fn default() -> Self {
+        Self {
+            point_entity: PointEntity::default(),
+            model: S::default(),
+            skin: 0,
+            solid: PropSolid::default(),
+            disable_shadows: false,
+            disable_vertex_lighting: false,
+            fade_min_dist: -1.0,
+            fade_max_dist: 0.0,
+            lighting_origin: S::default(),
+        }
+    }
+}
+
+impl<'a, S> ToLower<Entity<S>> for PropStatic<S>
+where
+    S: AsRef<str> + Clone + From<&'a str> + From<String>,
+{
+    fn into_lower(self) -> Entity<S> {
+        let mut entity = lower_as(self.point_entity, "prop_static");
+        let props = &mut entity.props;
+        props.reserve_exact(8);
+
+        props.push(Property::new("model", self.model));
+        props_push_value(props, "skin", self.skin);
+        props.push(Property::new("solid", (self.solid as i32).to_string()));
+        props_push_value(props, "disableshadows", self.disable_shadows as i32);
+        props_push_value(props, "disablevertexlighting", self.disable_vertex_lighting as i32);
+        props.push(Property::new("fademindist", self.fade_min_dist.to_string()));
+        props_push_value(props, "fademaxdist", self.fade_max_dist);
+        props_push_string(props, "lightingorigin", self.lighting_origin);
+
+        entity
+    }
+}
+
+/// A model that can animate and be moved, not physically simulated.
+/// See also: <https://developer.valvesoftware.com/wiki/Prop_dynamic>
+#[derive(Clone, Debug, PartialEq)]
+pub struct PropDynamic<S> {
+    /// Common Fields. Including classname, origin, angles, flags, etc.
+    pub point_entity: PointEntity<S>,
+    /// World Model `(model)` Ex: `models/props_junk/wood_crate001a.mdl`.
+    pub model: S,
+    /// Skin `(skin)`
+    pub skin: i32,
+    /// Collisions `(solid)`
+    pub solid: PropSolid,
+    /// Default Animation `(DefaultAnim)` The animation to play when not playing another one.
+    pub default_animation: S,
+    /// Randomly Animate `(RandomAnimation)` Randomly pick animations to play.
+    pub random_animation: bool,
+    /// Start Disabled `(StartDisabled)`
+    pub start_disabled: bool,
+    /// Disable Shadows `(disableshadows)`
+    pub disable_shadows: bool,
+    /// Start Fade Dist `(fademindist)` Distance the model starts to fade. -1 for none.
+    pub fade_min_dist: f64,
+    /// End Fade Dist `(fademaxdist)` Distance the model is completely faded. 0 for none.
+    pub fade_max_dist: f64,
+}
+
+impl<S: Default> Default for PropDynamic<S> {
+    fn default() -> Self {
+        Self {
+            point_entity: PointEntity::default(),
+            model: S::default(),
+            skin: 0,
+            solid: PropSolid::default(),
+            default_animation: S::default(),
+            random_animation: false,
+            start_disabled: false,
+            disable_shadows: false,
+            fade_min_dist: -1.0,
+            fade_max_dist: 0.0,
+        }
+    }
+}
+
+impl<'a, S> ToLower<Entity<S>> for PropDynamic<S>
+where
+    S: AsRef<str> + Clone + From<&'a str> + From<String>,
+{
+    fn into_lower(self) -> Entity<S> {
+        let mut entity = lower_as(self.point_entity, "prop_dynamic");
+        let props = &mut entity.props;
+        props.reserve_exact(9);
+
+        props.push(Property::new("model", self.model));
+        props_push_value(props, "skin", self.skin);
+        props.push(Property::new("solid", (self.solid as i32).to_string()));
+        props_push_string(props, "DefaultAnim", self.default_animation);
+        props_push_value(props, "RandomAnimation", self.random_animation as i32);
+        props_push_value(props, "StartDisabled", self.start_disabled as i32);
+        props_push_value(props, "disableshadows", self.disable_shadows as i32);
+        props.push(Property::new("fademindist", self.fade_min_dist.to_string()));
+        props_push_value(props, "fademaxdist", self.fade_max_dist);
+
+        entity
+    }
+}
+
+/// A named point for other entities to target. Ex: where a camera looks.
+/// See also: <https://developer.valvesoftware.com/wiki/Info_target>
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InfoTarget<S> {
+    /// Common Fields. Including classname, origin, angles, flags, etc.
+    pub point_entity: PointEntity<S>,
+}
+
+impl<'a, S> ToLower<Entity<S>> for InfoTarget<S>
+where
+    S: AsRef<str> + Clone + From<&'a str> + From<String>,
+{
+    fn into_lower(self) -> Entity<S> {
+        lower_as(self.point_entity, "info_target")
+    }
+}
+
+/// Fires outputs when the map spawns. Ex: `OnMapSpawn`.
+/// See also: <https://developer.valvesoftware.com/wiki/Logic_auto>
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogicAuto<S> {
+    /// Common Fields. Including classname, origin, angles, flags, outputs, etc.
+    pub point_entity: PointEntity<S>,
+    /// Flag. Remove this entity after firing.
+    pub remove_on_fire: bool,
+    /// Global State to Read `(globalstate)` If set, fires `OnMapSpawn` only when this
+    /// global state is on.
+    pub global_state: S,
+}
+
+impl<S: Default> Default for LogicAuto<S> {
+    fn default() -> Self {
+        Self {
+            point_entity: PointEntity::default(),
+            remove_on_fire: true,
+            global_state: S::default(),
+        }
+    }
+}
+
+impl<'a, S> ToLower<Entity<S>> for LogicAuto<S>
+where
+    S: AsRef<str> + Clone + From<&'a str> + From<String>,
+{
+    fn into_lower(mut self) -> Entity<S> {
+        self.point_entity.flags |= self.remove_on_fire as i32;
+        let mut entity = lower_as(self.point_entity, "logic_auto");
+        props_push_string(&mut entity.props, "globalstate", self.global_state);
+        entity
+    }
+}
+
+/// Fires `OnTrigger` when its `Trigger` input is fired. Ex: to group outputs.
+/// See also: <https://developer.valvesoftware.com/wiki/Logic_relay>
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LogicRelay<S> {
+    /// Common Fields. Including classname, origin, angles, flags, outputs, etc.
+    pub point_entity: PointEntity<S>,
+    /// Flag. Only trigger once, then remove itself.
+    pub only_once: bool,
+    /// Flag. Allow being triggered again while its outputs are still pending.
+    pub allow_fast_retrigger: bool,
+    /// Start Disabled `(StartDisabled)`
+    pub start_disabled: bool,
+}
+
+impl<'a, S> ToLower<Entity<S>> for LogicRelay<S>
+where
+    S: AsRef<str> + Clone + From<&'a str> + From<String>,
+{
+    fn into_lower(mut self) -> Entity<S> {
+        self.point_entity.flags |= self.only_once as i32 | (self.allow_fast_retrigger as i32) << 1;
+        let mut entity = lower_as(self.point_entity, "logic_relay");
+        props_push_value(&mut entity.props, "StartDisabled", self.start_disabled as i32);
+        entity
+    }
+}
+
+/// Plays a sound.
+/// See also: <https://developer.valvesoftware.com/wiki/Ambient_generic>
+#[derive(Clone, Debug, PartialEq)]
+pub struct AmbientGeneric<S> {
+    /// Common Fields. Including classname, origin, angles, flags, etc.
+    pub point_entity: PointEntity<S>,
+    /// Flag. Play everywhere, ignoring `radius`.
+    pub play_everywhere: bool,
+    /// Flag. Start silent, needs the `PlaySound` input.
+    pub start_silent: bool,
+    /// Flag. Is not looped. The sound only plays once.
+    pub not_looped: bool,
+
+    /// Sound Name `(message)` A sound file or soundscript. Ex: `ambient/wind/wind1.wav`.
+    pub sound: S,
+    /// Volume `(health)` 0 to 10.
+    pub volume: i32,
+    /// Pitch `(pitch)` 1 to 255, 100 is normal.
+    pub pitch: i32,
+    /// Max Audible Distance `(radius)`
+    pub radius: f64,
+    /// Source Entity `(SourceEntityName)` Play the sound from this entity instead.
+    pub source_entity: S,
+}
+
+impl<S: Default> Default for AmbientGeneric<S> {
+    fn default() -> Self {
+        Self {
+            point_entity: PointEntity::default(),
+            play_everywhere: false,
+            start_silent: false,
+            not_looped: false,
+            sound: S::default(),
+            volume: 10,
+            pitch: 100,
+            radius: 1250.0,
+            source_entity: S::default(),
+        }
+    }
+}
+
+impl<'a, S> ToLower<Entity<S>> for AmbientGeneric<S>
+where
+    S: AsRef<str> + Clone + From<&'a str> + From<String>,
+{
+    fn into_lower(mut self) -> Entity<S> {
+        self.point_entity.flags |= self.play_everywhere as i32
+            | (self.start_silent as i32) << 4
+            | (self.not_looped as i32) << 5;
+        let mut entity = lower_as(self.point_entity, "ambient_generic");
+        let props = &mut entity.props;
+        props.reserve_exact(5);
+
+        props.push(Property::new("message", self.sound));
+        props.push(Property::new("health", self.volume.to_string()));
+        props.push(Property::new("pitch", self.pitch.to_string()));
+        props.push(Property::new("radius", self.radius.to_string()));
+        props_push_string(props, "SourceEntityName", self.source_entity);
+
+        entity
+    }
+}
+
+/// Plays a soundscape to players who can see it and are within `radius`.
+/// See also: <https://developer.valvesoftware.com/wiki/Env_soundscape>
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnvSoundscape<S> {
+    /// Common Fields. Including classname, origin, angles, flags, etc.
+    pub point_entity: PointEntity<S>,
+    /// Soundscape `(soundscape)` Name of the soundscape. Ex: `Nature.Forest`.
+    pub soundscape: S,
+    /// Radius `(radius)` -1 for everywhere.
+    pub radius: f64,
+    /// Start Disabled `(StartDisabled)`
+    pub start_disabled: bool,
+    /// Sound Positions `(position0)` to `(position7)` Names of entities to play
+    /// the positional sounds of the soundscape from.
+    pub positions: Vec<S>,
+}
+
+impl<S: Default> Default for EnvSoundscape<S> {
+    fn default() -> Self {
+        Self {
+            point_entity: PointEntity::default(),
+            soundscape: S::default(),
+            radius: 128.0,
+            start_disabled: false,
+            positions: Vec::new(),
+        }
+    }
+}
+
+impl<'a, S> ToLower<Entity<S>> for EnvSoundscape<S>
+where
+    S: AsRef<str> + Clone + From<&'a str> + From<String>,
+{
+    fn into_lower(self) -> Entity<S> {
+        debug_assert!(self.positions.len() <= 8, "Max 8 soundscape positions");
+        let mut entity = lower_as(self.point_entity, "env_soundscape");
+        let props = &mut entity.props;
+
+        props.push(Property::new("soundscape", self.soundscape));
+        props.push(Property::new("radius", self.radius.to_string()));
+        props_push_value(props, "StartDisabled", self.start_disabled as i32);
+        for (i, position) in self.positions.into_iter().take(8).enumerate() {
+            props.push(Property::new(format!("position{i}"), position));
+        }
+
+        entity
+    }
+}
+
+/// The origin of the 3d skybox. The point in the skybox that matches the world origin.
+/// See also: <https://developer.valvesoftware.com/wiki/Sky_camera>
+#[derive(Clone, Debug, PartialEq)]
+pub struct SkyCamera<S> {
+    /// Common Fields. Including classname, origin, angles, flags, etc.
+    pub point_entity: PointEntity<S>,
+    /// 3D Skybox scale `(scale)` How many world units one skybox unit is.
+    pub scale: i32,
+    /// Fog Enable `(fogenable)`
+    pub fog_enabled: bool,
+    /// Fog Blend `(fogblend)` Blend between the primary and secondary color by direction.
+    pub fog_blend: bool,
+    /// Primary Fog Color `(fogcolor)`
+    pub fog_color: RGB8,
+    /// Secondary Fog Color `(fogcolor2)`
+    pub fog_color2: RGB8,
+    /// Primary Fog Direction `(fogdir)`
+    pub fog_dir: Vector3<f64>,
+    /// Fog Start `(fogstart)` In world units, not skybox units.
+    pub fog_start: f64,
+    /// Fog End `(fogend)` In world units, not skybox units.
+    pub fog_end: f64,
+    /// Fog Max Density `(fogmaxdensity)` 0 to 1.
+    pub fog_max_density: f64,
+}
+
+impl<S: Default> Default for SkyCamera<S> {
+    fn default() -> Self {
+        Self {
+            point_entity: PointEntity::default(),
+            scale: 16,
+            fog_enabled: false,
+            fog_blend: false,
+            fog_color: RGB8::new(255, 255, 255),
+            fog_color2: RGB8::new(255, 255, 255),
+            fog_dir: Vector3::new(1.0, 0.0, 0.0),
+            fog_start: 500.0,
+            fog_end: 2000.0,
+            fog_max_density: 1.0,
+        }
+    }
+}
+
+impl<'a, S> ToLower<Entity<S>> for SkyCamera<S>
+where
+    S: AsRef<str> + Clone + From<&'a str> + From<String>,
+{
+    fn into_lower(self) -> Entity<S> {
+        let mut entity = lower_as(self.point_entity, "sky_camera");
+        let props = &mut entity.props;
+        props.reserve_exact(9);
+
+        props.push(Property::new("scale", self.scale.to_string()));
+        props.push(Property::new("fogenable", (self.fog_enabled as i32).to_string()));
+        props.push(Property::new("fogblend", (self.fog_blend as i32).to_string()));
+        props.push(Property::new("fogcolor", rgb_string(self.fog_color)));
+        props.push(Property::new("fogcolor2", rgb_string(self.fog_color2)));
+        props.push(Property::new("fogdir", self.fog_dir.to_string()));
+        props.push(Property::new("fogstart", self.fog_start.to_string()));
+        props.push(Property::new("fogend", self.fog_end.to_string()));
+        props.push(Property::new("fogmaxdensity", self.fog_max_density.to_string()));
+
+        entity
+    }
+}
+
+/// A decal like material projected onto brush faces. Ex: road markings, graffiti.
+/// See also: <https://developer.valvesoftware.com/wiki/Info_overlay>
+#[derive(Clone, Debug, PartialEq)]
+pub struct InfoOverlay<S> {
+    /// Common Fields. Including classname, origin, angles, flags, etc.
+    /// The origin is the center of the overlay.
+    pub point_entity: PointEntity<S>,
+    /// Material `(material)` Ex: `decals/lambdalogo`.
+    pub material: S,
+    /// Brush faces `(sides)` Space separated ids of the faces the overlay is projected onto.
+    pub sides: S,
+    /// Render Order `(RenderOrder)` Higher numbers are drawn on top. 0 to 3.
+    pub render_order: i32,
+    /// Overlay Basis Normal `(BasisNormal)` The direction the overlay faces.
+    pub basis_normal: Vector3<f32>,
+    /// Overlay Basis U `(BasisU)` The direction of the U texture axis.
+    pub basis_u: Vector3<f32>,
+    /// Overlay Basis V `(BasisV)` The direction of the V texture axis.
+    pub basis_v: Vector3<f32>,
+    /// Corners `(uv0)` to `(uv3)` Relative to the origin along `basis_u` and `basis_v`.
+    pub uvs: [Vector3<f32>; 4],
+    /// Texture U Start and End `(StartU)`, `(EndU)`
+    pub u_range: (f64, f64),
+    /// Texture V Start and End `(StartV)`, `(EndV)`
+    pub v_range: (f64, f64),
+}
+
+impl<S: Default> InfoOverlay<S> {
+    /// A `width` by `height` overlay facing `normal` centered on `origin`, the whole
+    /// material stretched to fit.
+    pub fn new(
+        material: S, sides: S, origin: &Vector3<f32>, normal: &Vector3<f32>, width: f32,
+        height: f32,
+    ) -> Self {
+        let basis_normal = normal.normalize();
+        // V is up on walls and north on floors and ceilings
+        let up = match basis_normal.z.abs() > 0.9 {
+            true => Vector3::new(0.0, 1.0, 0.0),
+            false => Vector3::new(0.0, 0.0, 1.0),
+        };
+        let basis_u = up.cross(&basis_normal).normalize();
+        let basis_v = basis_normal.cross(&basis_u);
+        let (u, v) = (width / 2.0, height / 2.0);
+        let point_entity = PointEntity {
+            origin: Vector3::new(origin.x as f64, origin.y as f64, origin.z as f64),
+            ..Default::default()
+        };
+
+        Self {
+            point_entity,
+            material,
+            sides,
+            render_order: 0,
+            basis_normal,
+            basis_u,
+            basis_v,
+            uvs: [
+                Vector3::new(-u, -v, 0.0),
+                Vector3::new(-u, v, 0.0),
+                Vector3::new(u, v, 0.0),
+                Vector3::new(u, -v, 0.0),
+            ],
+            u_range: (0.0, 1.0),
+            v_range: (0.0, 1.0),
+        }
+    }
+}
+
+impl<'a, S> ToLower<Entity<S>> for InfoOverlay<S>
+where
+    S: AsRef<str> + Clone + From<&'a str> + From<String>,
+{
+    fn into_lower(self) -> Entity<S> {
+        let basis_origin = self.point_entity.origin.to_string();
+        let mut entity = lower_as(self.point_entity, "info_overlay");
+        let props = &mut entity.props;
+        props.reserve_exact(15);
+
+        props.push(Property::new("material", self.material));
+        props.push(Property::new("sides", self.sides));
+        props_push_value(props, "RenderOrder", self.render_order);
+        props.push(Property::new("BasisOrigin", basis_origin));
+        props.push(Property::new("BasisNormal", self.basis_normal.to_string()));
+        props.push(Property::new("BasisU", self.basis_u.to_string()));
+        props.push(Property::new("BasisV", self.basis_v.to_string()));
+        for (i, uv) in self.uvs.iter().enumerate() {
+            props.push(Property::new(format!("uv{i}"), uv.to_string()));
+        }
+        props.push(Property::new("StartU", self.u_range.0.to_string()));
+        props.push(Property::new("EndU", self.u_range.1.to_string()));
+        props.push(Property::new("StartV", self.v_range.0.to_string()));
+        props.push(Property::new("EndV", self.v_range.1.to_string()));
+
+        entity
+    }
+}
+
+/// Brushes that don't block visibility, for detail that would slow down `vvis`.
+/// See also: <https://developer.valvesoftware.com/wiki/Func_detail>
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FuncDetail<'a> {
+    pub solids: Vec<Solid<'a>>,
+}
+
+impl<'a> FuncDetail<'a> {
+    pub const fn new(solids: Vec<Solid<'a>>) -> Self {
+        Self { solids }
+    }
+}
+
+impl<'a> ToLower<BrushEntity<'a>> for FuncDetail<'a> {
+    fn into_lower(self) -> BrushEntity<'a> {
+        let entity = Entity::new(vec![Property::new("classname", "func_detail")]);
+        BrushEntity::new(entity, self.solids)
+    }
+}
+
+/// What falls in a [`FuncPrecipitation`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PrecipitationType {
+    #[default]
+    Rain = 0,
+    Snow = 1,
+    Ash = 2,
+    Snowfall = 3,
+    // NOTE:GAME: l4d+
+    ParticleRain = 4,
+    ParticleAsh = 5,
+    ParticleRainstorm = 6,
+    ParticleSnow = 7,
+}
+
+/// Rain or snow falling inside brushes. Doesn't fall through solid brushes.
+/// See also: <https://developer.valvesoftware.com/wiki/Func_precipitation>
+#[derive(Clone, Debug, PartialEq)]
+pub struct FuncPrecipitation<'a> {
+    pub solids: Vec<Solid<'a>>,
+    /// Density `(renderamt)` 0 to 100 percent.
+    pub density: u8,
+    /// Color `(rendercolor)`
+    pub color: RGB8,
+    /// Precipitation Type `(preciptype)`
+    pub kind: PrecipitationType,
+}
+
+impl<'a> FuncPrecipitation<'a> {
+    pub const fn new(solids: Vec<Solid<'a>>, kind: PrecipitationType) -> Self {
+        Self { solids, density: 5, color: RGB8::new(100, 100, 100), kind }
+    }
+}
+
+impl<'a> ToLower<BrushEntity<'a>> for FuncPrecipitation<'a> {
+    fn into_lower(self) -> BrushEntity<'a> {
+        let entity = Entity::new(vec![
+            Property::new("classname", "func_precipitation"),
+            Property::new("renderamt", self.density.to_string()),
+            Property::new("rendercolor", rgb_string(self.color)),
+            Property::new("preciptype", (self.kind as i32).to_string()),
+        ]);
+        BrushEntity::new(entity, self.solids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation2::{shape::cube, Bounds, SolidOptions};
+    use crate::map::Map;
+    use crate::prelude::Material;
+    use crate::source::Output;
+    use crate::StrType;
+    use vmf_parser_nom::ast::Block;
+
+    fn value<'b>(entity: &'b Entity<StrType<'_>>, key: &str) -> Option<&'b str> {
+        entity.get(key).map(AsRef::as_ref)
+    }
+
+    #[test]
+    fn lower() {
+        let spawn: Entity<StrType> =
+            InfoPlayerStart { is_master: true, ..Default::default() }.into_lower();
+        assert_eq!(Some("info_player_start"), value(&spawn, "classname"));
+        assert_eq!(Some("1"), value(&spawn, "spawnflags"));
+        assert_eq!(None, value(&spawn, "LagCompensate"));
+
+        let mut relay = LogicRelay::<StrType> { only_once: true, ..Default::default() };
+        relay.point_entity.name = "relay".into();
+        relay.point_entity.outputs.push(Output {
+            delay: 1.5,
+            ..Output::new("OnTrigger".into(), "door".into(), "Open".into())
+        });
+        let relay = relay.into_lower();
+        assert_eq!(Some("logic_relay"), value(&relay, "classname"));
+        assert_eq!(Some("relay"), value(&relay, "targetname"));
+        assert_eq!("door,Open,,1.5,-1", relay.connections[0].value);
+
+        let spot: Entity<StrType> = LightSpot::default().into_lower();
+        assert_eq!(Some("light_spot"), value(&spot, "classname"));
+        assert_eq!(Some("255 255 255 200"), value(&spot, "_light"));
+        assert_eq!(Some("-1 -1 -1 1"), value(&spot, "_lightHDR"));
+        assert_eq!(Some("45"), value(&spot, "_cone"));
+
+        let sound: Entity<StrType> = AmbientGeneric {
+            sound: "ambient/wind/wind1.wav".into(),
+            start_silent: true,
+            not_looped: true,
+            ..Default::default()
+        }
+        .into_lower();
+        assert_eq!(Some("48"), value(&sound, "spawnflags"));
+        assert_eq!(Some("10"), value(&sound, "health"));
+
+        let overlay: Entity<StrType> = InfoOverlay::new(
+            "decals/lambdalogo".into(),
+            "12".into(),
+            &Vector3::new(0.0, 0.0, 0.0),
+            &Vector3::new(0.0, 0.0, 1.0),
+            64.0,
+            32.0,
+        )
+        .into_lower();
+        assert_eq!(Some("1 0 0"), value(&overlay, "BasisU"));
+        assert_eq!(Some("0 1 0"), value(&overlay, "BasisV"));
+        assert_eq!(Some("32 16 0"), value(&overlay, "uv2"));
+    }
+
+    #[test]
+    fn func_detail() {
+        let mat = Material::new("DEV/DEV_MEASUREWALL01C".into());
+        let bounds = Bounds::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(64.0, 64.0, 64.0));
+        let cube = cube(&bounds, &[&mat; 6], &SolidOptions::default());
+        let mut map = Map::default();
+        map.brush_entities.push(FuncDetail::new(vec![cube]).into_lower());
+
+        let vmf: vmf_parser_nom::ast::Vmf<StrType> = map.into_lower();
+        let entity: &Block<StrType> = vmf.inner.blocks.last().unwrap();
+        assert_eq!("entity", entity.name);
+        assert_eq!("func_detail", entity.props[0].value);
+        assert_eq!(1, entity.blocks.len());
+        assert_eq!("solid", entity.blocks[0].name);
+
+        let rain = FuncPrecipitation::new(vec![], PrecipitationType::ParticleRain).into_lower();
+        assert_eq!(Some("func_precipitation"), value(&rain.entity, "classname"));
+        assert_eq!(Some("4"), value(&rain.entity, "preciptype"));
+        assert_eq!(Some("100 100 100"), value(&rain.entity, "rendercolor"));
+        let wind: Entity<StrType> = EnvWind::default().into_lower();
+        assert_eq!(Some("env_wind"), value(&wind, "classname"));
+        assert_eq!(Some("250"), value(&wind, "maxgust"));
+    }
+}
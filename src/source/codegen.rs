@@ -0,0 +1,436 @@
+//! Generate Rust source for typed entity structs from an [`Fgd`], like the ones in
+//! [`catalogue`](super::catalogue). See the `fgd_codegen` binary.
+//!
+//! The generated file is meant to be added as a module of [`source`](crate::source).
+
+use std::fmt::Write;
+
+use crate::source::{Fgd, FgdClass, FgdKey, FgdValueType};
+
+/// Keys that are fields of [`PointEntity`](super::PointEntity).
+const POINT_ENTITY_KEYS: [&str; 11] = [
+    "targetname",
+    "parentname",
+    "origin",
+    "angles",
+    "classname",
+    "spawnflags",
+    "effects",
+    "vscripts",
+    "thinkfunction",
+    "lagcompensate",
+    "is_autoaim_target",
+];
+
+const KEYWORDS: [&str; 38] = [
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use",
+    "where", "while", "abstract",
+];
+
+/// The Rust type of a field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FieldType {
+    Str,
+    Int,
+    Float,
+    Bool,
+    Vector,
+    Angles,
+}
+
+impl FieldType {
+    fn of(key: &FgdKey) -> Self {
+        match key.value_type {
+            FgdValueType::Integer => Self::Int,
+            FgdValueType::Float => Self::Float,
+            FgdValueType::Boolean => Self::Bool,
+            FgdValueType::Vector => Self::Vector,
+            FgdValueType::Angle => Self::Angles,
+            FgdValueType::Choices
+                if !key.choices.is_empty()
+                    && key.choices.iter().all(|choice| choice.value.parse::<i32>().is_ok()) =>
+            {
+                Self::Int
+            }
+            _ => Self::Str,
+        }
+    }
+
+    const fn rust(self) -> &'static str {
+        match self {
+            Self::Str => "S",
+            Self::Int => "i32",
+            Self::Float => "f64",
+            Self::Bool => "bool",
+            Self::Vector => "Vector3<f64>",
+            Self::Angles => "Angles",
+        }
+    }
+
+    /// A Rust expression of the `default` of a key.
+    fn default(self, default: Option<&str>) -> String {
+        let default = default.unwrap_or("").trim();
+        let numbers = || {
+            let numbers: Vec<f64> =
+                default.split_whitespace().map(|n| n.parse().unwrap_or(0.0)).collect();
+            let n = |i: usize| numbers.get(i).copied().unwrap_or(0.0);
+            [n(0), n(1), n(2)]
+        };
+        match self {
+            Self::Str if default.is_empty() => "S::default()".to_string(),
+            Self::Str => format!("{default:?}.into()"),
+            Self::Int => (default.parse::<f64>().unwrap_or(0.0) as i32).to_string(),
+            Self::Float => format!("{:?}", default.parse::<f64>().unwrap_or(0.0)),
+            Self::Bool => (default.parse::<f64>().unwrap_or(0.0) != 0.0).to_string(),
+            Self::Vector => {
+                let [x, y, z] = numbers();
+                format!("Vector3::new({x:?}, {y:?}, {z:?})")
+            }
+            Self::Angles => {
+                let [pitch, yaw, roll] = numbers();
+                format!("Angles {{ pitch: {pitch:?}, yaw: {yaw:?}, roll: {roll:?} }}")
+            }
+        }
+    }
+}
+
+/// A key of a class as a field of the generated struct.
+struct Field<'b> {
+    name: String,
+    key: &'b FgdKey,
+    field_type: FieldType,
+}
+
+/// `info_player_start` -> `InfoPlayerStart`
+fn struct_name(classname: &str) -> String {
+    let mut name: String = classname
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().unwrap_or_default().to_ascii_uppercase();
+            first.to_string() + chars.as_str()
+        })
+        .collect();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, 'E');
+    }
+    name
+}
+
+/// `_lightHDR` -> `light_hdr`
+fn field_name(key: &str) -> String {
+    let mut name = String::new();
+    let mut prev_lower = false;
+    for c in key.chars() {
+        if c.is_ascii_uppercase() && prev_lower {
+            name.push('_');
+        }
+        prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        name.push(if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' });
+    }
+    let mut name = name.trim_matches('_').to_string();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert_str(0, "key_");
+    }
+    if KEYWORDS.contains(&name.as_str()) {
+        name.push('_');
+    }
+    name
+}
+
+/// `"Start on"` -> `START_ON`
+fn const_name(name: &str) -> String {
+    let name = field_name(&name.replace(' ', "_")).to_ascii_uppercase();
+    name.trim_end_matches('_').to_string()
+}
+
+/// Write `text` as doc comments, one per line.
+fn doc(out: &mut String, indent: &str, text: &str) {
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        writeln!(out, "{indent}/// {line}").unwrap();
+    }
+}
+
+/// The fields of a class, excluding ones in [`PointEntity`](super::PointEntity).
+fn fields<'b>(fgd: &'b Fgd, class: &'b FgdClass) -> Vec<Field<'b>> {
+    let mut fields: Vec<Field> = Vec::new();
+    for key in fgd.keys(class) {
+        if POINT_ENTITY_KEYS.contains(&key.name.to_ascii_lowercase().as_str()) {
+            continue;
+        }
+        let mut name = field_name(&key.name);
+        // ex: `fogcolor` and `FogColor`
+        while fields.iter().any(|field| field.name == name) {
+            name.push('_');
+        }
+        fields.push(Field { name, key, field_type: FieldType::of(key) });
+    }
+    fields
+}
+
+/// Generate typed structs for the point classes in `classnames`, or all point
+/// classes if empty. Each struct has the [`PointEntity`](super::PointEntity) fields,
+/// a field per key with docs and defaults from the FGD, constants for spawnflags,
+/// [`ToLower`](crate::vmf::ToLower) into an [`Entity`](crate::map::Entity) and
+/// `TryFrom<&Entity>` to parse one.
+///
+/// Unknown classnames and non point classes are skipped.
+pub fn codegen(fgd: &Fgd, classnames: &[&str]) -> String {
+    let classes: Vec<_> = match classnames.is_empty() {
+        true => fgd.classes.iter().filter(|class| class.kind.is_point()).collect(),
+        false => classnames
+            .iter()
+            .filter_map(|name| fgd.class(name))
+            .filter(|class| class.kind.is_point())
+            .collect(),
+    };
+
+    let mut out = String::new();
+    out += "//! Generated by `fgd_codegen`.\n\n";
+    out += "#![allow(unused_imports)]\n\n";
+    out += "use vmf_parser_nom::ast::Property;\n\n";
+    out += "use crate::map::{Angles, Entity};\n";
+    out += "use crate::prelude::Vector3;\n";
+    out += "use crate::source::entity::{parse_angles, parse_vector3, props_push_string};\n";
+    out += "use crate::source::PointEntity;\n";
+    out += "use crate::vmf::ToLower;\n";
+    for class in classes {
+        out.push('\n');
+        class_code(&mut out, fgd, class);
+    }
+    out
+}
+
+fn class_code(out: &mut String, fgd: &Fgd, class: &FgdClass) {
+    let name = struct_name(&class.name);
+    let classname = &class.name;
+    let fields = fields(fgd, class);
+    let keys = fgd.keys(class);
+    let flags = keys.iter().find(|key| key.value_type == FgdValueType::Flags);
+
+    // struct
+    doc(out, "", &class.description);
+    writeln!(out, "/// `{classname}`").unwrap();
+    *out += "#[derive(Clone, Debug, PartialEq)]\n";
+    writeln!(out, "pub struct {name}<S> {{").unwrap();
+    *out += "    /// Common Fields. Including classname, origin, angles, flags, etc.\n";
+    *out += "    pub point_entity: PointEntity<S>,\n";
+    for Field { name, key, field_type } in fields.iter() {
+        doc(out, "    ", &format!("{} `({})` {}", key.display_name, key.name, key.description));
+        if field_type == &FieldType::Int && !key.choices.is_empty() {
+            *out += "    ///\n";
+            for choice in key.choices.iter() {
+                writeln!(out, "    /// - {}: {}", choice.value, choice.display_name).unwrap();
+            }
+        }
+        writeln!(out, "    pub {name}: {},", field_type.rust()).unwrap();
+    }
+    *out += "}\n\n";
+
+    // flags
+    if let Some(flags) = flags.filter(|flags| !flags.choices.is_empty()) {
+        writeln!(out, "impl<S> {name}<S> {{").unwrap();
+        for flag in flags.choices.iter() {
+            doc(out, "    ", &format!("Flag. {}", flag.display_name));
+            let value = flag.value.parse::<i32>().unwrap_or(0);
+            writeln!(out, "    pub const {}: i32 = {value};", const_name(&flag.display_name))
+                .unwrap();
+        }
+        *out += "}\n\n";
+    }
+
+    // default
+    let default_flags: i32 = flags
+        .iter()
+        .flat_map(|flags| flags.choices.iter())
+        .filter(|flag| flag.default)
+        .filter_map(|flag| flag.value.parse::<i32>().ok())
+        .fold(0, |flags, flag| flags | flag);
+    writeln!(out, "impl<S: Default + From<&'static str>> Default for {name}<S> {{").unwrap();
+    *out += "    fn default() -> Self {\n";
+    *out += "        Self {\n";
+    match default_flags {
+        0 => *out += "            point_entity: PointEntity::default(),\n",
+        flags => {
+            writeln!(
+                out,
+                "            point_entity: PointEntity {{ flags: {flags}, ..Default::default() }},"
+            )
+            .unwrap();
+        }
+    }
+    for Field { name, key, field_type } in fields.iter() {
+        writeln!(out, "            {name}: {},", field_type.default(key.default.as_deref()))
+            .unwrap();
+    }
+    *out += "        }\n    }\n}\n\n";
+
+    // lowering
+    writeln!(out, "impl<'a, S> ToLower<Entity<S>> for {name}<S>").unwrap();
+    *out += "where\n    S: AsRef<str> + Clone + From<&'a str> + From<String>,\n{\n";
+    *out += "    fn into_lower(mut self) -> Entity<S> {\n";
+    writeln!(out, "        self.point_entity.classname = \"{classname}\".into();").unwrap();
+    if fields.is_empty() {
+        *out += "        self.point_entity.into_lower()\n";
+    } else {
+        *out += "        let mut entity = self.point_entity.into_lower();\n";
+        *out += "        let props = &mut entity.props;\n\n";
+        for Field { name, key, field_type } in fields.iter() {
+            let key = &key.name;
+            let line = match field_type {
+                FieldType::Str => format!("props_push_string(props, \"{key}\", self.{name});"),
+                FieldType::Bool => format!(
+                    "props.push(Property::new(\"{key}\", (self.{name} as i32).to_string()));"
+                ),
+                _ => format!("props.push(Property::new(\"{key}\", self.{name}.to_string()));"),
+            };
+            writeln!(out, "        {line}").unwrap();
+        }
+        *out += "\n        entity\n";
+    }
+    *out += "    }\n}\n\n";
+
+    // parsing
+    writeln!(out, "impl<'b, S> TryFrom<&'b Entity<S>> for {name}<S>").unwrap();
+    *out += "where\n    S: AsRef<str> + Clone + Default + From<&'static str> + From<String>,\n{\n";
+    *out += "    type Error = &'static str;\n\n";
+    *out += "    fn try_from(entity: &'b Entity<S>) -> Result<Self, Self::Error> {\n";
+    *out += "        let point_entity = PointEntity::try_from(entity)?;\n";
+    writeln!(
+        out,
+        "        if !point_entity.classname.as_ref().eq_ignore_ascii_case(\"{classname}\") {{"
+    )
+    .unwrap();
+    writeln!(out, "            return Err(\"Not a {classname}\");\n        }}").unwrap();
+    if fields.is_empty() {
+        *out += "        Ok(Self { point_entity })\n    }\n}\n";
+        return;
+    }
+    *out += "        let mut this = Self { point_entity, ..Self::default() };\n";
+    *out += "        for prop in entity.props.iter() {\n";
+    *out += "            let value = prop.value.as_ref().trim();\n";
+    *out += "            match prop.key.as_ref().to_ascii_lowercase().as_str() {\n";
+    for Field { name, key, field_type } in fields.iter() {
+        let error = format!("\"Invalid {}\"", key.name);
+        let parse = match field_type {
+            FieldType::Str => "prop.value.clone()".to_string(),
+            FieldType::Int | FieldType::Float => format!("value.parse().map_err(|_| {error})?"),
+            FieldType::Bool => "value != \"0\"".to_string(),
+            FieldType::Vector => format!("parse_vector3(value).ok_or({error})?"),
+            FieldType::Angles => format!("parse_angles(value).ok_or({error})?"),
+        };
+        let key = key.name.to_ascii_lowercase();
+        writeln!(out, "                \"{key}\" => this.{name} = {parse},").unwrap();
+    }
+    *out += "                _ => (),\n            }\n        }\n";
+    *out += "        Ok(this)\n    }\n}\n";
+}
+
+/// [`codegen()`] of the test FGD, to check that the generated code compiles and works.
+#[cfg(test)]
+mod generated;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{Angles, Entity};
+    use crate::prelude::Vector3;
+    use crate::vmf::ToLower;
+    use crate::StrType;
+    use generated::EnvSparkler;
+
+    const FGD: &str = r#"
+@BaseClass = Targetname [ targetname(target_source) : "Name" ]
+@PointClass base(Targetname) = env_sparkler : "Makes sparks." +
+    " Very shiny."
+[
+    spawnflags(flags) =
+    [
+        1 : "Start on" : 1
+        4 : "Silent" : 0
+    ]
+    MaxDelay(float) : "Max Delay" : "2.5" : "Longest delay between sparks."
+    Magnitude(choices) : "Magnitude" : 1 =
+    [
+        1 : "Small"
+        2 : "Large"
+    ]
+    sound(sound) : "Sound" : "DoSpark"
+    type(boolean) : "Type"
+    direction(angle) : "Direction" : "-90 0 0"
+]
+@SolidClass = func_detail []
+"#;
+
+    #[test]
+    fn names() {
+        assert_eq!("InfoPlayerStart", struct_name("info_player_start"));
+        assert_eq!("E2dSkybox", struct_name("2d_skybox"));
+        assert_eq!("light_hdr", field_name("_lightHDR"));
+        assert_eq!("fog_color2", field_name("FogColor2"));
+        assert_eq!("type_", field_name("type"));
+        assert_eq!("START_ON", const_name("Start on"));
+    }
+
+    #[test]
+    fn generate() {
+        let fgd = Fgd::parse(FGD).unwrap();
+        let code = codegen(&fgd, &[]);
+        assert_eq!(code, codegen(&fgd, &["env_sparkler", "func_detail", "missing"]));
+
+        for line in [
+            "/// Makes sparks. Very shiny.",
+            "pub struct EnvSparkler<S> {",
+            "    /// Max Delay `(MaxDelay)` Longest delay between sparks.",
+            "    pub max_delay: f64,",
+            "    /// - 2: Large",
+            "    pub magnitude: i32,",
+            "    pub sound: S,",
+            "    pub type_: bool,",
+            "    pub direction: Angles,",
+            "    pub const START_ON: i32 = 1;",
+            "            point_entity: PointEntity { flags: 1, ..Default::default() },",
+            "            max_delay: 2.5,",
+            "            sound: \"DoSpark\".into(),",
+            "            direction: Angles { pitch: -90.0, yaw: 0.0, roll: 0.0 },",
+            "        self.point_entity.classname = \"env_sparkler\".into();",
+            "        props.push(Property::new(\"type\", (self.type_ as i32).to_string()));",
+            "        props_push_string(props, \"sound\", self.sound);",
+            "                \"maxdelay\" => this.max_delay = value.parse().map_err(|_| \"Invalid MaxDelay\")?,",
+        ] {
+            assert!(code.lines().any(|code_line| code_line == line), "missing: {line}\n{code}");
+        }
+        // targetname is part of point_entity
+        assert!(!code.contains("pub targetname"));
+        assert!(!code.contains("FuncDetail"));
+        assert_eq!(code, include_str!("codegen/generated.rs"), "regenerate codegen/generated.rs");
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut sparkler = EnvSparkler::<StrType> {
+            max_delay: 0.5,
+            magnitude: 2,
+            type_: true,
+            direction: Angles { pitch: 0.0, yaw: 90.0, roll: 0.0 },
+            ..Default::default()
+        };
+        sparkler.point_entity.classname = "env_sparkler".into();
+        sparkler.point_entity.origin = Vector3::new(64.0, -32.0, 8.0);
+        assert_eq!(EnvSparkler::<StrType>::START_ON, sparkler.point_entity.flags);
+        sparkler.point_entity.flags |= EnvSparkler::<StrType>::SILENT;
+        let entity: Entity<StrType> = sparkler.clone().into_lower();
+        assert_eq!(Some(&"1".into()), entity.get("type"));
+        assert_eq!(Some(&"DoSpark".into()), entity.get("sound"));
+        assert_eq!(Ok(sparkler), EnvSparkler::try_from(&entity));
+
+        let mut other = entity.clone();
+        other.set("classname", "env_spark");
+        assert!(EnvSparkler::try_from(&other).is_err());
+        other.set("classname", "env_sparkler").set("MaxDelay", "soon");
+        assert_eq!(Err("Invalid MaxDelay"), EnvSparkler::try_from(&other));
+    }
+}
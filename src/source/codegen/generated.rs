@@ -0,0 +1,98 @@
+//! Generated by `fgd_codegen`.
+
+#![allow(unused_imports)]
+
+use vmf_parser_nom::ast::Property;
+
+use crate::map::{Angles, Entity};
+use crate::prelude::Vector3;
+use crate::source::entity::{parse_angles, parse_vector3, props_push_string};
+use crate::source::PointEntity;
+use crate::vmf::ToLower;
+
+/// Makes sparks. Very shiny.
+/// `env_sparkler`
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnvSparkler<S> {
+    /// Common Fields. Including classname, origin, angles, flags, etc.
+    pub point_entity: PointEntity<S>,
+    /// Max Delay `(MaxDelay)` Longest delay between sparks.
+    pub max_delay: f64,
+    /// Magnitude `(Magnitude)`
+    ///
+    /// - 1: Small
+    /// - 2: Large
+    pub magnitude: i32,
+    /// Sound `(sound)`
+    pub sound: S,
+    /// Type `(type)`
+    pub type_: bool,
+    /// Direction `(direction)`
+    pub direction: Angles,
+}
+
+impl<S> EnvSparkler<S> {
+    /// Flag. Start on
+    pub const START_ON: i32 = 1;
+    /// Flag. Silent
+    pub const SILENT: i32 = 4;
+}
+
+impl<S: Default + From<&'static str>> Default for EnvSparkler<S> {
+    fn default() -> Self {
+        Self {
+            point_entity: PointEntity { flags: 1, ..Default::default() },
+            max_delay: 2.5,
+            magnitude: 1,
+            sound: "DoSpark".into(),
+            type_: false,
+            direction: Angles { pitch: -90.0, yaw: 0.0, roll: 0.0 },
+        }
+    }
+}
+
+impl<'a, S> ToLower<Entity<S>> for EnvSparkler<S>
+where
+    S: AsRef<str> + Clone + From<&'a str> + From<String>,
+{
+    fn into_lower(mut self) -> Entity<S> {
+        self.point_entity.classname = "env_sparkler".into();
+        let mut entity = self.point_entity.into_lower();
+        let props = &mut entity.props;
+
+        props.push(Property::new("MaxDelay", self.max_delay.to_string()));
+        props.push(Property::new("Magnitude", self.magnitude.to_string()));
+        props_push_string(props, "sound", self.sound);
+        props.push(Property::new("type", (self.type_ as i32).to_string()));
+        props.push(Property::new("direction", self.direction.to_string()));
+
+        entity
+    }
+}
+
+impl<'b, S> TryFrom<&'b Entity<S>> for EnvSparkler<S>
+where
+    S: AsRef<str> + Clone + Default + From<&'static str> + From<String>,
+{
+    type Error = &'static str;
+
+    fn try_from(entity: &'b Entity<S>) -> Result<Self, Self::Error> {
+        let point_entity = PointEntity::try_from(entity)?;
+        if !point_entity.classname.as_ref().eq_ignore_ascii_case("env_sparkler") {
+            return Err("Not a env_sparkler");
+        }
+        let mut this = Self { point_entity, ..Self::default() };
+        for prop in entity.props.iter() {
+            let value = prop.value.as_ref().trim();
+            match prop.key.as_ref().to_ascii_lowercase().as_str() {
+                "maxdelay" => this.max_delay = value.parse().map_err(|_| "Invalid MaxDelay")?,
+                "magnitude" => this.magnitude = value.parse().map_err(|_| "Invalid Magnitude")?,
+                "sound" => this.sound = prop.value.clone(),
+                "type" => this.type_ = value != "0",
+                "direction" => this.direction = parse_angles(value).ok_or("Invalid direction")?,
+                _ => (),
+            }
+        }
+        Ok(this)
+    }
+}
@@ -6,17 +6,16 @@ use rgb::RGB8;
 use vmf_parser_nom::ast::Property;
 
 use crate::{
-    map::Entity,
-    source::{Angles, ColorBrightness},
+    map::{parse_numbers, Entity},
     prelude::Vector3,
+    source::{Angles, ColorBrightness},
     vmf::ToLower,
 };
 
-// shell. format keyvalues on valve dev wiki to doc comments:
-// xclip -o -sel clip | sed ':a;N;$!ba;s/\n    //g' | sed '/^$/d' | sed ':a;N;$!ba;s/\n)/)/g' | sed -e 's_^_/// _' -e 's/<.*>//g' -e 's/ (in all games since)//g' -e 's/(/`(/' -e 's/)/)`/'
+// generate more entities from a .fgd with the `fgd_codegen` binary, see [`super::codegen`]
 
 /// Push strtype if not empty.
-fn props_push_string<'a, S>(props: &mut Vec<Property<S, S>>, key: &'a str, value: S)
+pub(super) fn props_push_string<'a, S>(props: &mut Vec<Property<S, S>>, key: &'a str, value: S)
 where
     S: AsRef<str> + From<&'a str>,
 {
@@ -26,7 +25,7 @@ where
 }
 
 /// Push value if not default and convert to string.
-fn props_push_value<'a, S, T>(props: &mut Vec<Property<S, S>>, key: &'a str, value: T)
+pub(super) fn props_push_value<'a, S, T>(props: &mut Vec<Property<S, S>>, key: &'a str, value: T)
 where
     S: AsRef<str> + From<&'a str> + From<String>,
     T: Default + Display + PartialEq,
@@ -36,6 +35,65 @@ where
     }
 }
 
+/// Lower `point_entity` with its classname set to `classname`.
+pub(super) fn lower_as<'a, S>(mut point_entity: PointEntity<S>, classname: &'a str) -> Entity<S>
+where
+    S: AsRef<str> + Clone + From<&'a str> + From<String>,
+{
+    point_entity.classname = classname.into();
+    point_entity.into_lower()
+}
+
+/// "r g b", unlike [`RGB8`]'s `Display`.
+pub(super) fn rgb_string(color: RGB8) -> String {
+    format!("{} {} {}", color.r, color.g, color.b)
+}
+
+/// Parse `"x y z"`.
+pub(super) fn parse_vector3(value: &str) -> Option<Vector3<f64>> {
+    parse_numbers(value).map(Vector3::from)
+}
+
+/// Parse `"pitch yaw roll"`.
+pub(super) fn parse_angles(value: &str) -> Option<Angles> {
+    let [pitch, yaw, roll] = parse_numbers(value)?;
+    Some(Angles { pitch, yaw, roll })
+}
+
+/// Parse `"r g b"`.
+pub(super) fn parse_rgb(value: &str) -> Option<RGB8> {
+    let Vector3 { x: r, y: g, z: b } = parse_vector3(value)?;
+    let [r, g, b] = [r, g, b].map(|c| (0.0..=255.0).contains(&c).then_some(c as u8));
+    Some(RGB8::new(r?, g?, b?))
+}
+
+/// Parse `"0"`/`"1"`, or `"false"`/`"true"`.
+pub(super) fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => value.parse::<i32>().ok().map(|n| n != 0),
+    }
+}
+
+/// Parse a HDR override color. `"-1 -1 -1 1"` means same as LDR.
+fn parse_color_hdr(value: &str) -> Result<Option<ColorBrightness>, &'static str> {
+    match value.starts_with("-1") {
+        true => Ok(None),
+        false => value.parse().map(Some),
+    }
+}
+
+/// Check the classname of a parsed [`PointEntity`].
+fn check_classname<S: AsRef<str>>(
+    point_entity: &PointEntity<S>, classname: &str,
+) -> Result<(), &'static str> {
+    match point_entity.classname.as_ref().eq_ignore_ascii_case(classname) {
+        true => Ok(()),
+        false => Err("Wrong classname"),
+    }
+}
+
 // TODO:DOCS:
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct PointEntity<S> {
@@ -68,6 +126,10 @@ pub struct PointEntity<S> {
     pub lag_compensate: bool,
     /// Is Automatic-Aim Target `(is_autoaim_target)` If set to 1, this entity will slow down aiming movement for consoles and joystick controllers when the entity is under the crosshairs.
     pub is_autoaim_target: bool,
+    /// Outputs fired to other entities. Ex: `OnTrigger`, `OnMapSpawn`.
+    ///
+    /// See also: <https://developer.valvesoftware.com/wiki/Inputs_and_Outputs>
+    pub outputs: Vec<Output<S>>,
 }
 
 // from str and String for cow,
@@ -88,10 +150,115 @@ where
         props_push_value(props, "effects", self.effects);
         props_push_string(props, "vscripts", self.vscripts);
         props_push_string(props, "thinkfunction", self.vthink_fn);
-        props_push_value(props, "LagCompensate", "1"); // TODO: alloc
-        props_push_value(props, "is_autoaim_target", "1");
+        props_push_value(props, "LagCompensate", self.lag_compensate as i32);
+        props_push_value(props, "is_autoaim_target", self.is_autoaim_target as i32);
+
+        let mut entity = Entity::new(props_v);
+        entity.connections = self.outputs.into_iter().map(|output| output.into_lower()).collect();
+        entity
+    }
+}
+
+impl<'b, S> TryFrom<&'b Entity<S>> for PointEntity<S>
+where
+    S: AsRef<str> + Clone + Default + From<String>,
+{
+    type Error = &'static str;
+
+    /// The common fields of any entity. Other keys are ignored.
+    fn try_from(entity: &'b Entity<S>) -> Result<Self, Self::Error> {
+        let mut this = Self::default();
+        for prop in entity.props.iter() {
+            let value = prop.value.as_ref().trim();
+            let int = |error| value.parse::<i32>().map_err(|_| error);
+            match prop.key.as_ref().to_ascii_lowercase().as_str() {
+                "targetname" => this.name = prop.value.clone(),
+                "parentname" => this.parent = prop.value.clone(),
+                "origin" => this.origin = parse_vector3(value).ok_or("Invalid origin")?,
+                "angles" => this.angles = parse_angles(value).ok_or("Invalid angles")?,
+                "classname" => this.classname = prop.value.clone(),
+                "spawnflags" => this.flags = int("Invalid spawnflags")?,
+                "effects" => this.effects = int("Invalid effects")?,
+                "vscripts" => this.vscripts = prop.value.clone(),
+                "thinkfunction" => this.vthink_fn = prop.value.clone(),
+                "lagcompensate" => this.lag_compensate = int("Invalid LagCompensate")? != 0,
+                "is_autoaim_target" => {
+                    this.is_autoaim_target = int("Invalid is_autoaim_target")? != 0;
+                }
+                _ => (),
+            }
+        }
+        if this.classname.as_ref().is_empty() {
+            return Err("Missing classname");
+        }
+        this.outputs = entity.connections.iter().map(Output::try_from).collect::<Result<_, _>>()?;
+        Ok(this)
+    }
+}
+
+/// An output of an entity, fires `input` on `target` when `output` happens.
+/// Ex: `OnTrigger` -> `door`, `Open`.
+///
+/// See also: <https://developer.valvesoftware.com/wiki/Inputs_and_Outputs>
+#[derive(Clone, Debug, PartialEq)]
+pub struct Output<S> {
+    /// The output of this entity. Ex: `OnTrigger`.
+    pub output: S,
+    /// Name of the entity to send the input to. Ex: `door` or `!activator`.
+    pub target: S,
+    /// The input to fire on the target. Ex: `Open`.
+    pub input: S,
+    /// Parameter of the input, if any.
+    pub param: S,
+    /// Seconds to wait before firing.
+    pub delay: f64,
+    /// Number of times this output fires, -1 for infinite.
+    pub times_to_fire: i32,
+}
 
-        Entity::new(props_v)
+impl<S: Default> Output<S> {
+    /// Fire `input` on `target` every time `output` happens, without delay.
+    pub fn new(output: S, target: S, input: S) -> Self {
+        Self { output, target, input, param: S::default(), delay: 0.0, times_to_fire: -1 }
+    }
+}
+
+impl<'a, S> ToLower<Property<S, S>> for Output<S>
+where
+    S: AsRef<str> + Clone + From<&'a str> + From<String>,
+{
+    fn into_lower(self) -> Property<S, S> {
+        let Output { output, target, input, param, delay, times_to_fire } = self;
+        let (target, input, param) = (target.as_ref(), input.as_ref(), param.as_ref());
+        Property::new(output, format!("{target},{input},{param},{delay},{times_to_fire}"))
+    }
+}
+
+impl<'b, S> TryFrom<&'b Property<S, S>> for Output<S>
+where
+    S: AsRef<str> + Clone + From<String>,
+{
+    type Error = &'static str;
+
+    /// Parse a connection. Separated by commas, or `\x1b` in newer games.
+    fn try_from(prop: &'b Property<S, S>) -> Result<Self, Self::Error> {
+        let value = prop.value.as_ref();
+        let separator = if value.contains('\x1b') { '\x1b' } else { ',' };
+        let parts: Vec<_> = value.splitn(5, separator).collect();
+        let [target, input, param, delay, times_to_fire] = parts[..] else {
+            return Err("Invalid output");
+        };
+        Ok(Self {
+            output: prop.key.clone(),
+            target: target.to_string().into(),
+            input: input.to_string().into(),
+            param: param.to_string().into(),
+            delay: delay.trim().parse().map_err(|_| "Invalid output delay")?,
+            times_to_fire: times_to_fire
+                .trim()
+                .parse()
+                .map_err(|_| "Invalid output times to fire")?,
+        })
     }
 }
 
@@ -162,7 +329,7 @@ where
         let direct_color_hdr = self.direct_color_hdr.unwrap_or(self.direct_color.clone());
         let amb_color_hdr = self.amb_color_hdr.unwrap_or(self.amb_color.clone());
 
-        let mut entity = self.point_entity.into_lower();
+        let mut entity = lower_as(self.point_entity, "light_environment");
         let props = &mut entity.props;
         props.reserve_exact(8);
 
@@ -179,6 +346,45 @@ where
     }
 }
 
+impl<'b, S> TryFrom<&'b Entity<S>> for LightEnviroment<S>
+where
+    S: AsRef<str> + Clone + Default + From<String>,
+{
+    type Error = &'static str;
+
+    fn try_from(entity: &'b Entity<S>) -> Result<Self, Self::Error> {
+        let point_entity = PointEntity::try_from(entity)?;
+        check_classname(&point_entity, "light_environment")?;
+        let mut this = Self { point_entity, ..Self::default() };
+        for prop in entity.props.iter() {
+            let value = prop.value.as_ref().trim();
+            let float = |error| value.parse::<f64>().map_err(|_| error);
+            match prop.key.as_ref().to_ascii_lowercase().as_str() {
+                "pitch" => this.pitch = Some(float("Invalid pitch")?),
+                "_light" => this.direct_color = value.parse()?,
+                "_ambient" => this.amb_color = value.parse()?,
+                "_lighthdr" => this.direct_color_hdr = parse_color_hdr(value)?,
+                "_lightscalehdr" => this.direct_hdr_scale = float("Invalid _lightscaleHDR")?,
+                "_ambienthdr" => this.amb_color_hdr = parse_color_hdr(value)?,
+                "_ambientscalehdr" => this.amb_hdr_scale = float("Invalid _AmbientScaleHDR")?,
+                "sunspreadangle" => this.sun_spread_angle = float("Invalid SunSpreadAngle")?,
+                _ => (),
+            }
+        }
+        // defaults written by lowering
+        if this.pitch == Some(-this.point_entity.angles.pitch) {
+            this.pitch = None;
+        }
+        if this.direct_color_hdr.as_ref() == Some(&this.direct_color) {
+            this.direct_color_hdr = None;
+        }
+        if this.amb_color_hdr.as_ref() == Some(&this.amb_color) {
+            this.amb_color_hdr = None;
+        }
+        Ok(this)
+    }
+}
+
 // TODO:DOCS: todo include c fields
 // TODO: is angles needed? not on wiki be is in hammer and there is dir/angle input IO
 /// See also: <https://developer.valvesoftware.com/wiki/Shadow_control>
@@ -215,13 +421,13 @@ where
     S: AsRef<str> + Clone + From<&'a str> + From<String>,
 {
     fn into_lower(self) -> Entity<S> {
-        let mut entity = self.point_entity.into_lower();
+        let mut entity = lower_as(self.point_entity, "shadow_control");
         let props = &mut entity.props;
         props.reserve_exact(4);
 
-        props.push(Property::new("color", self.shadow_color.to_string()));
+        props.push(Property::new("color", rgb_string(self.shadow_color)));
         props.push(Property::new("distance", self.max_dist.to_string()));
-        props.push(Property::new("disableallshadows", self.disabled.to_string()));
+        props.push(Property::new("disableallshadows", (self.disabled as i32).to_string()));
         props.push(Property::new(
             "enableshadowsfromlocallights",
             (self.local_light_shadows as i32).to_string(),
@@ -231,6 +437,35 @@ where
     }
 }
 
+impl<'b, S> TryFrom<&'b Entity<S>> for ShadowControl<S>
+where
+    S: AsRef<str> + Clone + Default + From<String>,
+{
+    type Error = &'static str;
+
+    fn try_from(entity: &'b Entity<S>) -> Result<Self, Self::Error> {
+        let point_entity = PointEntity::try_from(entity)?;
+        check_classname(&point_entity, "shadow_control")?;
+        let mut this = Self { point_entity, ..Self::default() };
+        for prop in entity.props.iter() {
+            let value = prop.value.as_ref().trim();
+            match prop.key.as_ref().to_ascii_lowercase().as_str() {
+                "color" => this.shadow_color = parse_rgb(value).ok_or("Invalid color")?,
+                "distance" => this.max_dist = value.parse().map_err(|_| "Invalid distance")?,
+                "disableallshadows" => {
+                    this.disabled = parse_bool(value).ok_or("Invalid disableallshadows")?;
+                }
+                "enableshadowsfromlocallights" => {
+                    this.local_light_shadows =
+                        parse_bool(value).ok_or("Invalid enableshadowsfromlocallights")?;
+                }
+                _ => (),
+            }
+        }
+        Ok(this)
+    }
+}
+
 // TODO:DOCS:
 /// See also: <https://developer.valvesoftware.com/wiki/Env_fog_controller>
 #[derive(Clone, Debug, PartialEq)]
@@ -301,7 +536,7 @@ where
 {
     fn into_lower(mut self) -> Entity<S> {
         self.point_entity.flags = self.is_master as i32;
-        let mut entity = self.point_entity.into_lower();
+        let mut entity = lower_as(self.point_entity, "env_fog_controller");
         let props = &mut entity.props;
         props.reserve_exact(11);
 
@@ -310,8 +545,8 @@ where
         props.push(Property::new("fogend", self.end.to_string()));
         props.push(Property::new("fogmaxdensity", self.max_density.to_string()));
         props.push(Property::new("farz", self.far_z.to_string()));
-        props.push(Property::new("fogcolor", self.primary_color.to_string()));
-        props.push(Property::new("fogcolor2", self.secondary_color.to_string()));
+        props.push(Property::new("fogcolor", rgb_string(self.primary_color)));
+        props.push(Property::new("fogcolor2", rgb_string(self.secondary_color)));
         props.push(Property::new("fogblend", (self.use_blend as i32).to_string()));
         props.push(Property::new("fogdir", self.primary_blend_dir.to_string()));
         props.push(Property::new("use_angles", (self.use_angles_for_dir as i32).to_string()));
@@ -321,3 +556,112 @@ where
         entity
     }
 }
+
+impl<'b, S> TryFrom<&'b Entity<S>> for EnvFogController<S>
+where
+    S: AsRef<str> + Clone + Default + From<String>,
+{
+    type Error = &'static str;
+
+    fn try_from(entity: &'b Entity<S>) -> Result<Self, Self::Error> {
+        let mut point_entity = PointEntity::try_from(entity)?;
+        check_classname(&point_entity, "env_fog_controller")?;
+        let is_master = point_entity.flags & 1 != 0;
+        point_entity.flags &= !1;
+        let mut this = Self { point_entity, is_master, ..Self::default() };
+        for prop in entity.props.iter() {
+            let value = prop.value.as_ref().trim();
+            let float = |error| value.parse::<f64>().map_err(|_| error);
+            let bool = |error| parse_bool(value).ok_or(error);
+            match prop.key.as_ref().to_ascii_lowercase().as_str() {
+                "fogenable" => this.start_enabled = bool("Invalid fogenable")?,
+                "fogstart" => this.start = float("Invalid fogstart")?,
+                "fogend" => this.end = float("Invalid fogend")?,
+                "fogmaxdensity" => this.max_density = float("Invalid fogmaxdensity")?,
+                "farz" => this.far_z = float("Invalid farz")? as i32,
+                "fogcolor" => this.primary_color = parse_rgb(value).ok_or("Invalid fogcolor")?,
+                "fogcolor2" => {
+                    this.secondary_color = parse_rgb(value).ok_or("Invalid fogcolor2")?;
+                }
+                "fogblend" => this.use_blend = bool("Invalid fogblend")?,
+                "fogdir" => {
+                    this.primary_blend_dir = parse_vector3(value).ok_or("Invalid fogdir")?;
+                }
+                "use_angles" => this.use_angles_for_dir = bool("Invalid use_angles")?,
+                "foglerptime" => this.interp_time = float("Invalid foglerptime")?,
+                "hdrcolorscale" => this.hdr_color_scale = float("Invalid HDRColorScale")?,
+                _ => (),
+            }
+        }
+        Ok(this)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StrType;
+
+    #[test]
+    fn round_trip() {
+        let mut light = LightEnviroment::<StrType> {
+            direct_color: ColorBrightness::new(255, 240, 200, 400),
+            amb_color_hdr: Some(ColorBrightness::new(100, 120, 160, 50)),
+            sun_spread_angle: 90.0,
+            ..Default::default()
+        };
+        light.point_entity.angles = Angles { pitch: -30.0, yaw: 135.0, roll: 0.0 };
+        light.point_entity.classname = "light_environment".into();
+        let entity = light.clone().into_lower();
+        assert_eq!(Some("light_environment"), entity.classname());
+        assert_eq!(Ok(light), LightEnviroment::try_from(&entity));
+        assert!(ShadowControl::try_from(&entity).is_err());
+
+        let mut shadows = ShadowControl::<StrType> {
+            shadow_color: RGB8::new(50, 60, 70),
+            disabled: true,
+            ..Default::default()
+        };
+        shadows.point_entity.classname = "shadow_control".into();
+        let entity = shadows.clone().into_lower();
+        assert_eq!(Some(&"50 60 70".into()), entity.get("color"));
+        assert_eq!(Ok(shadows), ShadowControl::try_from(&entity));
+
+        let mut fog = EnvFogController::<StrType> {
+            is_master: true,
+            primary_color: RGB8::new(120, 110, 100),
+            primary_blend_dir: Vector3::new(-1.0, -1.0, 1.0),
+            use_blend: true,
+            ..Default::default()
+        };
+        fog.point_entity.name = "fog".into();
+        fog.point_entity.classname = "env_fog_controller".into();
+        let entity = fog.clone().into_lower();
+        assert_eq!(Some(&"1".into()), entity.get("spawnflags"));
+        assert_eq!(Ok(fog), EnvFogController::try_from(&entity));
+    }
+
+    #[test]
+    fn parse() {
+        let entity: Entity<StrType> = Entity::new(vec![
+            Property::new("classname", "shadow_control"),
+            Property::new("angles", "0 0 0"),
+            Property::new("color", "128 128 128"),
+            Property::new("disableallshadows", "false"),
+        ]);
+        let shadows = ShadowControl::try_from(&entity).unwrap();
+        assert_eq!(RGB8::new(128, 128, 128), shadows.shadow_color);
+        assert!(!shadows.disabled);
+
+        let mut entity: Entity<StrType> = Entity::new(vec![
+            Property::new("classname", "light_environment"),
+            Property::new("_lightHDR", "-1 -1 -1 1"),
+        ]);
+        let light = LightEnviroment::try_from(&entity).unwrap();
+        assert_eq!(None, light.direct_color_hdr);
+        entity.set("_light", "300 0 0 200");
+        assert!(LightEnviroment::try_from(&entity).is_err());
+        entity.remove("classname");
+        assert_eq!(Err("Missing classname"), PointEntity::try_from(&entity));
+    }
+}
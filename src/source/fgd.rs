@@ -0,0 +1,815 @@
+//! Parsing `.fgd` files, the entity definitions of a game, and validating [`Entity`]s against them.
+//!
+//! See also: <https://developer.valvesoftware.com/wiki/FGD>
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io;
+use std::path::Path;
+
+use super::text::{self, Cursor, Include, Scanner, Token as _};
+use crate::map::{Entity, Map};
+
+/// The kind of class, from `@PointClass`, `@SolidClass`, etc.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FgdClassKind {
+    /// `@BaseClass` Only inherited from by other classes, can't be placed.
+    Base,
+    /// `@PointClass` An entity at a point. Also `@NPCClass`, `@KeyFrameClass`, etc.
+    Point,
+    /// `@SolidClass` An entity made of brushes.
+    Solid,
+    /// `@FilterClass` A point entity that filters other entities.
+    Filter,
+    /// `@NPCClass` A point entity that is an NPC.
+    Npc,
+    /// Any other class. Ex: `@KeyFrameClass`, `@MoveClass`.
+    Other(String),
+}
+
+impl FgdClassKind {
+    fn from_directive(directive: &str) -> Option<Self> {
+        Some(match directive.to_ascii_lowercase().as_str() {
+            "baseclass" => Self::Base,
+            "pointclass" => Self::Point,
+            "solidclass" => Self::Solid,
+            "filterclass" => Self::Filter,
+            "npcclass" => Self::Npc,
+            other if other.ends_with("class") => Self::Other(directive.to_string()),
+            _ => return None,
+        })
+    }
+
+    /// Is placed at a point and needs an `origin`.
+    pub const fn is_point(&self) -> bool {
+        !matches!(self, Self::Base | Self::Solid)
+    }
+}
+
+/// The type of a keyvalue. Ex: `integer` in `health(integer)`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FgdValueType {
+    String,
+    Integer,
+    Float,
+    Boolean,
+    /// One of the [`FgdKey::choices`].
+    Choices,
+    /// A bitfield of the [`FgdKey::choices`].
+    Flags,
+    /// `r g b` 0 to 255, optionally with a 4th brightness.
+    Color255,
+    /// `r g b` 0 to 1, optionally with a 4th brightness.
+    Color1,
+    /// `x y z`. Also `origin`.
+    Vector,
+    /// `pitch yaw roll`
+    Angle,
+    /// Name of another entity. `target_destination`, `target_source`, etc.
+    Target,
+    /// Path to a file. `studio`, `sound`, `material`, `sprite`, `decal`, etc.
+    File,
+    /// Any other type, treated as a string.
+    Other(String),
+}
+
+impl FgdValueType {
+    fn parse(name: &str) -> Self {
+        match name.to_ascii_lowercase().as_str() {
+            "string" => Self::String,
+            "integer" => Self::Integer,
+            "float" => Self::Float,
+            "boolean" => Self::Boolean,
+            "choices" => Self::Choices,
+            "flags" => Self::Flags,
+            "color255" => Self::Color255,
+            "color1" => Self::Color1,
+            "vector" | "origin" | "vecline" => Self::Vector,
+            "angle" => Self::Angle,
+            "target_source"
+            | "target_destination"
+            | "target_name_or_class"
+            | "filterclass"
+            | "npcclass"
+            | "pointentityclass" => Self::Target,
+            "studio" | "sound" | "material" | "sprite" | "decal" | "scene" => Self::File,
+            _ => Self::Other(name.to_string()),
+        }
+    }
+
+    /// Is `value` a valid value of this type. `Choices` and `Flags` only check
+    /// that it could be one, see [`FgdKey::check()`].
+    pub fn is_valid(&self, value: &str) -> bool {
+        let value = value.trim();
+        let numbers = |min: usize, max: usize| {
+            let parts: Vec<_> = value.split_whitespace().collect();
+            (min..=max).contains(&parts.len()) && parts.iter().all(|n| n.parse::<f64>().is_ok())
+        };
+        match self {
+            Self::Integer | Self::Flags => value.parse::<i64>().is_ok(),
+            Self::Float => value.parse::<f64>().is_ok(),
+            Self::Boolean => matches!(value, "0" | "1"),
+            Self::Color255 => {
+                let rgb: Vec<_> = value.split_whitespace().take(3).collect();
+                // `-1 -1 -1 1` is the default of `_lightHDR`, meaning the same as `_light`
+                let is_hdr_default = rgb.iter().all(|n| n.parse::<f64>() == Ok(-1.0));
+                numbers(3, 4) && (rgb.iter().all(|n| n.parse::<u8>().is_ok()) || is_hdr_default)
+            }
+            Self::Color1 => numbers(3, 4),
+            Self::Vector | Self::Angle => numbers(3, 3),
+            _ => true,
+        }
+    }
+}
+
+/// A choice or flag of a `choices` or `flags` keyvalue.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FgdChoice {
+    /// The value, or the bit for flags. Ex: `"0"`, `"512"`.
+    pub value: String,
+    pub display_name: String,
+    /// Only for flags, if it is set by default.
+    pub default: bool,
+}
+
+/// A keyvalue of a class. Ex: `health(integer) : "Health" : 100 : "Hit points."`
+#[derive(Clone, Debug, PartialEq)]
+pub struct FgdKey {
+    pub name: String,
+    pub value_type: FgdValueType,
+    pub display_name: String,
+    pub default: Option<String>,
+    pub description: String,
+    /// Marked `readonly` or `report`.
+    pub read_only: bool,
+    /// Choices of `choices` or flags of `flags` keys.
+    pub choices: Vec<FgdChoice>,
+}
+
+impl FgdKey {
+    /// Check a `value` of this key. See [`EntityError`].
+    pub fn check(&self, classname: &str, value: &str) -> Result<(), EntityError> {
+        let error = |kind: fn(_, _, _) -> EntityError| {
+            Err(kind(classname.to_string(), self.name.clone(), value.to_string()))
+        };
+        if !self.value_type.is_valid(value) {
+            return error(EntityError::WrongType);
+        }
+        match self.value_type {
+            FgdValueType::Choices if !self.choices.is_empty() => {
+                let value = value.trim();
+                let is_choice = |choice: &FgdChoice| {
+                    choice.value == value
+                        || matches!((choice.value.parse::<f64>(), value.parse::<f64>()),
+                            (Ok(a), Ok(b)) if a == b)
+                };
+                if !self.choices.iter().any(is_choice) {
+                    return error(EntityError::BadChoice);
+                }
+            }
+            FgdValueType::Flags => {
+                let known = self.choices.iter().filter_map(|flag| flag.value.parse::<i64>().ok());
+                let known = known.fold(0, |bits, bit| bits | bit);
+                if value.trim().parse::<i64>().unwrap_or(0) & !known != 0 {
+                    return error(EntityError::BadChoice);
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+}
+
+/// An input or output of a class. Ex: `input Kill(void) : "Removes this entity."`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FgdIo {
+    pub name: String,
+    /// Type of the parameter. Ex: `void`, `integer`, `string`.
+    pub param_type: String,
+    pub description: String,
+}
+
+/// A class of entity. Ex: `@PointClass base(Targetname) = info_target : "..." [ ... ]`
+#[derive(Clone, Debug, PartialEq)]
+pub struct FgdClass {
+    pub kind: FgdClassKind,
+    pub name: String,
+    pub description: String,
+    /// Classes inherited from with `base()`.
+    pub bases: Vec<String>,
+    /// Other helpers in the header, their name and raw arguments. Ex: `("studio", "\"x.mdl\"")`
+    pub helpers: Vec<(String, String)>,
+    pub keys: Vec<FgdKey>,
+    pub inputs: Vec<FgdIo>,
+    pub outputs: Vec<FgdIo>,
+}
+
+/// Entity definitions of a game from one or more `.fgd` files.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Fgd {
+    pub classes: Vec<FgdClass>,
+    /// Files from `@include`. Already loaded if loaded with [`Fgd::load()`].
+    pub includes: Vec<String>,
+    /// Class name to index in `classes`. Case sensitive, ex: `Light` and `light` in `base.fgd`.
+    index: HashMap<String, usize>,
+}
+
+impl Fgd {
+    /// Load a `.fgd` file and its `@include`s, relative to its directory.
+    /// Ex: `tf.fgd` includes `base.fgd`.
+    ///
+    /// # Errors
+    ///
+    /// * Returns `InvalidData` if a file includes itself, directly or through other files.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        text::load(path.as_ref())
+    }
+
+    /// Parse the text of a `.fgd` file. `@include`s are not loaded.
+    pub fn parse(input: &str) -> io::Result<Self> {
+        let mut parser = Parser::new(tokenize(input)?);
+        let mut fgd = Self::default();
+        while let Some(token) = parser.next() {
+            let directive = match token {
+                Token::Punct('@', _) => parser.word()?,
+                token => return Err(parser.error(&token, "expected @")),
+            };
+            if let Some(kind) = FgdClassKind::from_directive(&directive) {
+                fgd.add_class(parser.class(kind)?);
+                continue;
+            }
+            match directive.to_ascii_lowercase().as_str() {
+                "include" => fgd.includes.push(parser.string()?),
+                // @mapsize(-16384, 16384), @MaterialExclusion [ ... ], @AutoVisGroup = "" [ ... ]
+                _ => parser.skip_directive(),
+            }
+        }
+        Ok(fgd)
+    }
+
+    /// Add the classes of `other`, replacing classes of the same name.
+    pub fn extend(&mut self, other: Self) {
+        self.includes.extend(other.includes);
+        for class in other.classes {
+            self.add_class(class);
+        }
+    }
+
+    /// Add a class, replacing a class of the same name.
+    pub fn add_class(&mut self, class: FgdClass) {
+        match self.index.get(&class.name) {
+            Some(&i) => self.classes[i] = class,
+            None => {
+                self.index.insert(class.name.clone(), self.classes.len());
+                self.classes.push(class);
+            }
+        }
+    }
+
+    /// Get a class by name. Falls back to ignoring case if there isn't an exact match.
+    pub fn class(&self, name: &str) -> Option<&FgdClass> {
+        match self.index.get(name) {
+            Some(&i) => Some(&self.classes[i]),
+            None => self.classes.iter().find(|class| class.name.eq_ignore_ascii_case(name)),
+        }
+    }
+
+    /// All keys of a class including inherited ones. Keys of the class override its bases.
+    pub fn keys<'b>(&'b self, class: &'b FgdClass) -> Vec<&'b FgdKey> {
+        let mut keys = Vec::new();
+        self.inherited(class, &mut Vec::new(), &mut |class| keys.extend(class.keys.iter()));
+        // later (more derived) keys override
+        let mut seen = Vec::new();
+        let mut unique: Vec<_> = keys
+            .into_iter()
+            .rev()
+            .filter(|key| {
+                let name = key.name.to_ascii_lowercase();
+                let is_new = !seen.contains(&name);
+                seen.push(name);
+                is_new
+            })
+            .collect();
+        unique.reverse();
+        unique
+    }
+
+    /// All inputs of a class including inherited ones.
+    pub fn inputs<'b>(&'b self, class: &'b FgdClass) -> Vec<&'b FgdIo> {
+        let mut inputs = Vec::new();
+        self.inherited(class, &mut Vec::new(), &mut |class| inputs.extend(class.inputs.iter()));
+        inputs
+    }
+
+    /// All outputs of a class including inherited ones.
+    pub fn outputs<'b>(&'b self, class: &'b FgdClass) -> Vec<&'b FgdIo> {
+        let mut outputs = Vec::new();
+        self.inherited(class, &mut Vec::new(), &mut |class| outputs.extend(class.outputs.iter()));
+        outputs
+    }
+
+    /// Call `f` on each base of `class` depth first, then `class`. Skips cycles.
+    fn inherited<'b>(
+        &'b self, class: &'b FgdClass, visited: &mut Vec<&'b str>, f: &mut impl FnMut(&'b FgdClass),
+    ) {
+        if visited.contains(&class.name.as_str()) {
+            return;
+        }
+        visited.push(&class.name);
+        for base in class.bases.iter().filter_map(|base| self.class(base)) {
+            self.inherited(base, visited, f);
+        }
+        f(class);
+    }
+
+    /// Check an entity against its class. See [`EntityError`].
+    pub fn validate_entity<S: AsRef<str>>(&self, entity: &Entity<S>) -> Vec<EntityError> {
+        let get = |key: &str| {
+            entity.props.iter().find(|prop| prop.key.as_ref().eq_ignore_ascii_case(key))
+        };
+        let classname = match get("classname") {
+            Some(prop) => prop.value.as_ref(),
+            None => return vec![EntityError::MissingClassname],
+        };
+        let class = match self.class(classname) {
+            Some(class) if class.kind != FgdClassKind::Base => class,
+            _ => return vec![EntityError::UnknownClass(classname.to_string())],
+        };
+
+        let mut errors = Vec::new();
+        if class.kind.is_point() && get("origin").is_none() {
+            errors.push(EntityError::MissingOrigin(classname.to_string()));
+        }
+        let keys = self.keys(class);
+        for prop in entity.props.iter() {
+            let (key, value) = (prop.key.as_ref(), prop.value.as_ref());
+            if ["classname", "origin", "id", "hammerid"]
+                .iter()
+                .any(|k| k.eq_ignore_ascii_case(key))
+            {
+                continue;
+            }
+            match keys.iter().find(|def| def.name.eq_ignore_ascii_case(key)) {
+                Some(def) => errors.extend(def.check(classname, value).err()),
+                None => {
+                    errors.push(EntityError::UnknownKey(classname.to_string(), key.to_string()));
+                }
+            }
+        }
+        errors
+    }
+
+    /// Check all entities of a map. Returns the index of the entity with each error,
+    /// indexes after `map.entities` are `map.brush_entities`.
+    pub fn validate(&self, map: &Map) -> Vec<(usize, EntityError)> {
+        let entities = map.entities.iter().chain(map.brush_entities.iter().map(|e| &e.entity));
+        entities
+            .enumerate()
+            .flat_map(|(i, entity)| self.validate_entity(entity).into_iter().map(move |e| (i, e)))
+            .collect()
+    }
+}
+
+impl Include for Fgd {
+    const FORMAT: &'static str = "FGD";
+
+    fn parse(input: &str) -> io::Result<Self> {
+        Self::parse(input)
+    }
+
+    /// Included classes come first, so this file's classes override them.
+    fn include(self, load: &mut dyn FnMut(&str) -> io::Result<Self>) -> io::Result<Self> {
+        let mut fgd = Self::default();
+        for include in self.includes.iter() {
+            fgd.extend(load(include)?);
+        }
+        fgd.includes.extend(self.includes);
+        for class in self.classes {
+            fgd.add_class(class);
+        }
+        Ok(fgd)
+    }
+}
+
+/// A problem with an [`Entity`] found by [`Fgd::validate()`].
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EntityError {
+    MissingClassname,
+    /// Classname not in the FGD, or is a base class.
+    UnknownClass(String),
+    /// Point entity without an origin. (classname)
+    MissingOrigin(String),
+    /// (classname, key)
+    UnknownKey(String, String),
+    /// Value isn't the type of the key. (classname, key, value)
+    WrongType(String, String, String),
+    /// Value isn't one of the choices or has unknown flags. (classname, key, value)
+    BadChoice(String, String, String),
+}
+
+impl Display for EntityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingClassname => write!(f, "missing classname"),
+            Self::UnknownClass(class) => write!(f, "unknown class \"{class}\""),
+            Self::MissingOrigin(class) => write!(f, "{class}: missing origin"),
+            Self::UnknownKey(class, key) => write!(f, "{class}: unknown key \"{key}\""),
+            Self::WrongType(class, key, value) => {
+                write!(f, "{class}: wrong type for \"{key}\": \"{value}\"")
+            }
+            Self::BadChoice(class, key, value) => {
+                write!(f, "{class}: invalid choice for \"{key}\": \"{value}\"")
+            }
+        }
+    }
+}
+
+impl std::error::Error for EntityError {}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    /// An identifier or number. (word, line)
+    Word(String, usize),
+    /// A quoted string. (string, line)
+    Str(String, usize),
+    /// One of `@()[]=:,+`. (char, line)
+    Punct(char, usize),
+}
+
+impl text::Token for Token {
+    const FORMAT: &'static str = "FGD";
+
+    fn line(&self) -> usize {
+        match self {
+            Self::Word(_, line) | Self::Str(_, line) | Self::Punct(_, line) => *line,
+        }
+    }
+}
+
+const PUNCTUATION: &str = "@()[]=:,+";
+
+fn tokenize(input: &str) -> io::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = Scanner::new(input, Token::FORMAT);
+    while let Some(char) = chars.start() {
+        let line = chars.line;
+        match char {
+            '"' => tokens.push(Token::Str(chars.string()?, line)),
+            c if PUNCTUATION.contains(c) => tokens.push(Token::Punct(c, line)),
+            c => {
+                let mut word = c.to_string();
+                while let Some(c) =
+                    chars.next_if(|&c| !c.is_whitespace() && !PUNCTUATION.contains(c) && c != '"')
+                {
+                    word.push(c);
+                }
+                tokens.push(Token::Word(word, line));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+type Parser = Cursor<Token>;
+
+impl Parser {
+    fn peek_punct(&self, punct: char) -> bool {
+        matches!(self.peek(), Some(Token::Punct(c, _)) if *c == punct)
+    }
+
+    fn expect(&mut self, punct: char) -> io::Result<()> {
+        match self.next() {
+            Some(Token::Punct(c, _)) if c == punct => Ok(()),
+            Some(token) => Err(self.error(&token, &format!("expected '{punct}'"))),
+            None => Err(self.eof()),
+        }
+    }
+
+    fn word(&mut self) -> io::Result<String> {
+        match self.next() {
+            Some(Token::Word(word, _)) => Ok(word),
+            Some(token) => Err(self.error(&token, "expected a name")),
+            None => Err(self.eof()),
+        }
+    }
+
+    /// A string, concatenated with `+`.
+    fn string(&mut self) -> io::Result<String> {
+        let mut string = match self.next() {
+            Some(Token::Str(string, _)) => string,
+            Some(token) => return Err(self.error(&token, "expected a string")),
+            None => return Err(self.eof()),
+        };
+        while self.peek_punct('+') {
+            self.skip();
+            string += &self.string()?;
+        }
+        Ok(string)
+    }
+
+    /// A string or a word (ex: a number) or nothing, for defaults.
+    fn value(&mut self) -> io::Result<Option<String>> {
+        match self.peek() {
+            Some(Token::Str(..)) => self.string().map(Some),
+            Some(Token::Word(..)) => self.word().map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// `: value` if there is a `:`.
+    fn field(&mut self) -> io::Result<Option<String>> {
+        if !self.peek_punct(':') {
+            return Ok(None);
+        }
+        self.skip();
+        self.value()
+    }
+
+    /// The raw tokens between `(` and the matching `)`, space separated.
+    fn parens(&mut self) -> io::Result<String> {
+        self.expect('(')?;
+        let mut depth = 1;
+        let mut raw = Vec::new();
+        loop {
+            match self.next().ok_or_else(|| self.eof())? {
+                Token::Punct('(', _) => depth += 1,
+                Token::Punct(')', _) if depth == 1 => break,
+                Token::Punct(')', _) => depth -= 1,
+                Token::Punct(c, _) => raw.push(c.to_string()),
+                Token::Word(word, _) => raw.push(word),
+                Token::Str(string, _) => raw.push(format!("\"{string}\"")),
+            }
+        }
+        Ok(raw.join(" "))
+    }
+
+    /// Skip an unsupported directive up to and including its `[ ]` block, if any.
+    fn skip_directive(&mut self) {
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Punct('@', _) => return,
+                Token::Punct('[', _) => {
+                    let mut depth = 0;
+                    while let Some(token) = self.next() {
+                        match token {
+                            Token::Punct('[', _) => depth += 1,
+                            Token::Punct(']', _) if depth == 1 => return,
+                            Token::Punct(']', _) => depth -= 1,
+                            _ => (),
+                        }
+                    }
+                    return;
+                }
+                _ => self.skip(),
+            }
+        }
+    }
+
+    fn class(&mut self, kind: FgdClassKind) -> io::Result<FgdClass> {
+        let mut bases = Vec::new();
+        let mut helpers = Vec::new();
+        while !self.peek_punct('=') {
+            let helper = self.word()?;
+            let args = match self.peek_punct('(') {
+                true => self.parens()?,
+                false => String::new(),
+            };
+            if helper.eq_ignore_ascii_case("base") {
+                bases.extend(args.split(',').map(|base| base.trim().to_string()));
+            } else {
+                helpers.push((helper, args));
+            }
+        }
+        self.expect('=')?;
+        let name = self.word()?;
+        let description = self.field()?.unwrap_or_default();
+
+        let mut class = FgdClass {
+            kind,
+            name,
+            description,
+            bases,
+            helpers,
+            keys: Vec::new(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        };
+        self.expect('[')?;
+        while !self.peek_punct(']') {
+            let name = self.word()?;
+            match name.to_ascii_lowercase().as_str() {
+                io @ ("input" | "output") => {
+                    let io = match io {
+                        "input" => &mut class.inputs,
+                        _ => &mut class.outputs,
+                    };
+                    let name = self.word()?;
+                    let param_type = self.parens()?;
+                    let description = self.field()?.unwrap_or_default();
+                    io.push(FgdIo { name, param_type, description });
+                }
+                _ => class.keys.push(self.key(name)?),
+            }
+        }
+        self.expect(']')?;
+        Ok(class)
+    }
+
+    fn key(&mut self, name: String) -> io::Result<FgdKey> {
+        let value_type = FgdValueType::parse(&self.parens()?);
+        let mut read_only = false;
+        // a bare `key(type)` is followed by the next key's name
+        while let Some(Token::Word(word, _)) = self.peek() {
+            if !matches!(word.to_ascii_lowercase().as_str(), "readonly" | "report") {
+                break;
+            }
+            read_only = true;
+            self.skip();
+        }
+        let display_name = self.field()?.unwrap_or_default();
+        let default = self.field()?;
+        let description = self.field()?.unwrap_or_default();
+
+        let mut choices = Vec::new();
+        if self.peek_punct('=') {
+            self.skip();
+            self.expect('[')?;
+            while !self.peek_punct(']') {
+                let value = self.value()?.ok_or_else(|| match self.peek() {
+                    Some(token) => self.error(token, "expected a choice"),
+                    None => self.eof(),
+                })?;
+                let display_name = self.field()?.unwrap_or_default();
+                let default = self.field()?.is_some_and(|default| default == "1");
+                choices.push(FgdChoice { value, display_name, default });
+            }
+            self.expect(']')?;
+        }
+
+        Ok(FgdKey { name, value_type, display_name, default, description, read_only, choices })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::BrushEntity;
+    use vmf_parser_nom::ast::Property;
+
+    const FGD: &str = r#"
+// comment
+@mapsize(-16384, 16384)
+
+@BaseClass = Targetname
+[
+    targetname(target_source) : "Name" : : "The name that other entities refer to " +
+        "this entity by."
+    input Kill(void) : "Removes this entity from the world."
+    output OnUser1(void) : "Fired in response to FireUser1 input."
+]
+
+@BaseClass base(Targetname) = Light
+[
+    _light(color255) : "Brightness" : "255 255 255 200"
+    _lightHDR(color255) : "Brightness HDR" : "-1 -1 -1 1"
+    style(choices) : "Appearance" : 0 =
+    [
+        0 : "Normal"
+        10: "Fluorescent flicker"
+    ]
+]
+
+@PointClass base(Light) iconsprite("editor/light.vmt") sphere(_distance) = light : "An invisible light source."
+[
+    spawnflags(flags) =
+    [
+        1 : "Initially dark" : 0
+    ]
+    _distance(integer) readonly : "Maximum distance" : 0
+    input TurnOn(void) : "Turn the light on."
+]
+
+@SolidClass = func_detail : "Detail brushes." []
+
+@MaterialExclusion
+[
+    "debug"
+]
+"#;
+
+    fn entity(props: &[(&'static str, &'static str)]) -> Entity<&'static str> {
+        Entity::new(props.iter().map(|&(key, value)| Property::new(key, value)).collect())
+    }
+
+    #[test]
+    fn parse() {
+        let fgd = Fgd::parse(FGD).unwrap();
+        assert_eq!(4, fgd.classes.len());
+
+        let light = fgd.class("light").unwrap();
+        assert_eq!(FgdClassKind::Base, fgd.class("Light").unwrap().kind);
+        assert_eq!("func_detail", fgd.class("FUNC_DETAIL").unwrap().name);
+        assert_eq!(FgdClassKind::Point, light.kind);
+        assert_eq!("An invisible light source.", light.description);
+        assert_eq!(vec!["Light"], light.bases);
+        assert_eq!(("sphere".to_string(), "_distance".to_string()), light.helpers[1]);
+        let distance = &light.keys[1];
+        assert_eq!(FgdValueType::Integer, distance.value_type);
+        assert!(distance.read_only);
+        assert_eq!(Some("0".to_string()), distance.default);
+
+        let keys: Vec<_> = fgd.keys(light).iter().map(|key| key.name.as_str()).collect();
+        assert_eq!(
+            vec!["targetname", "_light", "_lightHDR", "style", "spawnflags", "_distance"],
+            keys
+        );
+        let targetname = fgd.keys(light)[0];
+        assert_eq!(None, targetname.default);
+        assert_eq!("The name that other entities refer to this entity by.", targetname.description);
+        let style = fgd.keys(light)[3];
+        assert_eq!("Fluorescent flicker", style.choices[1].display_name);
+
+        let inputs: Vec<_> = fgd.inputs(light).iter().map(|io| io.name.as_str()).collect();
+        assert_eq!(vec!["Kill", "TurnOn"], inputs);
+        assert_eq!("OnUser1", fgd.outputs(light)[0].name);
+
+        let bare = Fgd::parse("@PointClass = bare [ a(integer) b(string) c(float) readonly ]");
+        let keys = &bare.unwrap().classes[0].keys;
+        let names: Vec<_> = keys.iter().map(|key| key.name.as_str()).collect();
+        assert_eq!(vec!["a", "b", "c"], names);
+        assert_eq!((false, FgdValueType::String), (keys[1].read_only, keys[1].value_type.clone()));
+        assert!(keys[2].read_only);
+
+        assert!(Fgd::parse("@PointClass = broken [ key(integer) : \"x\" ").is_err());
+    }
+
+    #[test]
+    fn validate() {
+        let fgd = Fgd::parse(FGD).unwrap();
+        let ok = entity(&[
+            ("classname", "light"),
+            ("origin", "0 0 0"),
+            ("style", "10"),
+            ("_lightHDR", "-1 -1 -1 1"),
+        ]);
+        assert_eq!(Vec::<EntityError>::new(), fgd.validate_entity(&ok));
+
+        let errors = fgd.validate_entity(&entity(&[
+            ("classname", "light"),
+            ("_light", "300 0 0 200"),
+            ("_lightHDR", "-1 0 0 1"),
+            ("style", "3"),
+            ("spawnflags", "3"),
+            ("speed", "1"),
+        ]));
+        let light = || "light".to_string();
+        assert_eq!(
+            vec![
+                EntityError::MissingOrigin(light()),
+                EntityError::WrongType(light(), "_light".into(), "300 0 0 200".into()),
+                EntityError::WrongType(light(), "_lightHDR".into(), "-1 0 0 1".into()),
+                EntityError::BadChoice(light(), "style".into(), "3".into()),
+                EntityError::BadChoice(light(), "spawnflags".into(), "3".into()),
+                EntityError::UnknownKey(light(), "speed".into()),
+            ],
+            errors
+        );
+
+        let mut map = Map::default();
+        map.entities.push(Entity::new(vec![Property::new("classname", "Targetname")]));
+        map.brush_entities.push(BrushEntity::new(
+            Entity::new(vec![Property::new("classname", "func_detail")]),
+            vec![],
+        ));
+        assert_eq!(vec![(0, EntityError::UnknownClass("Targetname".into()))], fgd.validate(&map));
+    }
+
+    #[test]
+    fn load_include() {
+        let dir = Path::new("test/test_fgd");
+        _ = std::fs::remove_dir_all(dir);
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(dir.join("base.fgd"), FGD).unwrap();
+        std::fs::write(
+            dir.join("game.fgd"),
+            "@include \"base.fgd\"\n@PointClass base(Targetname) = info_target []",
+        )
+        .unwrap();
+
+        let fgd = Fgd::load(dir.join("game.fgd")).unwrap();
+        assert_eq!(vec!["base.fgd"], fgd.includes);
+        let target = fgd.class("info_target").unwrap();
+        assert_eq!("targetname", fgd.keys(target)[0].name);
+        assert_eq!(5, fgd.classes.len());
+
+        std::fs::write(dir.join("self.fgd"), "@include \"self.fgd\"").unwrap();
+        std::fs::write(dir.join("a.fgd"), "@include \"b.fgd\"").unwrap();
+        std::fs::write(dir.join("b.fgd"), "@include \"a.fgd\"").unwrap();
+        for file in ["self.fgd", "a.fgd"] {
+            let err = Fgd::load(dir.join(file)).unwrap_err();
+            assert_eq!(io::ErrorKind::InvalidData, err.kind(), "{file}");
+        }
+
+        _ = std::fs::remove_dir_all(dir);
+    }
+}
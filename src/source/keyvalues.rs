@@ -0,0 +1,412 @@
+//! Parsing KeyValues1 text, the format of `gameinfo.txt`, `.vmt`s, `.res` files, etc.
+//!
+//! Keys and values are quoted or unquoted strings, values can be a `{ }` block of more
+//! keyvalues. Escape sequences aren't used, so Windows paths keep their backslashes.
+//! A key or value can be followed by a conditional like `[$WIN32]`, see [`eval_condition`].
+//!
+//! See also: <https://developer.valvesoftware.com/wiki/KeyValues>
+
+use std::io;
+use std::path::Path;
+
+use super::text::{self, Cursor, Include, Scanner, Token as _};
+
+/// Conditions that are true on this platform, for [`KeyValues::filtered()`].
+pub const PLATFORM_CONDITIONS: &[&str] = if cfg!(windows) {
+    &["$WIN32", "$WINDOWS"]
+} else if cfg!(target_os = "macos") {
+    &["$OSX", "$POSIX"]
+} else {
+    &["$LINUX", "$POSIX"]
+};
+
+/// A string or a block of keyvalues.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KvValue {
+    Str(String),
+    Block(KeyValues),
+}
+
+impl KvValue {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Str(string) => Some(string),
+            Self::Block(_) => None,
+        }
+    }
+
+    pub const fn as_block(&self) -> Option<&KeyValues> {
+        match self {
+            Self::Str(_) => None,
+            Self::Block(block) => Some(block),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyValue {
+    pub key: String,
+    pub value: KvValue,
+    /// Ex: `$WIN32 && !$X360` from `[$WIN32 && !$X360]`.
+    pub condition: Option<String>,
+}
+
+/// An ordered list of keyvalues, keys can repeat. Ex: `Game` in `SearchPaths`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KeyValues {
+    pub pairs: Vec<KeyValue>,
+    /// Files from `#base`. Already merged if loaded with [`KeyValues::load()`].
+    pub bases: Vec<String>,
+    /// Files from `#include`. Already appended if loaded with [`KeyValues::load()`].
+    pub includes: Vec<String>,
+}
+
+impl KeyValues {
+    /// Load a file and its `#base` and `#include` files, relative to its directory.
+    ///
+    /// `#include`d keyvalues are appended, `#base` keyvalues are only added if the file doesn't
+    /// have them already.
+    ///
+    /// # Errors
+    ///
+    /// * Returns `InvalidData` if a file includes itself, directly or through other files.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        text::load(path.as_ref())
+    }
+
+    /// Parse KeyValues text. `#base` and `#include` files are not loaded.
+    pub fn parse(input: &str) -> io::Result<Self> {
+        let mut parser = Parser::new(tokenize(input)?);
+        let kv = parser.pairs(true)?;
+        match parser.next() {
+            None => Ok(kv),
+            Some(token) => Err(parser.error(&token, "unexpected '}'")),
+        }
+    }
+
+    /// Add keyvalues from `base` with keys this doesn't have, merging blocks with the same key.
+    pub fn merge_base(&mut self, base: Self) {
+        for pair in base.pairs {
+            let existing = self.pairs.iter_mut().find(|p| p.key.eq_ignore_ascii_case(&pair.key));
+            match (existing, pair.value) {
+                (None, value) => self.pairs.push(KeyValue { value, ..pair }),
+                (Some(KeyValue { value: KvValue::Block(block), .. }), KvValue::Block(base)) => {
+                    block.merge_base(base);
+                }
+                (Some(_), _) => (),
+            }
+        }
+    }
+
+    /// The first value of `key`, case insensitive.
+    pub fn get(&self, key: &str) -> Option<&KvValue> {
+        self.pairs
+            .iter()
+            .find(|pair| pair.key.eq_ignore_ascii_case(key))
+            .map(|p| &p.value)
+    }
+
+    /// All values of `key` in order, case insensitive.
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a KvValue> {
+        self.pairs
+            .iter()
+            .filter(move |pair| pair.key.eq_ignore_ascii_case(key))
+            .map(|p| &p.value)
+    }
+
+    /// The first string value of `key`, case insensitive.
+    pub fn str(&self, key: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .filter(|pair| pair.key.eq_ignore_ascii_case(key))
+            .find_map(|pair| pair.value.as_str())
+    }
+
+    /// The first block of `key`, case insensitive.
+    pub fn block(&self, key: &str) -> Option<&KeyValues> {
+        self.pairs
+            .iter()
+            .filter(|pair| pair.key.eq_ignore_ascii_case(key))
+            .find_map(|pair| pair.value.as_block())
+    }
+
+    /// Remove keyvalues with conditions that are false with `defines`, in blocks too.
+    /// Ex: [`PLATFORM_CONDITIONS`].
+    pub fn filtered(&self, defines: &[&str]) -> Self {
+        let pairs = self
+            .pairs
+            .iter()
+            .filter(|pair| pair.condition.as_ref().is_none_or(|c| eval_condition(c, defines)))
+            .map(|pair| KeyValue {
+                value: match &pair.value {
+                    KvValue::Block(block) => KvValue::Block(block.filtered(defines)),
+                    string => string.clone(),
+                },
+                ..pair.clone()
+            })
+            .collect();
+        Self { pairs, ..self.clone() }
+    }
+}
+
+impl Include for KeyValues {
+    const FORMAT: &'static str = "KeyValues";
+
+    fn parse(input: &str) -> io::Result<Self> {
+        Self::parse(input)
+    }
+
+    fn include(mut self, load: &mut dyn FnMut(&str) -> io::Result<Self>) -> io::Result<Self> {
+        for include in self.includes.clone() {
+            self.pairs.extend(load(&include)?.pairs);
+        }
+        for base in self.bases.clone() {
+            self.merge_base(load(&base)?);
+        }
+        Ok(self)
+    }
+}
+
+/// Evaluate a conditional like `$WIN32 || $OSX` or `$POSIX && !$OSX`, true if a term is in
+/// `defines`. `&&` binds tighter than `||`, case insensitive.
+pub fn eval_condition(condition: &str, defines: &[&str]) -> bool {
+    let term = |term: &str| {
+        let term = term.trim();
+        let (not, name) = match term.strip_prefix('!') {
+            Some(name) => (true, name.trim()),
+            None => (false, term),
+        };
+        not != defines.iter().any(|define| define.eq_ignore_ascii_case(name))
+    };
+    condition.split("||").any(|all| all.split("&&").all(term))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    /// A quoted or unquoted string. (string, line)
+    Str(String, usize),
+    /// `{` (line)
+    Open(usize),
+    /// `}` (line)
+    Close(usize),
+    /// Inside `[ ]`. (condition, line)
+    Condition(String, usize),
+}
+
+impl text::Token for Token {
+    const FORMAT: &'static str = "KeyValues";
+
+    fn line(&self) -> usize {
+        match self {
+            Self::Str(_, line) | Self::Open(line) | Self::Close(line) => *line,
+            Self::Condition(_, line) => *line,
+        }
+    }
+}
+
+fn tokenize(input: &str) -> io::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = Scanner::new(input, Token::FORMAT);
+    while let Some(char) = chars.start() {
+        let line = chars.line;
+        match char {
+            '{' => tokens.push(Token::Open(line)),
+            '}' => tokens.push(Token::Close(line)),
+            '"' => tokens.push(Token::Str(chars.string()?, line)),
+            '[' => {
+                let mut condition = String::new();
+                loop {
+                    match chars.next() {
+                        Some(']') => break,
+                        Some('\n') | None => {
+                            return Err(chars.invalid(line, "unterminated conditional"))
+                        }
+                        Some(c) => condition.push(c),
+                    }
+                }
+                tokens.push(Token::Condition(condition.trim().to_string(), line));
+            }
+            c => {
+                let mut string = c.to_string();
+                while let Some(c) = chars.next_if(|&c| !c.is_whitespace() && !"{}\"[".contains(c)) {
+                    string.push(c);
+                }
+                tokens.push(Token::Str(string, line));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+type Parser = Cursor<Token>;
+
+impl Parser {
+    /// A condition if there is one.
+    fn condition(&mut self) -> Option<String> {
+        match self.peek() {
+            Some(Token::Condition(condition, _)) => {
+                let condition = condition.clone();
+                self.skip();
+                Some(condition)
+            }
+            _ => None,
+        }
+    }
+
+    /// Keyvalues up to a `}` or the end of the file at the `root`.
+    fn pairs(&mut self, root: bool) -> io::Result<KeyValues> {
+        let mut kv = KeyValues::default();
+        loop {
+            let key = match self.peek() {
+                Some(Token::Close(_)) if !root => {
+                    self.skip();
+                    return Ok(kv);
+                }
+                Some(Token::Close(_)) | None if root => return Ok(kv),
+                None => return Err(self.eof()),
+                _ => match self.next() {
+                    Some(Token::Str(key, _)) => key,
+                    Some(token) => return Err(self.error(&token, "expected a key")),
+                    None => return Err(self.eof()),
+                },
+            };
+            let mut condition = self.condition();
+            let value = match self.next() {
+                Some(Token::Str(value, _)) => KvValue::Str(value),
+                Some(Token::Open(_)) => KvValue::Block(self.pairs(false)?),
+                Some(token) => return Err(self.error(&token, "expected a value or '{'")),
+                None => return Err(self.eof()),
+            };
+            condition = condition.or_else(|| self.condition());
+
+            match (key.to_ascii_lowercase().as_str(), value) {
+                ("#base", KvValue::Str(file)) if root => kv.bases.push(file),
+                ("#include", KvValue::Str(file)) if root => kv.includes.push(file),
+                (_, value) => kv.pairs.push(KeyValue { key, value, condition }),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAMEINFO: &str = r#"
+// comment
+"GameInfo"
+{
+    game        "Team Fortress 2"
+    type multiplayer_only
+    icon "resource/game" [$WIN32]
+    icon "resource/game_osx" [$OSX]
+    FileSystem
+    {
+        SteamAppId 440 // trailing comment
+        SearchPaths
+        {
+            game+mod+custom_mod     tf/custom/*
+            game_lv                 tf/tf2_lv.vpk
+            game+mod                tf/tf2_textures.vpk
+            game+game_write+mod+mod_write+default_write_path |gameinfo_path|.
+            game                    |all_source_engine_paths|hl2
+        }
+    }
+    "multi
+line" "}"
+}
+"#;
+
+    #[test]
+    fn parse() {
+        let kv = KeyValues::parse(GAMEINFO).unwrap();
+        assert_eq!(1, kv.pairs.len());
+        let info = kv.block("gameinfo").unwrap();
+        assert_eq!(Some("Team Fortress 2"), info.str("GAME"));
+        assert_eq!(Some("multiplayer_only"), info.str("type"));
+        assert_eq!(Some("}"), info.str("multi\nline"));
+
+        let icons: Vec<_> = info.pairs.iter().filter(|p| p.key == "icon").collect();
+        assert_eq!(Some("$WIN32"), icons[0].condition.as_deref());
+        let windows = info.filtered(&["$WIN32"]);
+        assert_eq!(
+            vec![Some("resource/game")],
+            windows.get_all("icon").map(KvValue::as_str).collect::<Vec<_>>()
+        );
+        assert_eq!(Some("resource/game_osx"), info.filtered(&["$OSX", "$POSIX"]).str("icon"));
+        assert_eq!(None, info.filtered(&[]).str("icon"));
+
+        let file_system = info.block("filesystem").unwrap();
+        assert_eq!(Some("440"), file_system.str("steamappid"));
+        let search_paths = file_system.block("searchpaths").unwrap();
+        assert_eq!(5, search_paths.pairs.len());
+        assert_eq!("game+mod+custom_mod", search_paths.pairs[0].key);
+        assert_eq!(Some("|gameinfo_path|."), search_paths.pairs[3].value.as_str());
+
+        assert!(KeyValues::parse("a { b c").is_err());
+        assert!(KeyValues::parse("a b }").is_err());
+        assert!(KeyValues::parse("a \"b").is_err());
+        assert!(KeyValues::parse("a").is_err());
+        let error = KeyValues::parse("a\n{\n{").unwrap_err();
+        assert!(error.to_string().starts_with("KeyValues line 3"), "{error}");
+    }
+
+    #[test]
+    fn conditions() {
+        assert!(eval_condition("$WIN32", &["$WIN32"]));
+        assert!(eval_condition("$win32", &["$WIN32"]));
+        assert!(!eval_condition("!$WIN32", &["$WIN32"]));
+        assert!(eval_condition("$WIN32 || $OSX", &["$OSX"]));
+        assert!(eval_condition("$POSIX && !$OSX", &["$LINUX", "$POSIX"]));
+        assert!(!eval_condition("$POSIX && !$OSX", &["$OSX", "$POSIX"]));
+        assert!(eval_condition("$X360 || $POSIX && !$OSX", &["$POSIX"]));
+
+        let kv = KeyValues::parse("a [!$X360] { b 1 c 2 [$X360] }").unwrap();
+        let filtered = kv.filtered(PLATFORM_CONDITIONS);
+        assert_eq!(Some("1"), filtered.block("a").unwrap().str("b"));
+        assert_eq!(None, filtered.block("a").unwrap().str("c"));
+    }
+
+    #[test]
+    fn base() {
+        let dir = Path::new("test/test_keyvalues");
+        _ = std::fs::remove_dir_all(dir);
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("sub/base.res"), "root { a base b base c { d base } }").unwrap();
+        std::fs::write(dir.join("extra.res"), "extra { e 1 }").unwrap();
+        std::fs::write(
+            dir.join("main.res"),
+            "#base \"sub/base.res\"\n#include extra.res\nroot { a main c { f main } }",
+        )
+        .unwrap();
+
+        let kv = KeyValues::load(dir.join("main.res")).unwrap();
+        assert_eq!(vec!["sub/base.res"], kv.bases);
+        assert_eq!(
+            vec!["root", "extra"],
+            kv.pairs.iter().map(|p| p.key.as_str()).collect::<Vec<_>>()
+        );
+        let root = kv.block("root").unwrap();
+        assert_eq!(Some("main"), root.str("a"));
+        assert_eq!(Some("base"), root.str("b"));
+        let c = root.block("c").unwrap();
+        assert_eq!((Some("main"), Some("base")), (c.str("f"), c.str("d")));
+        assert_eq!(Some("1"), kv.block("extra").unwrap().str("e"));
+
+        // included twice, but not a cycle
+        std::fs::write(dir.join("twice.res"), "#include extra.res\n#base sub/../extra.res")
+            .unwrap();
+        let twice = KeyValues::load(dir.join("twice.res")).unwrap();
+        assert_eq!(Some("1"), twice.block("extra").unwrap().str("e"));
+
+        std::fs::write(dir.join("self.res"), "#base self.res\nroot { a 1 }").unwrap();
+        std::fs::write(dir.join("sub/a.res"), "#include ../b.res").unwrap();
+        std::fs::write(dir.join("b.res"), "#base sub/a.res").unwrap();
+        for file in ["self.res", "b.res", "sub/a.res"] {
+            let err = KeyValues::load(dir.join(file)).unwrap_err();
+            assert_eq!(io::ErrorKind::InvalidData, err.kind(), "{file}");
+        }
+
+        _ = std::fs::remove_dir_all(dir);
+    }
+}
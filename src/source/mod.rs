@@ -2,12 +2,27 @@
 //!
 //! See also [`crate::map`]
 
+pub(crate) mod catalogue;
+pub(crate) mod codegen;
 pub(crate) mod entity;
+pub(crate) mod fgd;
+pub(crate) mod keyvalues;
+pub(crate) mod text;
 
+pub use catalogue::*;
+pub use codegen::*;
 pub use entity::*;
+pub use fgd::*;
+pub use keyvalues::*;
 
-use crate::{light::GlobalLighting, map::Angles};
-use std::fmt::Display;
+use crate::{
+    light::GlobalLighting,
+    map::{Angles, Entity},
+    prelude::Vector3,
+    vmf::ToLower,
+};
+use rgb::{ComponentMap, RGB};
+use std::{fmt::Display, str::FromStr};
 
 #[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Ord, Hash)]
 /// RGB with brightness
@@ -22,8 +37,35 @@ impl ColorBrightness {
     pub const fn new(r: u8, g: u8, b: u8, a: i32) -> Self {
         Self { r, g, b, a }
     }
+
+    /// Linear intensity, how vrad reads `_light` and `_lightHDR`.
+    /// Colors have a gamma of 2.2 and are scaled by `brightness / 255`.
+    pub fn to_linear(&self) -> RGB<f64> {
+        let linear = |c: u8| (c as f64 / 255.0).powf(GAMMA) * self.a as f64;
+        RGB::new(linear(self.r), linear(self.g), linear(self.b))
+    }
+
+    /// Inverse of [`Self::to_linear`], with brightness set to the brightest channel.
+    /// Negative channels are 0.
+    pub fn from_linear(linear: RGB<f64>) -> Self {
+        let linear = linear.map(|c| c.max(0.0));
+        let max = linear.r.max(linear.g).max(linear.b);
+        if max == 0.0 {
+            return Self::new(0, 0, 0, 0);
+        }
+        let color = |c: f64| ((c / max).powf(GAMMA.recip()) * 255.0).round() as u8;
+        Self::new(color(linear.r), color(linear.g), color(linear.b), max.round() as i32)
+    }
+
+    /// A `_lightHDR` value that gives `linear` with a `_lightscaleHDR` of `scale`.
+    pub fn from_linear_hdr(linear: RGB<f64>, scale: f64) -> Self {
+        Self::from_linear(linear.map(|c| c / scale))
+    }
 }
 
+/// Gamma vrad uses for light colors.
+const GAMMA: f64 = 2.2;
+
 impl Display for ColorBrightness {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let ColorBrightness { r, g, b, a } = *self;
@@ -31,16 +73,118 @@ impl Display for ColorBrightness {
     }
 }
 
+/// Parse `"r g b brightness"`. Brightness is 255 if missing.
+impl FromStr for ColorBrightness {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<_> = s.split_whitespace().collect();
+        let (rgb, a) = match parts[..] {
+            [r, g, b] => ([r, g, b], "255"),
+            [r, g, b, a] => ([r, g, b], a),
+            _ => return Err("Expected 3 or 4 numbers"),
+        };
+        let [r, g, b] = rgb.map(|c| c.parse::<u8>().map_err(|_| "Invalid color"));
+        // hammer sometimes writes brightness as a float
+        let a = a.parse::<f64>().map_err(|_| "Invalid brightness")?;
+        Ok(Self::new(r?, g?, b?, a as i32))
+    }
+}
+
 /// All information for global lighting, shadowing and post processing fog, etc.
+///
+/// Lowers to a `light_environment`, a `light_directional` per extra light, a `shadow_control`
+/// and optionally an `env_fog_controller` and `env_sun`, all facing the same way as the sun.
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct GlobalLightShadows<S> {
     pub light: GlobalLighting,
+    /// Angles are set to the sun's.
     pub shadows: ShadowControl<S>,
-    pub fog: EnvFogController<S>,
+    /// Angles and blend direction are set to face the sun. `None` for no fog.
+    pub fog: Option<EnvFogController<S>>,
+    /// Angles are set to the sun's. `None` for no sun sprite.
+    pub sun: Option<EnvSun<S>>,
+}
+
+impl<S> GlobalLightShadows<S> {
+    /// Classnames of all entities this lowers to.
+    pub const CLASSNAMES: [&'static str; 5] = [
+        "light_environment",
+        "light_directional",
+        "shadow_control",
+        "env_fog_controller",
+        "env_sun",
+    ];
+}
+
+impl<'a, S> ToLower<Vec<Entity<S>>> for GlobalLightShadows<S>
+where
+    S: AsRef<str> + Clone + Default + From<&'a str> + From<String>,
+{
+    fn into_lower(self) -> Vec<Entity<S>> {
+        let GlobalLightShadows { light, mut shadows, fog, sun } = self;
+        let sun_dir = &light.sun_dir;
+        let mut entities = Vec::with_capacity(4 + light.dir_lights.len());
+
+        entities.push(light.light_environment::<S>().into_lower());
+        entities.extend(light.light_directionals::<S>().into_iter().map(ToLower::into_lower));
+
+        shadows.point_entity.angles = sun_dir.clone();
+        entities.push(shadows.into_lower());
+
+        if let Some(mut fog) = fog {
+            // primary color when looking at the sun
+            fog.point_entity.angles = sun_dir.reversed();
+            let Vector3 { x, y, z } = sun_dir.reversed().forward();
+            // + 0.0 for no -0
+            let round = |n: f64| (n * 1e4).round() / 1e4 + 0.0;
+            fog.primary_blend_dir = Vector3::new(round(x), round(y), round(z));
+            entities.push(fog.into_lower());
+        }
+        if let Some(mut sun) = sun {
+            sun.point_entity.angles = sun_dir.clone();
+            sun.use_angles = true;
+            sun.pitch = None;
+            entities.push(sun.into_lower());
+        }
+        entities
+    }
 }
 
-// impl<'a> ToLower<'a, [PointEntity; 3]> for GlobalLightShadows {
-//     fn into_lower(self) -> [PointEntity; 3] {
-//         todo!()
-//     }
-// }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Map;
+    use crate::StrType;
+
+    #[test]
+    fn global_lighting() {
+        let lighting = GlobalLightShadows::<StrType> {
+            light: GlobalLighting {
+                sun_dir: Angles { pitch: 45.0, yaw: 45.0, roll: 0.0 },
+                dir_lights: vec![(ColorBrightness::new(255, 0, 0, 20), Angles::default())],
+                ..Default::default()
+            },
+            fog: Some(EnvFogController::default()),
+            sun: Some(EnvSun::default()),
+            ..Default::default()
+        };
+        let entities: Vec<Entity<StrType>> = lighting.clone().into_lower();
+        let classnames: Vec<_> = entities.iter().filter_map(Entity::classname).collect();
+        assert_eq!(GlobalLightShadows::<StrType>::CLASSNAMES.to_vec(), classnames);
+
+        let light = LightEnviroment::try_from(&entities[0]).unwrap();
+        assert_eq!(Some(Angles { pitch: 45.0, yaw: 45.0, roll: 0.0 }), entities[0].angles());
+        assert_eq!(Some(&"-45".into()), entities[0].get("pitch"));
+        assert_eq!(lighting.light.sun_color, light.direct_color);
+        assert_eq!(entities[0].angles(), entities[2].angles());
+        assert_eq!(entities[0].angles(), entities[4].angles());
+        assert_eq!(Some(&"-0.5 -0.5 0.7071".into()), entities[3].get("fogdir"));
+
+        let mut map = Map::default();
+        map.defaults_l4d2();
+        assert_eq!(2, map.entities.len());
+        map.set_global_lighting(lighting);
+        assert_eq!(5, map.entities.len());
+    }
+}
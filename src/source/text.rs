@@ -0,0 +1,145 @@
+//! Tokenizing, parsing and include loading shared by the [`super::fgd`] and
+//! [`super::keyvalues`] text formats.
+
+use std::fmt::Debug;
+use std::io;
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::str::Chars;
+
+/// `InvalidData` with the format and line, ex: `FGD line 3: unterminated string`.
+fn invalid(format: &str, line: usize, msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{format} line {line}: {msg}"))
+}
+
+/// Chars of the input, counting lines.
+pub(crate) struct Scanner<'a> {
+    chars: Peekable<Chars<'a>>,
+    format: &'static str,
+    pub line: usize,
+}
+
+impl<'a> Scanner<'a> {
+    pub fn new(input: &'a str, format: &'static str) -> Self {
+        Self { chars: input.chars().peekable(), format, line: 1 }
+    }
+
+    /// The first char of the next token, after whitespace and `//` comments.
+    pub fn start(&mut self) -> Option<char> {
+        loop {
+            match self.next()? {
+                '/' if self.chars.peek() == Some(&'/') => {
+                    while self.chars.next_if(|&c| c != '\n').is_some() {}
+                }
+                c if c.is_whitespace() => (),
+                c => return Some(c),
+            }
+        }
+    }
+
+    pub fn next(&mut self) -> Option<char> {
+        let char = self.chars.next();
+        self.line += (char == Some('\n')) as usize;
+        char
+    }
+
+    pub fn next_if(&mut self, f: impl FnOnce(&char) -> bool) -> Option<char> {
+        match self.chars.peek() {
+            Some(c) if f(c) => self.next(),
+            _ => None,
+        }
+    }
+
+    /// The rest of a quoted string, after its opening `"`.
+    pub fn string(&mut self) -> io::Result<String> {
+        let start = self.line;
+        let mut string = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(string),
+                Some(c) => string.push(c),
+                None => return Err(self.invalid(start, "unterminated string")),
+            }
+        }
+    }
+
+    pub fn invalid(&self, line: usize, msg: &str) -> io::Error {
+        invalid(self.format, line, msg)
+    }
+}
+
+/// A token of a text format.
+pub(crate) trait Token: Clone + Debug {
+    /// Name of the format in errors, ex: `FGD`.
+    const FORMAT: &'static str;
+
+    fn line(&self) -> usize;
+}
+
+/// Tokens being parsed, format specific parsing is added with `impl Cursor<Token>`.
+pub(crate) struct Cursor<T> {
+    tokens: Vec<T>,
+    index: usize,
+}
+
+impl<T: Token> Cursor<T> {
+    pub const fn new(tokens: Vec<T>) -> Self {
+        Self { tokens, index: 0 }
+    }
+
+    pub fn next(&mut self) -> Option<T> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        self.tokens.get(self.index)
+    }
+
+    /// Skip the peeked token.
+    pub const fn skip(&mut self) {
+        self.index += 1;
+    }
+
+    pub fn error(&self, token: &T, msg: &str) -> io::Error {
+        invalid(T::FORMAT, token.line(), &format!("{msg}, found {token:?}"))
+    }
+
+    pub fn eof(&self) -> io::Error {
+        let line = self.tokens.last().map_or(1, T::line);
+        invalid(T::FORMAT, line, "unexpected end of file")
+    }
+}
+
+/// A text format with files that include other files, relative to their directory.
+pub(crate) trait Include: Sized {
+    /// Name of the format in errors, ex: `FGD`.
+    const FORMAT: &'static str;
+
+    /// Parse one file, its includes are not loaded.
+    fn parse(input: &str) -> io::Result<Self>;
+
+    /// Merge in the files this includes, loaded with `load`.
+    fn include(self, load: &mut dyn FnMut(&str) -> io::Result<Self>) -> io::Result<Self>;
+}
+
+/// Load a file and its includes, `InvalidData` if they include each other in a cycle.
+pub(crate) fn load<T: Include>(path: &Path) -> io::Result<T> {
+    load_nested(path, &mut Vec::new())
+}
+
+/// `loading` is the chain of files being loaded, to catch include cycles.
+fn load_nested<T: Include>(path: &Path, loading: &mut Vec<PathBuf>) -> io::Result<T> {
+    let path = path.canonicalize()?;
+    if loading.contains(&path) {
+        let msg = format!("{} `{}` includes itself", T::FORMAT, path.display());
+        return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+    }
+    let file = T::parse(&std::fs::read_to_string(&path)?)?;
+    let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    loading.push(path);
+    let loaded = file.include(&mut |include| load_nested(&dir.join(include), loading));
+    loading.pop();
+    loaded
+}
@@ -8,7 +8,7 @@ use crate::utils::Vec2d;
 use crate::utils::{NextChunk, TryMap};
 use crate::{
     generation::region::Room,
-    map::{DispInfo, Entity, Map, Side, Solid},
+    map::{BrushEntity, DispInfo, Entity, Map, Side, Solid},
     prelude::{Plane, Vector3},
     StrType,
 };
@@ -95,6 +95,7 @@ impl<'a> ToLower<Vmf<StrType<'a>>> for Map<'a> {
         });
         // ENTS HERE
         vmf.inner.blocks.extend(self.entities.iter().map(|e| e.to_lower()));
+        vmf.inner.blocks.extend(self.brush_entities.iter().map(|e| e.to_lower()));
         // cameras unnessesary
         // cordons unnessesary
 
@@ -179,11 +180,22 @@ impl<'a> ToLower<Block<StrType<'a>>> for Entity<StrType<'a>> {
             // name: self.classname.into(),
             name: "entity".into(),
             props: self.props,
-            blocks: vec![],
+            blocks: match self.connections.is_empty() {
+                true => vec![],
+                false => vec![Block::new("connections", self.connections, vec![])],
+            },
         }
     }
 }
 
+impl<'a> ToLower<Block<StrType<'a>>> for BrushEntity<'a> {
+    fn into_lower(self) -> Block<StrType<'a>> {
+        let mut block = self.entity.into_lower();
+        block.blocks.extend(self.solids.iter().map(|s| s.to_lower()));
+        block
+    }
+}
+
 impl<'a, T: Clone + Display> ToLower<Vec<Property<StrType<'a>, StrType<'a>>>> for Vec2d<T> {
     /// https://developer.valvesoftware.com/wiki/.vmf#Normals
     fn to_lower(&self) -> Vec<Property<StrType<'a>, StrType<'a>>> {